@@ -0,0 +1,56 @@
+;; A source plugin that emits three events, reports that no event is ready on
+;; the fourth poll, and is done from then on.
+;;
+;; The canonical ABI return area of `poll` is at offset 0:
+;;
+;;   0   poll-result discriminant (0 = ready, 1 = pending, 2 = done)
+;;   8   key option discriminant, 12 key pointer, 16 key length
+;;   20  metadata list pointer, 24 metadata list length
+;;   28  payload pointer, 32 payload length
+;;   40  timestamp option discriminant, 48 timestamp
+;;
+;; Event N (starting at 1) carries the single byte payload `N`, a key equal to
+;; its payload and a timestamp of N seconds, except for the first event which
+;; has neither. The third event carries the `source: wat` metadata entry.
+(module
+  (memory (export "memory") 1)
+  (global $count (mut i32) (i32.const 0))
+  (data (i32.const 64) "123")
+  (data (i32.const 96) "source")
+  (data (i32.const 104) "wat")
+  (data (i32.const 128) "\60\00\00\00\06\00\00\00\68\00\00\00\03\00\00\00")
+  (func (export "poll") (result i32)
+    (global.set $count (i32.add (global.get $count) (i32.const 1)))
+    (if (i32.le_u (global.get $count) (i32.const 3))
+      (then
+        ;; ready(event)
+        (i32.store8 (i32.const 0) (i32.const 0))
+        ;; key
+        (if (i32.eq (global.get $count) (i32.const 1))
+          (then (i32.store8 (i32.const 8) (i32.const 0)))
+          (else
+            (i32.store8 (i32.const 8) (i32.const 1))
+            (i32.store (i32.const 12) (i32.add (i32.const 63) (global.get $count)))
+            (i32.store (i32.const 16) (i32.const 1))))
+        ;; metadata
+        (i32.store (i32.const 20) (i32.const 128))
+        (i32.store (i32.const 24) (i32.eq (global.get $count) (i32.const 3)))
+        ;; payload
+        (i32.store (i32.const 28) (i32.add (i32.const 63) (global.get $count)))
+        (i32.store (i32.const 32) (i32.const 1))
+        ;; timestamp
+        (if (i32.eq (global.get $count) (i32.const 1))
+          (then (i32.store8 (i32.const 40) (i32.const 0)))
+          (else
+            (i32.store8 (i32.const 40) (i32.const 1))
+            (i64.store (i32.const 48) (i64.mul (i64.extend_i32_u (global.get $count)) (i64.const 1000)))))
+        (return (i32.const 0))))
+    ;; pending
+    (if (i32.eq (global.get $count) (i32.const 4))
+      (then
+        (i32.store8 (i32.const 0) (i32.const 1))
+        (return (i32.const 0))))
+    ;; done
+    (i32.store8 (i32.const 0) (i32.const 2))
+    (i32.const 0))
+)
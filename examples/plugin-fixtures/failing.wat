@@ -0,0 +1,6 @@
+;; A source plugin that traps whenever it is polled
+(module
+  (memory (export "memory") 1)
+  (func (export "poll") (result i32)
+    unreachable)
+)
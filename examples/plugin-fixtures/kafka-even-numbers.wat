@@ -0,0 +1,61 @@
+;; An intercept plugin that discards Kafka events whose payload is an odd
+;; decimal number, and forwards every other event.
+;;
+;; The canonical ABI passes the context to `intercept` through a pointer, since
+;; it has too many fields to be passed as arguments. It is laid out as:
+;;
+;;   0   auth option discriminant, 4 auth pointer, 8 auth length
+;;   12  connection-ctx
+;;   32  event-ctx discriminant (0 = kafka)
+;;   40  kafka-event-ctx, whose payload is at:
+;;         52 payload option discriminant, 56 payload pointer, 60 payload length
+;;
+;; The returned action is written to the return area at offset 0, which only
+;; holds its discriminant (0 = forward, 1 = discard) for these actions.
+(module
+  (memory (export "memory") 1)
+  ;; Kiwi allocates the context after the return area. Every call is made on a
+  ;; fresh instance, so allocations are never freed
+  (global $heap (mut i32) (i32.const 32))
+  (func (export "cabi_realloc")
+    (param $ptr i32) (param $old_size i32) (param $align i32) (param $new_size i32)
+    (result i32)
+    (local $start i32)
+    (local.set $start
+      (i32.and
+        (i32.add (global.get $heap) (i32.sub (local.get $align) (i32.const 1)))
+        (i32.sub (i32.const 0) (local.get $align))))
+    (global.set $heap (i32.add (local.get $start) (local.get $new_size)))
+    (if (i32.gt_u (global.get $heap) (i32.mul (memory.size) (i32.const 65536)))
+      (then
+        (drop
+          (memory.grow
+            (i32.div_u
+              (i32.add
+                (i32.sub (global.get $heap) (i32.mul (memory.size) (i32.const 65536)))
+                (i32.const 65535))
+              (i32.const 65536))))))
+    (local.get $start))
+  (func (export "intercept") (param $ctx i32) (result i32)
+    (local $len i32)
+    ;; forward
+    (i32.store8 (i32.const 0) (i32.const 0))
+    (if (i32.and
+          (i32.eqz (i32.load8_u offset=32 (local.get $ctx)))
+          (i32.load8_u offset=52 (local.get $ctx)))
+      (then
+        (local.set $len (i32.load offset=60 (local.get $ctx)))
+        (if (local.get $len)
+          (then
+            ;; Decimal digits share the parity of their ASCII code
+            (if (i32.and
+                  (i32.load8_u
+                    (i32.add
+                      (i32.load offset=56 (local.get $ctx))
+                      (i32.sub (local.get $len) (i32.const 1))))
+                  (i32.const 1))
+              (then
+                ;; discard
+                (i32.store8 (i32.const 0) (i32.const 1))))))))
+    (i32.const 0))
+)
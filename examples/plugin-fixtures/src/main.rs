@@ -0,0 +1,40 @@
+use std::path::Path;
+
+use anyhow::Context;
+use wit_component::StringEncoding;
+use wit_parser::Resolve;
+
+/// Fixtures built from this directory, along with the world they implement
+const FIXTURES: &[(&str, &str, &str)] = &[
+    ("counter.wat", "source-hook", "counter-source.wasm"),
+    ("failing.wat", "source-hook", "failing-source.wasm"),
+    (
+        "kafka-even-numbers.wat",
+        "intercept-hook",
+        "kafka-even-numbers-intercept.wasm",
+    ),
+];
+
+/// Builds the WebAssembly plugins used by Kiwi's tests, embedding the
+/// metadata of the world each of them implements
+fn main() -> anyhow::Result<()> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let out_dir = root.join("../../src/kiwi/tests/wasm");
+
+    let mut resolve = Resolve::default();
+    let (pkg, _) = resolve.push_dir(&root.join("../../src/wit"))?;
+
+    for (source, world, output) in FIXTURES {
+        let world = resolve.select_world(pkg, Some(world))?;
+        let mut bytes =
+            wat::parse_file(root.join(source)).context(format!("Failed to parse {}", source))?;
+
+        wit_component::embed_component_metadata(&mut bytes, &resolve, world, StringEncoding::UTF8)?;
+
+        std::fs::write(out_dir.join(output), bytes)
+            .context(format!("Failed to write {}", output))?;
+        println!("Built {}", output);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,44 @@
+//! A simple source plugin that emits a tick at a configurable interval
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use kiwi_sdk::hook::source::{source, Event, Poll};
+
+/// Number of ticks emitted so far
+static TICKS: AtomicU64 = AtomicU64::new(0);
+/// Time (in milliseconds since the Unix epoch) at which the last tick was emitted
+static LAST_TICK_MS: AtomicU64 = AtomicU64::new(0);
+
+/// You must use the `#[source]` macro to define a source plugin.
+#[source]
+fn poll() -> Poll {
+    // Environment variables configured for the source are exposed to the plugin
+    let interval_ms: u64 = std::env::var("TICK_INTERVAL_MS")
+        .ok()
+        .and_then(|interval| interval.parse().ok())
+        .unwrap_or(1000);
+
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock must be after the Unix epoch")
+        .as_millis() as u64;
+
+    if now_ms - LAST_TICK_MS.load(Ordering::Relaxed) < interval_ms {
+        // Returning `Poll::Pending` instructs Kiwi to poll the plugin again after
+        // the source's poll interval
+        return Poll::Pending;
+    }
+
+    LAST_TICK_MS.store(now_ms, Ordering::Relaxed);
+    let tick = TICKS.fetch_add(1, Ordering::Relaxed);
+
+    // Returning `Poll::Ready` instructs Kiwi to emit the event to all subscribers
+    // of the source
+    Poll::Ready(Event {
+        key: None,
+        metadata: Default::default(),
+        payload: format!(r#"{{"tick":{}}}"#, tick).into_bytes(),
+        timestamp: Some(now_ms),
+    })
+}
@@ -62,6 +62,11 @@ pub fn intercept(_attr: TokenStream, item: TokenStream) -> TokenStream {
                     match value {
                         self::bindings::kiwi::kiwi::intercept_types::EventCtx::Kafka(ctx) => Self::Kafka(ctx.into()),
                         self::bindings::kiwi::kiwi::intercept_types::EventCtx::Counter(ctx) => Self::Counter(ctx.into()),
+                        self::bindings::kiwi::kiwi::intercept_types::EventCtx::Redis(ctx) => Self::Redis(ctx.into()),
+                        self::bindings::kiwi::kiwi::intercept_types::EventCtx::Nats(ctx) => Self::Nats(ctx.into()),
+                        self::bindings::kiwi::kiwi::intercept_types::EventCtx::Mqtt(ctx) => Self::Mqtt(ctx.into()),
+                        self::bindings::kiwi::kiwi::intercept_types::EventCtx::Postgres(ctx) => Self::Postgres(ctx.into()),
+                        self::bindings::kiwi::kiwi::intercept_types::EventCtx::Custom(ctx) => Self::Custom(ctx.into()),
                     }
                 }
             }
@@ -82,7 +87,10 @@ pub fn intercept(_attr: TokenStream, item: TokenStream) -> TokenStream {
                     let offset: i64 = value.offset.try_into().expect("offset conversion must not fail");
 
                     Self {
+                        source_id: value.source_id,
+                        key: value.key,
                         payload: value.payload,
+                        headers: value.headers,
                         topic: value.topic,
                         timestamp,
                         partition,
@@ -91,6 +99,72 @@ pub fn intercept(_attr: TokenStream, item: TokenStream) -> TokenStream {
                 }
             }
 
+            impl From<self::bindings::kiwi::kiwi::intercept_types::RedisEventCtx> for ::kiwi_sdk::hook::intercept::RedisEventCtx {
+                fn from(value: self::bindings::kiwi::kiwi::intercept_types::RedisEventCtx) -> Self {
+                    Self {
+                        source_id: value.source_id,
+                        channel: value.channel,
+                        pattern: value.pattern,
+                        entry_id: value.entry_id,
+                        payload: value.payload,
+                        fields: value.fields,
+                    }
+                }
+            }
+
+            impl From<self::bindings::kiwi::kiwi::intercept_types::NatsEventCtx> for ::kiwi_sdk::hook::intercept::NatsEventCtx {
+                fn from(value: self::bindings::kiwi::kiwi::intercept_types::NatsEventCtx) -> Self {
+                    let timestamp: Option<i64> = value.timestamp.map(|t| t.try_into().expect("timestamp conversion must not fail"));
+
+                    Self {
+                        source_id: value.source_id,
+                        subject: value.subject,
+                        headers: value.headers,
+                        payload: value.payload,
+                        sequence: value.sequence,
+                        timestamp,
+                    }
+                }
+            }
+
+            impl From<self::bindings::kiwi::kiwi::intercept_types::MqttEventCtx> for ::kiwi_sdk::hook::intercept::MqttEventCtx {
+                fn from(value: self::bindings::kiwi::kiwi::intercept_types::MqttEventCtx) -> Self {
+                    Self {
+                        source_id: value.source_id,
+                        topic: value.topic,
+                        payload: value.payload,
+                        qos: value.qos,
+                        retain: value.retain,
+                    }
+                }
+            }
+
+            impl From<self::bindings::kiwi::kiwi::intercept_types::PostgresEventCtx> for ::kiwi_sdk::hook::intercept::PostgresEventCtx {
+                fn from(value: self::bindings::kiwi::kiwi::intercept_types::PostgresEventCtx) -> Self {
+                    Self {
+                        source_id: value.source_id,
+                        channel: value.channel,
+                        payload: value.payload,
+                        process_id: value.process_id,
+                    }
+                }
+            }
+
+            impl From<self::bindings::kiwi::kiwi::intercept_types::CustomEventCtx> for ::kiwi_sdk::hook::intercept::CustomEventCtx {
+                fn from(value: self::bindings::kiwi::kiwi::intercept_types::CustomEventCtx) -> Self {
+                    let timestamp: i64 = value.timestamp.try_into().expect("timestamp conversion must not fail");
+
+                    Self {
+                        source_id: value.source_id,
+                        kind: value.kind,
+                        key: value.key,
+                        metadata: value.metadata.into_iter().collect(),
+                        payload: value.payload,
+                        timestamp,
+                    }
+                }
+            }
+
             impl From<self::bindings::kiwi::kiwi::intercept_types::ConnectionCtx> for ::kiwi_sdk::hook::intercept::ConnectionCtx {
                 fn from(value: self::bindings::kiwi::kiwi::intercept_types::ConnectionCtx) -> Self {
                     match value {
@@ -122,6 +196,11 @@ pub fn intercept(_attr: TokenStream, item: TokenStream) -> TokenStream {
                     match value {
                         ::kiwi_sdk::hook::intercept::TransformedPayload::Kafka(payload) => Self::Kafka(payload),
                         ::kiwi_sdk::hook::intercept::TransformedPayload::Counter(count) => Self::Counter(count),
+                        ::kiwi_sdk::hook::intercept::TransformedPayload::Redis(payload) => Self::Redis(payload),
+                        ::kiwi_sdk::hook::intercept::TransformedPayload::Nats(payload) => Self::Nats(payload),
+                        ::kiwi_sdk::hook::intercept::TransformedPayload::Mqtt(payload) => Self::Mqtt(payload),
+                        ::kiwi_sdk::hook::intercept::TransformedPayload::Postgres(payload) => Self::Postgres(payload),
+                        ::kiwi_sdk::hook::intercept::TransformedPayload::Custom(payload) => Self::Custom(payload),
                     }
                 }
             }
@@ -232,3 +311,60 @@ pub fn authenticate(_attr: TokenStream, item: TokenStream) -> TokenStream {
     )
         .into()
 }
+
+/// Macro necessary for creating a source plugin.
+#[proc_macro_attribute]
+pub fn source(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let func = syn::parse_macro_input!(item as syn::ItemFn);
+    let func_name = &func.sig.ident;
+
+    // Like authenticate modules, source modules link in WASI, so we need to remap
+    // the WASI imports to their counterparts located in the Kiwi SDK.
+    quote!(
+        #func
+        mod __kiwi_source {
+            mod bindings {
+                #![allow(missing_docs)]
+                ::kiwi_sdk::wit_bindgen::generate!({
+                    path: #WIT_PATH,
+                    world: "source-hook",
+                    runtime_path: "::kiwi_sdk::wit_bindgen::rt",
+                    with: {
+                        "wasi:http/outgoing-handler@0.2.0": ::kiwi_sdk::wit::wasi::http::outgoing_handler,
+                        "wasi:http/types@0.2.0": ::kiwi_sdk::wit::wasi::http::types,
+                        "wasi:clocks/monotonic-clock@0.2.0": ::kiwi_sdk::wit::wasi::clocks::monotonic_clock,
+                        "wasi:io/poll@0.2.0": ::kiwi_sdk::wit::wasi::io::poll,
+                        "wasi:io/streams@0.2.0": ::kiwi_sdk::wit::wasi::io::streams,
+                        "wasi:io/error@0.2.0": ::kiwi_sdk::wit::wasi::io::error,
+                    },
+                });
+            }
+
+            struct Kiwi;
+
+            impl bindings::Guest for Kiwi {
+                fn poll() -> self::bindings::kiwi::kiwi::source_types::PollResult {
+                    super::#func_name().into()
+                }
+            }
+
+            impl From<::kiwi_sdk::hook::source::Poll> for self::bindings::kiwi::kiwi::source_types::PollResult {
+                fn from(value: ::kiwi_sdk::hook::source::Poll) -> Self {
+                    match value {
+                        ::kiwi_sdk::hook::source::Poll::Ready(event) => Self::Ready(self::bindings::kiwi::kiwi::source_types::Event {
+                            key: event.key,
+                            metadata: event.metadata.into_iter().collect(),
+                            payload: event.payload,
+                            timestamp: event.timestamp,
+                        }),
+                        ::kiwi_sdk::hook::source::Poll::Pending => Self::Pending,
+                        ::kiwi_sdk::hook::source::Poll::Done => Self::Done,
+                    }
+                }
+            }
+
+            bindings::export!(Kiwi with_types_in bindings);
+        }
+    )
+        .into()
+}
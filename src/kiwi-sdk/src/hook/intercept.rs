@@ -1,5 +1,7 @@
 //! Types and macros for building intercept hooks
 
+use std::collections::BTreeMap;
+
 pub use kiwi_macro::intercept;
 
 #[derive(Debug, Clone)]
@@ -9,6 +11,17 @@ pub enum TransformedPayload {
     Kafka(Option<Vec<u8>>),
     /// A transformed counter payload
     Counter(u64),
+    /// A transformed Redis payload
+    Redis(Option<Vec<u8>>),
+    /// A transformed NATS payload
+    Nats(Vec<u8>),
+    /// A transformed MQTT payload
+    Mqtt(Vec<u8>),
+    /// A transformed PostgreSQL notification payload
+    Postgres(Vec<u8>),
+    /// A transformed payload of a source without a dedicated variant, such as
+    /// HTTP, relay, file, exec and plugin sources
+    Custom(Vec<u8>),
 }
 
 #[derive(Debug, Clone)]
@@ -60,13 +73,31 @@ pub enum EventCtx {
     Kafka(KafkaEventCtx),
     /// A counter event context
     Counter(CounterEventCtx),
+    /// A Redis event context
+    Redis(RedisEventCtx),
+    /// A NATS event context
+    Nats(NatsEventCtx),
+    /// An MQTT event context
+    Mqtt(MqttEventCtx),
+    /// A PostgreSQL notification context
+    Postgres(PostgresEventCtx),
+    /// An event context for sources without a dedicated variant, such as
+    /// HTTP, relay, file, exec and plugin sources
+    Custom(CustomEventCtx),
 }
 
 #[derive(Debug, Clone)]
 /// A Kafka event context
 pub struct KafkaEventCtx {
+    /// The ID of the source the event was produced from
+    pub source_id: String,
+    /// The key of the event
+    pub key: Option<Vec<u8>>,
     /// The payload of the event
     pub payload: Option<Vec<u8>>,
+    /// The headers of the event, in the order they were produced. Header keys
+    /// may repeat
+    pub headers: Vec<(String, Option<Vec<u8>>)>,
     /// The topic to which the event was published
     pub topic: String,
     /// The timestamp of the event
@@ -85,3 +116,89 @@ pub struct CounterEventCtx {
     /// The current count of the counter
     pub count: u64,
 }
+
+#[derive(Debug, Clone)]
+/// A Redis event context
+pub struct RedisEventCtx {
+    /// The ID of the source the event was produced from
+    pub source_id: String,
+    /// The pub/sub channel the message was published to, or the key of the
+    /// stream the entry was read from
+    pub channel: String,
+    /// The pattern matching the channel, if the message was received via a
+    /// pattern subscription
+    pub pattern: Option<String>,
+    /// The ID of the stream entry, if the event was read from a stream
+    pub entry_id: Option<String>,
+    /// The payload of the pub/sub message
+    pub payload: Option<Vec<u8>>,
+    /// The field-value pairs of the stream entry, in order
+    pub fields: Vec<(String, Vec<u8>)>,
+}
+
+#[derive(Debug, Clone)]
+/// A NATS event context
+pub struct NatsEventCtx {
+    /// The ID of the source the event was produced from
+    pub source_id: String,
+    /// The subject the message was published to
+    pub subject: String,
+    /// The headers of the message, in the order they were sent. Header keys
+    /// may repeat
+    pub headers: Vec<(String, String)>,
+    /// The payload of the message
+    pub payload: Vec<u8>,
+    /// The sequence of the message within its JetStream stream, if the source
+    /// consumes a stream
+    pub sequence: Option<u64>,
+    /// The timestamp at which JetStream stored the message, if the source
+    /// consumes a stream
+    pub timestamp: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+/// An MQTT event context
+pub struct MqttEventCtx {
+    /// The ID of the source the event was produced from
+    pub source_id: String,
+    /// The topic the message was published to
+    pub topic: String,
+    /// The payload of the message
+    pub payload: Vec<u8>,
+    /// The QoS level the message was delivered with
+    pub qos: u8,
+    /// Whether the message was retained by the broker, rather than published
+    /// after the source subscribed
+    pub retain: bool,
+}
+
+#[derive(Debug, Clone)]
+/// A PostgreSQL notification context
+pub struct PostgresEventCtx {
+    /// The ID of the source the event was produced from
+    pub source_id: String,
+    /// The channel the notification was sent to
+    pub channel: String,
+    /// The payload of the notification
+    pub payload: Vec<u8>,
+    /// The process ID of the server backend that sent the notification
+    pub process_id: i32,
+}
+
+#[derive(Debug, Clone)]
+/// A generic event context, shared by sources without a dedicated context
+pub struct CustomEventCtx {
+    /// The ID of the source the event was produced from
+    pub source_id: String,
+    /// The type of the source the event was produced from, such as `http` or
+    /// `plugin`
+    pub kind: String,
+    /// The key of the event
+    pub key: Option<Vec<u8>>,
+    /// Additional information about the event, such as headers
+    pub metadata: BTreeMap<String, String>,
+    /// The payload of the event
+    pub payload: Vec<u8>,
+    /// The timestamp of the event
+    pub timestamp: i64,
+}
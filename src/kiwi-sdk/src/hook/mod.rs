@@ -2,3 +2,4 @@
 
 pub mod authenticate;
 pub mod intercept;
+pub mod source;
@@ -0,0 +1,32 @@
+//! Types and macros for building source plugins
+
+use std::collections::BTreeMap;
+
+pub use kiwi_macro::source;
+
+#[derive(Debug, Clone)]
+/// An event emitted by a source plugin
+pub struct Event {
+    /// The event key
+    pub key: Option<Vec<u8>>,
+    /// Additional information about the event, such as headers
+    pub metadata: BTreeMap<String, String>,
+    /// The event payload
+    pub payload: Vec<u8>,
+    /// The timestamp of the event, in milliseconds since the Unix epoch. If
+    /// `None`, Kiwi uses the time at which the event was polled
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+/// The result of polling a source plugin
+pub enum Poll {
+    /// An event is ready to be emitted
+    Ready(Event),
+    /// No event is ready. Kiwi polls the plugin again after the source's
+    /// configured poll interval
+    Pending,
+    /// The source has ended. Kiwi no longer polls the plugin, and closes all
+    /// subscriptions to the source
+    Done,
+}
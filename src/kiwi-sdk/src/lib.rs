@@ -47,6 +47,37 @@
 //!     Outcome::Authenticate
 //! }
 //! ```
+//!
+//! ## Source
+//! ```ignore
+//! //! A simple source plugin that emits three events before ending
+//! use std::sync::atomic::{AtomicU64, Ordering};
+//!
+//! use kiwi_sdk::hook::source::{source, Event, Poll};
+//!
+//! static COUNT: AtomicU64 = AtomicU64::new(0);
+//!
+//! /// You must use the `#[source]` macro to define a source plugin. The plugin
+//! /// stays loaded for the lifetime of the source, so state kept between polls
+//! /// is preserved.
+//! #[source]
+//! fn poll() -> Poll {
+//!     let count = COUNT.fetch_add(1, Ordering::Relaxed);
+//!
+//!     if count < 3 {
+//!         // Returning `Poll::Ready` instructs Kiwi to emit the event to subscribers
+//!         Poll::Ready(Event {
+//!             key: None,
+//!             metadata: Default::default(),
+//!             payload: count.to_string().into_bytes(),
+//!             timestamp: None,
+//!         })
+//!     } else {
+//!         // Returning `Poll::Done` instructs Kiwi to end the source
+//!         Poll::Done
+//!     }
+//! }
+//! ```
 
 pub mod hook;
 
@@ -1,8 +1,8 @@
-use std::collections::{btree_map, BTreeMap};
-use std::fmt::Debug;
+use std::collections::{btree_map, BTreeMap, BTreeSet};
 use std::sync::{Arc, Mutex};
 
 use arc_swap::ArcSwapOption;
+use futures::future::Either;
 use futures::stream::select_all::select_all;
 use futures::StreamExt;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
@@ -11,8 +11,10 @@ use tokio_stream::wrappers::BroadcastStream;
 use crate::config::Subscriber as SubscriberConfig;
 use crate::hook::intercept::types::TransformedPayload;
 use crate::hook::intercept::{self, types::Intercept};
-use crate::protocol::{Command, CommandResponse, Message, Notice};
-use crate::source::{Source, SourceId, SourceMessage, SourceResult};
+use crate::protocol::{self, Command, CommandResponse, Message, Notice};
+use crate::source::{
+    self, Replay, Source, SourceId, SourceMessage, SourceResult, SourceStream, SubscribeError,
+};
 use crate::subscription::{Subscription, SubscriptionRecvError};
 
 /// An actor that is responsible for the following:
@@ -37,9 +39,22 @@ pub struct ConnectionManager<I> {
     /// Subscriber configuration that applies to all subscriptions managed
     /// by this actor
     subscriber_config: SubscriberConfig,
+    /// Sources whose replay subscriptions are still being set up
+    pending_replays: BTreeSet<SourceId>,
+    /// Channels through which replay subscriptions are handed back to the actor
+    /// once they have been set up
+    replays_tx: UnboundedSender<(PendingSubscription, Result<SourceStream, String>)>,
+    replays_rx: UnboundedReceiver<(PendingSubscription, Result<SourceStream, String>)>,
+}
+
+/// A subscribe command awaiting the stream of its subscription
+struct PendingSubscription {
+    source_id: SourceId,
+    mode: protocol::SubscriptionMode,
+    partitions: Option<BTreeSet<i32>>,
+    degraded_reason: Option<String>,
 }
 
-#[derive(Debug)]
 /// Represents the current state of the actor's main processing loop, defining what action
 /// it should next take. The states here are externally-driven, meaning external
 /// events cause state transitions. As a result, there is no starting state which
@@ -47,6 +62,8 @@ pub struct ConnectionManager<I> {
 enum ConnectionManagerState<T> {
     /// A command has been received from the connection
     Command(Command),
+    /// The stream of a replay subscription has been set up
+    Replayed((PendingSubscription, Result<SourceStream, String>)),
     /// Results have been received from a source
     SourceResults((SourceId, Vec<T>)),
     /// An error has occurred while processing source results
@@ -66,6 +83,8 @@ where
         intercept: Arc<ArcSwapOption<I>>,
         subscriber_config: SubscriberConfig,
     ) -> Self {
+        let (replays_tx, replays_rx) = tokio::sync::mpsc::unbounded_channel();
+
         Self {
             cmd_rx,
             msg_tx,
@@ -75,6 +94,9 @@ where
             subscriptions: Default::default(),
             intercept,
             subscriber_config,
+            pending_replays: Default::default(),
+            replays_tx,
+            replays_rx,
         }
     }
 
@@ -103,6 +125,7 @@ where
                             None => break,
                         }
                     },
+                    Some(replayed) = self.replays_rx.recv() => ConnectionManagerState::Replayed(replayed),
                     // Since the stream combinator is re-computed on each iteration, receiving
                     // `None` does not signal we are done. It is very possible that the actor
                     // handle later signals to add a new subscription via `cmd_tx`
@@ -119,12 +142,18 @@ where
                 ConnectionManagerState::Command(cmd) => {
                     self.handle_command(cmd).await?;
                 }
+                ConnectionManagerState::Replayed((pending, source_stream)) => {
+                    // Replays of sources unsubscribed from in the meantime are discarded
+                    if self.pending_replays.remove(&pending.source_id) {
+                        self.complete_subscription(pending, source_stream)?;
+                    }
+                }
                 ConnectionManagerState::SourceResults((source_id, results)) => {
                     for result in results {
                         let source_id = source_id.clone();
                         match result {
                             SourceMessage::Result(incoming) => {
-                                self.forward_source_result(incoming).await?;
+                                self.forward_source_result(source_id, incoming).await?;
                             }
                             SourceMessage::MetadataChanged(message) => {
                                 if self.subscriptions.remove(&source_id).is_some() {
@@ -136,6 +165,26 @@ where
                                     ))?;
                                 }
                             }
+                            // Subscriptions routed to specific partitions are closed by the
+                            // routing layer, while other subscriptions simply stop observing
+                            // events from the revoked partitions
+                            SourceMessage::PartitionsRevoked(_) => (),
+                            SourceMessage::StatusChanged(status) => {
+                                let (status, message) = match status {
+                                    source::SourceStatus::Degraded(reason) => {
+                                        (protocol::SourceStatus::Degraded, Some(reason))
+                                    }
+                                    source::SourceStatus::Recovered => {
+                                        (protocol::SourceStatus::Recovered, None)
+                                    }
+                                };
+
+                                self.msg_tx.send(Message::Notice(Notice::SourceStatus {
+                                    source_id,
+                                    status,
+                                    message,
+                                }))?;
+                            }
                         }
                     }
                 }
@@ -151,7 +200,21 @@ where
                         }
                     }
                     SubscriptionRecvError::ProcessLag(lag) => {
-                        tracing::warn!(lag, source_id, connection = ?self.connection_ctx, "Receiver is lagging");
+                        let usage = self
+                            .sources
+                            .lock()
+                            .expect("poisoned lock")
+                            .get(&source_id)
+                            .and_then(|source| source.channel_usage());
+
+                        tracing::warn!(
+                            lag,
+                            source_id,
+                            channel_capacity = usage.map(|usage| usage.capacity),
+                            channel_len = usage.map(|usage| usage.len),
+                            connection = ?self.connection_ctx,
+                            "Receiver is lagging. Consider increasing the channel capacity of the source",
+                        );
                         self.msg_tx.send(Message::Notice(Notice::Lag {
                             source_id,
                             count: lag,
@@ -177,49 +240,109 @@ where
 
     async fn handle_command(&mut self, command: Command) -> anyhow::Result<()> {
         match command {
-            Command::Subscribe { source_id, mode } => {
-                let response = match self.subscriptions.entry(source_id.clone()) {
-                    btree_map::Entry::Occupied(_) => CommandResponse::SubscribeError {
-                        source_id,
-                        error: "Source already has an active subscription".to_string(),
-                    },
-                    btree_map::Entry::Vacant(entry) => {
-                        let response = if let Some(source) = self
-                            .sources
-                            .lock()
-                            .expect("poisoned lock")
-                            .get_mut(&source_id)
-                        {
-                            match source.subscribe() {
-                                Ok(rx) => {
-                                    let source_stream = BroadcastStream::new(rx);
-                                    let subscription = Subscription::from_mode(
-                                        source_stream,
-                                        mode,
-                                        self.subscriber_config.buffer_capacity,
-                                    );
-
-                                    entry.insert(subscription);
-
-                                    CommandResponse::SubscribeOk { source_id }
+            Command::Subscribe {
+                source_id,
+                mode,
+                from_offsets,
+                lookback_ms,
+                partitions,
+            } => {
+                if self.subscriptions.contains_key(&source_id)
+                    || self.pending_replays.contains(&source_id)
+                {
+                    self.msg_tx.send(Message::CommandResponse(
+                        CommandResponse::SubscribeError {
+                            source_id,
+                            error: "Source already has an active subscription".to_string(),
+                        },
+                    ))?;
+
+                    return Ok(());
+                }
+
+                let replay = match (from_offsets, lookback_ms) {
+                    (None, None) => Ok(None),
+                    (Some(offsets), None) => Ok(Some(Replay::Offsets(offsets))),
+                    (None, Some(lookback_ms)) => Ok(Some(Replay::Lookback(
+                        std::time::Duration::from_millis(lookback_ms),
+                    ))),
+                    (Some(_), Some(_)) => {
+                        Err("Only one of `fromOffsets` or `lookbackMs` may be specified"
+                            .to_string())
+                    }
+                };
+
+                // Clients subscribing to a degraded source are notified right away,
+                // since they would otherwise not learn of it until it recovers
+                let mut degraded_reason = None;
+
+                let subscribe = self
+                    .sources
+                    .lock()
+                    .expect("poisoned lock")
+                    .get_mut(&source_id)
+                    .map(|source| {
+                        degraded_reason = source.degraded_reason();
+
+                        replay.and_then(|replay| {
+                            if let Some(partitions) = partitions.as_ref() {
+                                let assigned = source
+                                    .partitions()
+                                    .ok_or(SubscribeError::PartitionsUnsupported)
+                                    .map_err(|err| err.to_string())?;
+                                let unassigned = partitions
+                                    .difference(&assigned)
+                                    .copied()
+                                    .collect::<Vec<_>>();
+
+                                if !unassigned.is_empty() {
+                                    return Err(SubscribeError::PartitionsNotAssigned(unassigned)
+                                        .to_string());
                                 }
-                                Err(err) => CommandResponse::SubscribeError {
-                                    source_id,
-                                    error: err.to_string(),
-                                },
                             }
-                        } else {
-                            CommandResponse::SubscribeError {
-                                source_id,
-                                error: "No source exists with the specified ID".to_string(),
+
+                            match replay {
+                                Some(replay) => {
+                                    source.subscribe_with_replay(replay).map(Either::Right)
+                                }
+                                None => source.subscribe().map(|rx| {
+                                    Either::Left(Box::pin(BroadcastStream::new(rx)) as SourceStream)
+                                }),
                             }
-                        };
+                            .map_err(|err| err.to_string())
+                        })
+                    });
 
-                        response
-                    }
+                let pending = PendingSubscription {
+                    source_id,
+                    mode,
+                    partitions,
+                    degraded_reason,
                 };
 
-                self.msg_tx.send(Message::CommandResponse(response))?;
+                match subscribe {
+                    // Setting up a replay may block on the backing system, so it is
+                    // awaited outside of the actor, which keeps serving its other
+                    // subscriptions in the meantime
+                    Some(Ok(Either::Right(replay))) => {
+                        self.pending_replays.insert(pending.source_id.clone());
+
+                        let replays_tx = self.replays_tx.clone();
+
+                        tokio::task::spawn(async move {
+                            let source_stream = replay.await.map_err(|err| err.to_string());
+                            let _ = replays_tx.send((pending, source_stream));
+                        });
+                    }
+                    Some(Ok(Either::Left(source_stream))) => {
+                        self.complete_subscription(pending, Ok(source_stream))?;
+                    }
+                    Some(Err(error)) => self.complete_subscription(pending, Err(error))?,
+                    None => self.complete_subscription(
+                        pending,
+                        Err("No source exists with the specified ID".to_string()),
+                    )?,
+                }
             }
             Command::Unsubscribe { source_id } => {
                 let response = match self.subscriptions.entry(source_id.clone()) {
@@ -227,6 +350,10 @@ where
                         entry.remove();
                         CommandResponse::UnsubscribeOk { source_id }
                     }
+                    // The replay is discarded once it has been set up
+                    btree_map::Entry::Vacant(_) if self.pending_replays.remove(&source_id) => {
+                        CommandResponse::UnsubscribeOk { source_id }
+                    }
                     btree_map::Entry::Vacant(_) => CommandResponse::UnsubscribeError {
                         source_id,
                         error: "Source does not have an active subscription".to_string(),
@@ -274,6 +401,60 @@ where
         Ok(())
     }
 
+    /// Responds to a subscribe command once the stream of the subscription has
+    /// been set up, adding the subscription if it succeeded
+    fn complete_subscription(
+        &mut self,
+        pending: PendingSubscription,
+        source_stream: Result<SourceStream, String>,
+    ) -> anyhow::Result<()> {
+        let PendingSubscription {
+            source_id,
+            mode,
+            partitions,
+            degraded_reason,
+        } = pending;
+
+        let response = match source_stream {
+            Ok(source_stream) => {
+                let source_stream = match partitions {
+                    Some(partitions) => source::route_partitions(source_stream, partitions),
+                    None => source_stream,
+                };
+
+                let subscription = Subscription::from_mode(
+                    source_stream,
+                    mode,
+                    self.subscriber_config.buffer_capacity,
+                );
+
+                self.subscriptions.insert(source_id.clone(), subscription);
+
+                CommandResponse::SubscribeOk {
+                    source_id: source_id.clone(),
+                }
+            }
+            Err(error) => CommandResponse::SubscribeError {
+                source_id: source_id.clone(),
+                error,
+            },
+        };
+
+        let subscribed = matches!(response, CommandResponse::SubscribeOk { .. });
+
+        self.msg_tx.send(Message::CommandResponse(response))?;
+
+        if let Some(reason) = degraded_reason.filter(|_| subscribed) {
+            self.msg_tx.send(Message::Notice(Notice::SourceStatus {
+                source_id,
+                status: protocol::SourceStatus::Degraded,
+                message: Some(reason),
+            }))?;
+        }
+
+        Ok(())
+    }
+
     /// Processes a source result by passing it through the intercept hook
     async fn process_source_result(
         &self,
@@ -304,6 +485,24 @@ where
                     (SourceResult::Counter(counter_event), TransformedPayload::Counter(count)) => {
                         counter_event.count = count;
                     }
+                    (SourceResult::Redis(redis_event), TransformedPayload::Redis(payload)) => {
+                        redis_event.payload = payload;
+                    }
+                    (SourceResult::Nats(nats_event), TransformedPayload::Nats(payload)) => {
+                        nats_event.payload = payload;
+                    }
+                    (SourceResult::Mqtt(mqtt_event), TransformedPayload::Mqtt(payload)) => {
+                        mqtt_event.payload = payload;
+                    }
+                    (
+                        SourceResult::Postgres(postgres_event),
+                        TransformedPayload::Postgres(payload),
+                    ) => {
+                        postgres_event.payload = payload;
+                    }
+                    (SourceResult::Custom(custom_event), TransformedPayload::Custom(payload)) => {
+                        custom_event.payload = payload;
+                    }
                     _ => {
                         return Err(anyhow::anyhow!(
                             "Plugin returned a transformed payload that does not match the source result"
@@ -319,10 +518,24 @@ where
     }
 
     /// Forward the source result along the connection's message channel
-    async fn forward_source_result(&mut self, incoming: SourceResult) -> anyhow::Result<()> {
+    async fn forward_source_result(
+        &mut self,
+        source_id: SourceId,
+        incoming: SourceResult,
+    ) -> anyhow::Result<()> {
         let incoming = self.process_source_result(incoming).await?;
         if let Some(incoming) = incoming {
-            self.msg_tx.send(incoming.into())?;
+            let (result, fallback) = protocol::SourceResult::encode(incoming);
+
+            if let Some(message) = fallback {
+                self.msg_tx
+                    .send(Message::Notice(Notice::PayloadEncodingFallback {
+                        source_id,
+                        message,
+                    }))?;
+            }
+
+            self.msg_tx.send(Message::Result(result))?;
         }
 
         Ok(())
@@ -331,11 +544,12 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::protocol;
-    use crate::source::{SourceMessage, SourceMetadata, SubscribeError};
+    use crate::protocol::{self, PayloadEncoding};
+    use crate::source::{PendingReplay, SourceMessage, SourceMetadata, SubscribeError};
 
     use super::*;
     use async_trait::async_trait;
+    use std::collections::BTreeSet;
     use std::time::Duration;
     use tokio::sync::broadcast::{Receiver, Sender};
 
@@ -343,6 +557,7 @@ mod tests {
     struct TestSource {
         tx: Sender<SourceMessage>,
         source_id: SourceId,
+        degraded_reason: Option<String>,
     }
 
     impl Source for TestSource {
@@ -350,6 +565,55 @@ mod tests {
             Ok(self.tx.subscribe())
         }
 
+        fn partitions(&self) -> Option<BTreeSet<i32>> {
+            Some(BTreeSet::from([0]))
+        }
+
+        fn degraded_reason(&self) -> Option<String> {
+            self.degraded_reason.clone()
+        }
+
+        fn source_id(&self) -> &SourceId {
+            &self.source_id
+        }
+
+        fn metadata_tx(&self) -> &Option<tokio::sync::mpsc::UnboundedSender<SourceMetadata>> {
+            &None
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    /// Source whose replays are only set up once signalled
+    struct ReplaySource {
+        tx: Sender<SourceMessage>,
+        source_id: SourceId,
+        setup_rx: Option<tokio::sync::oneshot::Receiver<()>>,
+    }
+
+    impl Source for ReplaySource {
+        fn subscribe(&mut self) -> Result<Receiver<SourceMessage>, SubscribeError> {
+            Ok(self.tx.subscribe())
+        }
+
+        fn subscribe_with_replay(
+            &mut self,
+            _replay: Replay,
+        ) -> Result<PendingReplay, SubscribeError> {
+            let rx = self.tx.subscribe();
+            let setup_rx = self.setup_rx.take();
+
+            Ok(Box::pin(async move {
+                if let Some(setup_rx) = setup_rx {
+                    let _ = setup_rx.await;
+                }
+
+                Ok(Box::pin(BroadcastStream::new(rx)) as SourceStream)
+            }))
+        }
+
         fn source_id(&self) -> &SourceId {
             &self.source_id
         }
@@ -382,10 +646,12 @@ mod tests {
             id: "test".to_string(),
             key: None,
             payload: None,
+            headers: Vec::new(),
             topic: "test".to_string(),
             timestamp: None,
             partition: 0,
             offset: 0,
+            payload_encoding: PayloadEncoding::Base64,
         })
     }
 
@@ -405,6 +671,9 @@ mod tests {
             .send(Command::Subscribe {
                 source_id: source_id.to_string(),
                 mode: mode.unwrap_or_default(),
+                from_offsets: None,
+                lookback_ms: None,
+                partitions: None,
             })
             .unwrap();
     }
@@ -426,6 +695,7 @@ mod tests {
             .unwrap();
     }
 
+    #[allow(clippy::type_complexity)]
     fn spawn_actor<P: Intercept + Clone + Send + Sync + 'static>(
         pre_forward: Option<P>,
         test_source_ids: Vec<String>,
@@ -451,6 +721,7 @@ mod tests {
                 Box::new(TestSource {
                     tx: source_tx.clone(),
                     source_id,
+                    degraded_reason: None,
                 }) as Box<dyn Source + Send + Sync + 'static>,
             )
         }));
@@ -627,6 +898,186 @@ mod tests {
         recv_subscribe_err(&mut msg_rx, "test2").await;
     }
 
+    #[tokio::test]
+    async fn test_source_subscribing_with_replay() {
+        let (cmd_tx, mut msg_rx, _, _, _) =
+            spawn_actor(Some(DiscardPlugin), vec!["test".to_string()], 100, None);
+
+        // Replay is rejected by sources that do not support it
+        cmd_tx
+            .send(Command::Subscribe {
+                source_id: "test".to_string(),
+                mode: protocol::SubscriptionMode::Push,
+                from_offsets: None,
+                lookback_ms: Some(1000),
+                partitions: None,
+            })
+            .unwrap();
+
+        recv_subscribe_err(&mut msg_rx, "test").await;
+
+        // Only one replay position may be specified
+        cmd_tx
+            .send(Command::Subscribe {
+                source_id: "test".to_string(),
+                mode: protocol::SubscriptionMode::Push,
+                from_offsets: Some(BTreeMap::from([(0, 0)])),
+                lookback_ms: Some(1000),
+                partitions: None,
+            })
+            .unwrap();
+
+        recv_subscribe_err(&mut msg_rx, "test").await;
+
+        // A failed replay subscription does not prevent a regular subscription
+        send_subscribe_cmd(&cmd_tx, "test", Some(protocol::SubscriptionMode::Push));
+
+        recv_subscribe_ok(&mut msg_rx, "test").await;
+    }
+
+    #[tokio::test]
+    async fn test_replay_setup_does_not_block_actor() {
+        let (cmd_tx, mut msg_rx, source_tx, _, sources) =
+            spawn_actor::<DiscardPlugin>(None, vec!["test".to_string()], 100, None);
+        let (setup_tx, setup_rx) = tokio::sync::oneshot::channel();
+
+        sources.lock().unwrap().insert(
+            "replayed".to_string(),
+            Box::new(ReplaySource {
+                tx: source_tx.clone(),
+                source_id: "replayed".to_string(),
+                setup_rx: Some(setup_rx),
+            }),
+        );
+
+        let subscribe_with_replay = || {
+            cmd_tx
+                .send(Command::Subscribe {
+                    source_id: "replayed".to_string(),
+                    mode: protocol::SubscriptionMode::Push,
+                    from_offsets: None,
+                    lookback_ms: Some(1000),
+                    partitions: None,
+                })
+                .unwrap();
+        };
+
+        subscribe_with_replay();
+
+        // Other commands are handled while the replay is being set up
+        send_subscribe_cmd(&cmd_tx, "test", Some(protocol::SubscriptionMode::Push));
+
+        recv_subscribe_ok(&mut msg_rx, "test").await;
+
+        subscribe_with_replay();
+
+        recv_subscribe_err(&mut msg_rx, "replayed").await;
+
+        setup_tx.send(()).unwrap();
+
+        recv_subscribe_ok(&mut msg_rx, "replayed").await;
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribing_discards_pending_replay() {
+        let (cmd_tx, mut msg_rx, source_tx, _, sources) =
+            spawn_actor::<DiscardPlugin>(None, vec![], 100, None);
+        let (setup_tx, setup_rx) = tokio::sync::oneshot::channel();
+
+        sources.lock().unwrap().insert(
+            "replayed".to_string(),
+            Box::new(ReplaySource {
+                tx: source_tx.clone(),
+                source_id: "replayed".to_string(),
+                setup_rx: Some(setup_rx),
+            }),
+        );
+
+        cmd_tx
+            .send(Command::Subscribe {
+                source_id: "replayed".to_string(),
+                mode: protocol::SubscriptionMode::Push,
+                from_offsets: None,
+                lookback_ms: Some(1000),
+                partitions: None,
+            })
+            .unwrap();
+
+        send_unsubscribe_cmd(&cmd_tx, "replayed");
+
+        recv_unsubscribe_ok(&mut msg_rx, "replayed").await;
+
+        setup_tx.send(()).unwrap();
+
+        // The replay is not subscribed to once set up
+        let _ = source_tx.send(SourceMessage::Result(test_kafka_source_result()));
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), msg_rx.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_source_subscribing_with_partitions() {
+        let (cmd_tx, mut msg_rx, source_tx, _, _) =
+            spawn_actor::<DiscardPlugin>(None, vec!["test".to_string()], 100, None);
+
+        let subscribe = |partitions: BTreeSet<i32>| {
+            cmd_tx
+                .send(Command::Subscribe {
+                    source_id: "test".to_string(),
+                    mode: protocol::SubscriptionMode::Push,
+                    from_offsets: None,
+                    lookback_ms: None,
+                    partitions: Some(partitions),
+                })
+                .unwrap();
+        };
+
+        // Partitions not consumed by the source cannot be routed to
+        subscribe(BTreeSet::from([0, 1]));
+
+        recv_subscribe_err(&mut msg_rx, "test").await;
+
+        subscribe(BTreeSet::from([0]));
+
+        recv_subscribe_ok(&mut msg_rx, "test").await;
+
+        for partition in [1, 0] {
+            let mut result = test_kafka_source_result();
+
+            if let SourceResult::Kafka(result) = &mut result {
+                result.partition = partition;
+            }
+
+            source_tx.send(SourceMessage::Result(result)).unwrap();
+        }
+
+        // Events from other partitions are not forwarded
+        match msg_rx.recv().await.unwrap() {
+            Message::Result(protocol::SourceResult::Kafka { partition, .. }) => {
+                assert_eq!(partition, 0)
+            }
+            m => panic!(
+                "actor should forward the routed event. Instead sent {:?}",
+                m
+            ),
+        }
+
+        // Revoking an unrelated partition does not affect the subscription, while
+        // revoking a routed partition closes it
+        source_tx
+            .send(SourceMessage::PartitionsRevoked(BTreeSet::from([1])))
+            .unwrap();
+        source_tx
+            .send(SourceMessage::PartitionsRevoked(BTreeSet::from([0])))
+            .unwrap();
+
+        recv_subscription_closed(&mut msg_rx, "test").await;
+    }
+
     #[tokio::test]
     async fn test_source_unsubscribing() {
         let (cmd_tx, mut msg_rx, _, _, _) =
@@ -754,7 +1205,7 @@ mod tests {
                             protocol::SourceResult::Kafka { payload, .. } => {
                                 assert_eq!(
                                     payload,
-                                    Some("hello".as_bytes().to_owned()),
+                                    Some(serde_json::Value::String("aGVsbG8=".into())),
                                     "message payload should have been transformed"
                                 );
                             },
@@ -873,6 +1324,79 @@ mod tests {
         recv_subscription_closed(&mut msg_rx, "test").await;
     }
 
+    #[tokio::test]
+    async fn test_source_status_forwarded_to_subscribers() {
+        let (cmd_tx, mut msg_rx, source_tx, _, _) =
+            spawn_actor::<DiscardPlugin>(None, vec!["test".to_string()], 100, None);
+
+        send_subscribe_cmd(&cmd_tx, "test", Some(protocol::SubscriptionMode::Push));
+
+        recv_subscribe_ok(&mut msg_rx, "test").await;
+
+        source_tx
+            .send(SourceMessage::StatusChanged(
+                source::SourceStatus::Degraded("Brokers are down".into()),
+            ))
+            .unwrap();
+
+        assert!(matches!(
+            msg_rx.recv().await.unwrap(),
+            Message::Notice(Notice::SourceStatus {
+                source_id,
+                status: protocol::SourceStatus::Degraded,
+                message: Some(message),
+            }) if source_id == "test" && message == "Brokers are down"
+        ));
+
+        // Subscriptions remain open while the source is degraded
+        source_tx
+            .send(SourceMessage::StatusChanged(
+                source::SourceStatus::Recovered,
+            ))
+            .unwrap();
+        source_tx
+            .send(SourceMessage::Result(test_kafka_source_result()))
+            .unwrap();
+
+        assert!(matches!(
+            msg_rx.recv().await.unwrap(),
+            Message::Notice(Notice::SourceStatus {
+                status: protocol::SourceStatus::Recovered,
+                message: None,
+                ..
+            })
+        ));
+        assert!(matches!(msg_rx.recv().await.unwrap(), Message::Result(_)));
+    }
+
+    #[tokio::test]
+    async fn test_source_status_sent_when_subscribing_to_degraded_source() {
+        let (cmd_tx, mut msg_rx, source_tx, _, sources) =
+            spawn_actor::<DiscardPlugin>(None, vec![], 100, None);
+
+        sources.lock().unwrap().insert(
+            "test".into(),
+            Box::new(TestSource {
+                tx: source_tx.clone(),
+                source_id: "test".into(),
+                degraded_reason: Some("Brokers are down".into()),
+            }),
+        );
+
+        send_subscribe_cmd(&cmd_tx, "test", Some(protocol::SubscriptionMode::Push));
+
+        recv_subscribe_ok(&mut msg_rx, "test").await;
+
+        assert!(matches!(
+            msg_rx.recv().await.unwrap(),
+            Message::Notice(Notice::SourceStatus {
+                source_id,
+                status: protocol::SourceStatus::Degraded,
+                message: Some(message),
+            }) if source_id == "test" && message == "Brokers are down"
+        ));
+    }
+
     #[tokio::test]
     async fn test_source_closes_on_upstream_source_closed() {
         let (cmd_tx, mut msg_rx, source_tx, _, sources) =
@@ -885,9 +1409,7 @@ mod tests {
         drop(source_tx);
 
         // Drop the source, which should cause the actor to emit a subscription closed notice
-        let mut sources = sources.lock().unwrap();
-
-        sources.remove("test");
+        sources.lock().unwrap().remove("test");
 
         recv_subscription_closed(&mut msg_rx, "test").await;
     }
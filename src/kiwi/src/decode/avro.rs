@@ -0,0 +1,516 @@
+//! Decoding of Avro binary encoded data into JSON
+use std::collections::HashMap;
+
+use anyhow::Context;
+use base64::Engine;
+use serde_json::{Map, Number, Value};
+
+use super::Reader;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Schema {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    Record {
+        fields: Vec<(String, Schema)>,
+    },
+    Enum {
+        symbols: Vec<String>,
+    },
+    Array(Box<Schema>),
+    Map(Box<Schema>),
+    Union(Vec<Schema>),
+    Fixed {
+        size: usize,
+    },
+    /// Reference to a named type (record, enum or fixed) by its full name
+    Named(String),
+}
+
+/// A parsed Avro schema, along with the named types it defines
+#[derive(Debug)]
+pub struct AvroSchema {
+    root: Schema,
+    names: HashMap<String, Schema>,
+}
+
+impl AvroSchema {
+    pub fn parse(schema: &str) -> anyhow::Result<Self> {
+        Self::parse_with_references(schema, [])
+    }
+
+    /// Parses the schema, which may use the named types defined by `references`.
+    /// References must be ordered such that each follows the references it uses
+    pub fn parse_with_references<'a>(
+        schema: &str,
+        references: impl IntoIterator<Item = &'a str>,
+    ) -> anyhow::Result<Self> {
+        let mut names = HashMap::new();
+
+        for reference in references {
+            let json: Value = serde_json::from_str(reference)
+                .context("Referenced Avro schema is not valid JSON")?;
+
+            parse_schema(&json, None, &mut names)?;
+        }
+
+        let json: Value = serde_json::from_str(schema).context("Avro schema is not valid JSON")?;
+        let root = parse_schema(&json, None, &mut names)?;
+
+        Ok(Self { root, names })
+    }
+
+    /// Decodes a single Avro binary encoded datum written with this schema.
+    /// Unions are represented by the value of the selected branch, logical
+    /// types by their underlying type and bytes as base64 encoded strings
+    pub fn decode(&self, bytes: &[u8]) -> anyhow::Result<Value> {
+        let mut reader = Reader::new(bytes);
+
+        self.decode_value(&self.root, &mut reader)
+    }
+
+    fn decode_value(&self, schema: &Schema, reader: &mut Reader) -> anyhow::Result<Value> {
+        let value = match schema {
+            Schema::Null => Value::Null,
+            Schema::Boolean => Value::Bool(reader.read_byte()? != 0),
+            Schema::Int | Schema::Long => Value::Number(reader.read_zigzag()?.into()),
+            Schema::Float => {
+                let bytes: [u8; 4] = reader.read_bytes(4)?.try_into()?;
+                float(f32::from_le_bytes(bytes).into())
+            }
+            Schema::Double => {
+                let bytes: [u8; 8] = reader.read_bytes(8)?.try_into()?;
+                float(f64::from_le_bytes(bytes))
+            }
+            Schema::Bytes => {
+                let len = read_len(reader)?;
+                Value::String(
+                    base64::engine::general_purpose::STANDARD.encode(reader.read_bytes(len)?),
+                )
+            }
+            Schema::String => Value::String(read_string(reader)?),
+            Schema::Record { fields } => {
+                let mut map = Map::with_capacity(fields.len());
+
+                for (name, schema) in fields {
+                    map.insert(name.clone(), self.decode_value(schema, reader)?);
+                }
+
+                Value::Object(map)
+            }
+            Schema::Enum { symbols } => {
+                let index = reader.read_zigzag()?;
+
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|index| symbols.get(index))
+                    .map(|symbol| Value::String(symbol.clone()))
+                    .ok_or_else(|| anyhow::anyhow!("Enum index {} is out of range", index))?
+            }
+            Schema::Array(items) => {
+                let mut values = Vec::new();
+
+                while let Some(count) = read_block_count(reader)? {
+                    for _ in 0..count {
+                        values.push(self.decode_value(items, reader)?);
+                    }
+                }
+
+                Value::Array(values)
+            }
+            Schema::Map(values) => {
+                let mut map = Map::new();
+
+                while let Some(count) = read_block_count(reader)? {
+                    for _ in 0..count {
+                        let key = read_string(reader)?;
+                        map.insert(key, self.decode_value(values, reader)?);
+                    }
+                }
+
+                Value::Object(map)
+            }
+            Schema::Union(branches) => {
+                let index = reader.read_zigzag()?;
+                let branch = usize::try_from(index)
+                    .ok()
+                    .and_then(|index| branches.get(index))
+                    .ok_or_else(|| anyhow::anyhow!("Union index {} is out of range", index))?;
+
+                self.decode_value(branch, reader)?
+            }
+            Schema::Fixed { size } => Value::String(
+                base64::engine::general_purpose::STANDARD.encode(reader.read_bytes(*size)?),
+            ),
+            Schema::Named(name) => {
+                let schema = self
+                    .names
+                    .get(name)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown Avro type {}", name))?;
+
+                self.decode_value(schema, reader)?
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+fn float(value: f64) -> Value {
+    // JSON has no representation for NaN or infinite values
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+fn read_len(reader: &mut Reader) -> anyhow::Result<usize> {
+    let len = reader.read_zigzag()?;
+
+    usize::try_from(len).map_err(|_| anyhow::anyhow!("Invalid length {}", len))
+}
+
+fn read_string(reader: &mut Reader) -> anyhow::Result<String> {
+    let len = read_len(reader)?;
+
+    Ok(std::str::from_utf8(reader.read_bytes(len)?)
+        .context("Avro string is not valid UTF-8")?
+        .to_string())
+}
+
+/// Reads the item count of the next array or map block, returning `None` once
+/// the terminating block has been reached
+fn read_block_count(reader: &mut Reader) -> anyhow::Result<Option<u64>> {
+    let count = reader.read_zigzag()?;
+
+    if count == 0 {
+        return Ok(None);
+    }
+
+    if count < 0 {
+        // A negative count is followed by the size of the block in bytes
+        reader.read_zigzag()?;
+    }
+
+    Ok(Some(count.unsigned_abs()))
+}
+
+fn full_name(name: &str, namespace: Option<&str>) -> String {
+    match namespace {
+        Some(namespace) if !name.contains('.') && !namespace.is_empty() => {
+            format!("{}.{}", namespace, name)
+        }
+        _ => name.to_string(),
+    }
+}
+
+fn primitive(name: &str) -> Option<Schema> {
+    let schema = match name {
+        "null" => Schema::Null,
+        "boolean" => Schema::Boolean,
+        "int" => Schema::Int,
+        "long" => Schema::Long,
+        "float" => Schema::Float,
+        "double" => Schema::Double,
+        "bytes" => Schema::Bytes,
+        "string" => Schema::String,
+        _ => return None,
+    };
+
+    Some(schema)
+}
+
+fn parse_schema(
+    json: &Value,
+    namespace: Option<&str>,
+    names: &mut HashMap<String, Schema>,
+) -> anyhow::Result<Schema> {
+    match json {
+        Value::String(name) => {
+            if let Some(schema) = primitive(name) {
+                return Ok(schema);
+            }
+
+            [full_name(name, namespace), name.clone()]
+                .into_iter()
+                .find(|name| names.contains_key(name))
+                .map(Schema::Named)
+                .ok_or_else(|| anyhow::anyhow!("Unknown Avro type {}", name))
+        }
+        Value::Array(branches) => Ok(Schema::Union(
+            branches
+                .iter()
+                .map(|branch| parse_schema(branch, namespace, names))
+                .collect::<anyhow::Result<_>>()?,
+        )),
+        Value::Object(object) => {
+            let typ = object
+                .get("type")
+                .ok_or_else(|| anyhow::anyhow!("Avro schema is missing `type`"))?;
+
+            let typ = match typ {
+                Value::String(typ) => typ.as_str(),
+                // e.g. {"type": {"type": "array", ...}}
+                typ => return parse_schema(typ, namespace, names),
+            };
+
+            let name = || -> anyhow::Result<(String, Option<String>)> {
+                let name = object
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| anyhow::anyhow!("Avro {} is missing `name`", typ))?;
+                let namespace = object
+                    .get("namespace")
+                    .and_then(Value::as_str)
+                    .or(namespace);
+                let full_name = full_name(name, namespace);
+                let namespace = full_name.rsplit_once('.').map(|(ns, _)| ns.to_string());
+
+                Ok((full_name, namespace))
+            };
+
+            match typ {
+                "record" | "error" => {
+                    let (full_name, namespace) = name()?;
+                    // Register the name before parsing fields to support recursive types
+                    names.insert(full_name.clone(), Schema::Record { fields: Vec::new() });
+
+                    let fields = object
+                        .get("fields")
+                        .and_then(Value::as_array)
+                        .ok_or_else(|| anyhow::anyhow!("Avro record is missing `fields`"))?
+                        .iter()
+                        .map(|field| {
+                            let name = field
+                                .get("name")
+                                .and_then(Value::as_str)
+                                .ok_or_else(|| anyhow::anyhow!("Avro field is missing `name`"))?;
+                            let typ = field.get("type").ok_or_else(|| {
+                                anyhow::anyhow!("Avro field {} is missing `type`", name)
+                            })?;
+
+                            Ok((
+                                name.to_string(),
+                                parse_schema(typ, namespace.as_deref(), names)?,
+                            ))
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+
+                    let schema = Schema::Record { fields };
+                    names.insert(full_name, schema.clone());
+
+                    Ok(schema)
+                }
+                "enum" => {
+                    let (full_name, _) = name()?;
+                    let symbols = object
+                        .get("symbols")
+                        .and_then(Value::as_array)
+                        .ok_or_else(|| anyhow::anyhow!("Avro enum is missing `symbols`"))?
+                        .iter()
+                        .map(|symbol| {
+                            symbol
+                                .as_str()
+                                .map(str::to_string)
+                                .ok_or_else(|| anyhow::anyhow!("Avro enum symbol must be a string"))
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+
+                    let schema = Schema::Enum { symbols };
+                    names.insert(full_name, schema.clone());
+
+                    Ok(schema)
+                }
+                "fixed" => {
+                    let (full_name, _) = name()?;
+                    let size = object
+                        .get("size")
+                        .and_then(Value::as_u64)
+                        .ok_or_else(|| anyhow::anyhow!("Avro fixed is missing `size`"))?;
+
+                    let schema = Schema::Fixed {
+                        size: size.try_into()?,
+                    };
+                    names.insert(full_name, schema.clone());
+
+                    Ok(schema)
+                }
+                "array" => {
+                    let items = object
+                        .get("items")
+                        .ok_or_else(|| anyhow::anyhow!("Avro array is missing `items`"))?;
+
+                    Ok(Schema::Array(Box::new(parse_schema(
+                        items, namespace, names,
+                    )?)))
+                }
+                "map" => {
+                    let values = object
+                        .get("values")
+                        .ok_or_else(|| anyhow::anyhow!("Avro map is missing `values`"))?;
+
+                    Ok(Schema::Map(Box::new(parse_schema(
+                        values, namespace, names,
+                    )?)))
+                }
+                // Primitive types, possibly annotated with a logical type
+                typ => parse_schema(&Value::String(typ.to_string()), namespace, names),
+            }
+        }
+        _ => Err(anyhow::anyhow!("Invalid Avro schema: {}", json)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zigzag(n: i64) -> Vec<u8> {
+        let mut n = ((n << 1) ^ (n >> 63)) as u64;
+        let mut bytes = Vec::new();
+
+        loop {
+            if n < 0x80 {
+                bytes.push(n as u8);
+                break;
+            }
+
+            bytes.push((n as u8 & 0x7f) | 0x80);
+            n >>= 7;
+        }
+
+        bytes
+    }
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut bytes = zigzag(s.len() as i64);
+        bytes.extend_from_slice(s.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_decodes_record() {
+        let schema = AvroSchema::parse(
+            r#"{
+                "type": "record",
+                "name": "User",
+                "namespace": "com.example",
+                "fields": [
+                    {"name": "id", "type": "long"},
+                    {"name": "name", "type": "string"},
+                    {"name": "email", "type": ["null", "string"]},
+                    {"name": "tags", "type": {"type": "array", "items": "string"}},
+                    {"name": "status", "type": {"type": "enum", "name": "Status", "symbols": ["ACTIVE", "DISABLED"]}},
+                    {"name": "previous", "type": ["null", "Status"]},
+                    {"name": "score", "type": "double"},
+                    {"name": "attributes", "type": {"type": "map", "values": "int"}},
+                    {"name": "created_at", "type": {"type": "long", "logicalType": "timestamp-millis"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let mut bytes = Vec::new();
+        bytes.extend(zigzag(42));
+        bytes.extend(string("Alice"));
+        // Union branch 1 (string)
+        bytes.extend(zigzag(1));
+        bytes.extend(string("alice@example.com"));
+        // Array with a single block of two items
+        bytes.extend(zigzag(2));
+        bytes.extend(string("a"));
+        bytes.extend(string("b"));
+        bytes.extend(zigzag(0));
+        // Enum index
+        bytes.extend(zigzag(1));
+        // Union branch 0 (null)
+        bytes.extend(zigzag(0));
+        bytes.extend(1.5f64.to_le_bytes());
+        // Map with a negative block count, followed by the block size
+        bytes.extend(zigzag(-1));
+        bytes.extend(zigzag(3));
+        bytes.extend(string("x"));
+        bytes.extend(zigzag(-7));
+        bytes.extend(zigzag(0));
+        bytes.extend(zigzag(1700000000000));
+
+        assert_eq!(
+            schema.decode(&bytes).unwrap(),
+            serde_json::json!({
+                "id": 42,
+                "name": "Alice",
+                "email": "alice@example.com",
+                "tags": ["a", "b"],
+                "status": "DISABLED",
+                "previous": null,
+                "score": 1.5,
+                "attributes": {"x": -7},
+                "created_at": 1700000000000i64,
+            })
+        );
+    }
+
+    #[test]
+    fn test_decodes_recursive_record() {
+        let schema = AvroSchema::parse(
+            r#"{
+                "type": "record",
+                "name": "Node",
+                "fields": [
+                    {"name": "value", "type": "int"},
+                    {"name": "next", "type": ["null", "Node"]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let mut bytes = Vec::new();
+        bytes.extend(zigzag(1));
+        bytes.extend(zigzag(1));
+        bytes.extend(zigzag(2));
+        bytes.extend(zigzag(0));
+
+        assert_eq!(
+            schema.decode(&bytes).unwrap(),
+            serde_json::json!({"value": 1, "next": {"value": 2, "next": null}})
+        );
+    }
+
+    #[test]
+    fn test_decodes_record_with_references() {
+        let reference = r#"{
+            "type": "record",
+            "name": "Address",
+            "namespace": "com.example",
+            "fields": [{"name": "city", "type": "string"}]
+        }"#;
+        let schema = AvroSchema::parse_with_references(
+            r#"{
+                "type": "record",
+                "name": "User",
+                "namespace": "com.example",
+                "fields": [{"name": "address", "type": "Address"}]
+            }"#,
+            [reference],
+        )
+        .unwrap();
+
+        assert_eq!(
+            schema.decode(&string("Paris")).unwrap(),
+            serde_json::json!({"address": {"city": "Paris"}})
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_data() {
+        let schema = AvroSchema::parse(r#""string""#).unwrap();
+
+        // Length exceeds the remaining input
+        assert!(schema.decode(&zigzag(10)).is_err());
+        assert!(AvroSchema::parse(r#"{"type": "record", "name": "Missing"}"#).is_err());
+        assert!(AvroSchema::parse(r#""Unknown""#).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+//! Decoding of schema registry framed Kafka payloads into JSON
+pub mod avro;
+pub mod protobuf;
+pub mod registry;
+
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use self::registry::{Schema, SchemaRegistry};
+
+/// Magic byte prefixing payloads framed with the schema registry wire format
+const MAGIC_BYTE: u8 = 0;
+
+/// Payload formats that can be decoded
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Avro,
+    Protobuf,
+}
+
+impl Format {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Format::Avro => "avro",
+            Format::Protobuf => "protobuf",
+        }
+    }
+}
+
+/// Decodes payloads framed with the schema registry wire format, resolving
+/// writer schemas from a [`SchemaRegistry`]
+pub struct Decoder {
+    format: Format,
+    registry: Arc<SchemaRegistry>,
+}
+
+impl Decoder {
+    pub fn new(format: Format, registry: Arc<SchemaRegistry>) -> Self {
+        Self { format, registry }
+    }
+
+    /// Decodes the payload into a JSON value
+    pub async fn decode(&self, bytes: &[u8]) -> anyhow::Result<Value> {
+        let mut reader = Reader::new(bytes);
+
+        if reader.read_byte()? != MAGIC_BYTE {
+            anyhow::bail!("Payload is not prefixed with the schema registry magic byte");
+        }
+
+        let id: [u8; 4] = reader.read_bytes(4)?.try_into()?;
+        let id = u32::from_be_bytes(id);
+        let schema = self.registry.schema(id).await?;
+
+        match (self.format, schema.as_ref()) {
+            (Format::Avro, Schema::Avro(schema)) => schema.decode(reader.remaining()),
+            (Format::Protobuf, Schema::Protobuf(file)) => {
+                // The message indexes identify the message type within the schema. As an
+                // optimization, a single zero count denotes the first message
+                let count = reader.read_zigzag()?;
+                let indexes = if count == 0 {
+                    vec![0]
+                } else {
+                    (0..count)
+                        .map(|_| Ok(usize::try_from(reader.read_zigzag()?)?))
+                        .collect::<anyhow::Result<Vec<_>>>()?
+                };
+
+                file.decode(&indexes, reader.remaining())
+            }
+            (format, _) => Err(anyhow::anyhow!(
+                "Schema {} is not a {} schema",
+                id,
+                format.as_str()
+            )),
+        }
+    }
+}
+
+/// Cursor over a byte slice, providing the primitive reads shared by the
+/// binary encodings
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn read_byte(&mut self) -> anyhow::Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_bytes(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        if n > self.bytes.len() {
+            anyhow::bail!("Unexpected end of input");
+        }
+
+        let (bytes, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+
+        Ok(bytes)
+    }
+
+    pub fn read_varint(&mut self) -> anyhow::Result<u64> {
+        let mut value = 0u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(anyhow::anyhow!("Variable-length integer is too long"))
+    }
+
+    pub fn read_zigzag(&mut self) -> anyhow::Result<i64> {
+        let value = self.read_varint()?;
+
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reader_reads_varints() {
+        let mut reader = Reader::new(&[0x96, 0x01, 0x03, 0x04]);
+
+        assert_eq!(reader.read_varint().unwrap(), 150);
+        assert_eq!(reader.read_zigzag().unwrap(), -2);
+        assert_eq!(reader.read_zigzag().unwrap(), 2);
+        assert!(reader.remaining().is_empty());
+        assert!(reader.read_byte().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_decodes_framed_payloads() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("1.avsc"),
+            r#"{"type": "record", "name": "A", "fields": [{"name": "n", "type": "int"}]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("2.proto"),
+            "syntax = \"proto3\"; message A { int32 n = 1; } message B { string s = 1; }",
+        )
+        .unwrap();
+
+        let registry = Arc::new(SchemaRegistry::file(dir.path()));
+        let avro = Decoder::new(Format::Avro, registry.clone());
+        let protobuf = Decoder::new(Format::Protobuf, registry);
+
+        assert_eq!(
+            avro.decode(&[0, 0, 0, 0, 1, 0x54]).await.unwrap(),
+            serde_json::json!({"n": 42})
+        );
+        assert_eq!(
+            protobuf
+                .decode(&[0, 0, 0, 0, 2, 0, 0x08, 42])
+                .await
+                .unwrap(),
+            serde_json::json!({"n": 42})
+        );
+        assert_eq!(
+            protobuf
+                .decode(&[0, 0, 0, 0, 2, 2, 2, 0x0a, 1, b'x'])
+                .await
+                .unwrap(),
+            serde_json::json!({"s": "x"})
+        );
+
+        // Missing magic byte
+        assert!(avro.decode(&[1, 0, 0, 0, 1, 0x54]).await.is_err());
+        // Schema type mismatch
+        assert!(avro.decode(&[0, 0, 0, 0, 2, 0]).await.is_err());
+        // Unknown schema
+        assert!(avro.decode(&[0, 0, 0, 0, 3, 0]).await.is_err());
+    }
+}
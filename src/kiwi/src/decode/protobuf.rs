@@ -0,0 +1,257 @@
+//! Decoding of Protobuf binary encoded messages into JSON, using schemas
+//! described by `.proto` definitions
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor};
+use protobuf::Message;
+use protobuf_parse::ProtoPathBuf;
+use serde_json::Value;
+
+/// Import path under which the parsed definition is laid out alongside its
+/// references
+const ROOT_PATH: &str = "kiwi/root.proto";
+
+/// A parsed `.proto` file
+#[derive(Debug)]
+pub struct ProtoFile {
+    /// Top-level messages, in declaration order
+    messages: Vec<MessageDescriptor>,
+}
+
+impl ProtoFile {
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        Self::parse_with_references(source, &BTreeMap::new())
+    }
+
+    /// Parses the definition, resolving its imports from `references`, which maps
+    /// import paths to the definitions they refer to. Well-known types such as
+    /// `google/protobuf/timestamp.proto` are always available
+    pub fn parse_with_references(
+        source: &str,
+        references: &BTreeMap<String, String>,
+    ) -> anyhow::Result<Self> {
+        // The parser resolves imports from the file system, so the definition and
+        // its references are laid out in a temporary directory
+        let dir = tempfile::tempdir()?;
+
+        for (path, content) in references
+            .iter()
+            .map(|(path, content)| (path.as_str(), content.as_str()))
+            .chain([(ROOT_PATH, source)])
+        {
+            let path = ProtoPathBuf::new(path.to_string())
+                .context(format!("Invalid Protobuf import path {}", path))?;
+            let file = dir.path().join(path.to_path());
+
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            std::fs::write(file, content)?;
+        }
+
+        let parsed = protobuf_parse::Parser::new()
+            .pure()
+            .include(dir.path())
+            .input(dir.path().join(ROOT_PATH))
+            .parse_and_typecheck()
+            .context("Invalid Protobuf schema")?;
+
+        let set = protobuf::descriptor::FileDescriptorSet {
+            file: parsed.file_descriptors,
+            ..Default::default()
+        };
+        let pool = DescriptorPool::decode(set.write_to_bytes()?.as_slice())
+            .context("Invalid Protobuf schema")?;
+
+        let messages = pool
+            .get_file_by_name(ROOT_PATH)
+            .ok_or_else(|| anyhow::anyhow!("Protobuf schema was not parsed"))?
+            .messages()
+            .collect();
+
+        Ok(Self { messages })
+    }
+
+    /// Decodes a message of the type identified by `indexes`. The first index selects
+    /// a top-level message, and each subsequent index selects a message nested within
+    /// the previously selected message. Messages are represented following the proto3
+    /// JSON mapping
+    pub fn decode(&self, indexes: &[usize], bytes: &[u8]) -> anyhow::Result<Value> {
+        let (first, rest) = indexes
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("Message indexes must not be empty"))?;
+
+        let mut descriptor = self
+            .messages
+            .get(*first)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Message index {} is out of range", first))?;
+
+        for index in rest {
+            let nested = descriptor.child_messages().nth(*index);
+
+            descriptor = nested.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Message index {} is out of range for {}",
+                    index,
+                    descriptor.full_name()
+                )
+            })?;
+        }
+
+        let message = DynamicMessage::decode(descriptor.clone(), bytes)
+            .context(format!("Invalid {} message", descriptor.full_name()))?;
+
+        Ok(serde_json::to_value(&message)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        syntax = "proto3";
+        package com.example;
+
+        import "other.proto";
+        option java_package = "com.example.proto";
+
+        // A user
+        message User {
+            int64 id = 1;
+            string first_name = 2;
+            repeated int32 scores = 3;
+            Status status = 4;
+            Address address = 5;
+            map<string, int32> counts = 6;
+            oneof contact {
+                string email = 7;
+                string phone = 8 [deprecated = true];
+            }
+            reserved 9, 10;
+            com.other.Tag tag = 11;
+
+            enum Status {
+                option allow_alias = true;
+                UNKNOWN = 0;
+                ACTIVE = 1;
+            }
+
+            /* Nested message */
+            message Address {
+                string city = 1;
+            }
+        }
+
+        message Other {
+            bool flag = 1;
+        }
+    "#;
+
+    const REFERENCE: &str = r#"
+        syntax = "proto3";
+        package com.other;
+
+        message Tag {
+            string name = 1;
+        }
+    "#;
+
+    fn parse() -> ProtoFile {
+        ProtoFile::parse_with_references(
+            SCHEMA,
+            &BTreeMap::from([("other.proto".to_string(), REFERENCE.to_string())]),
+        )
+        .unwrap()
+    }
+
+    fn varint(mut n: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        loop {
+            if n < 0x80 {
+                bytes.push(n as u8);
+                break;
+            }
+
+            bytes.push((n as u8 & 0x7f) | 0x80);
+            n >>= 7;
+        }
+
+        bytes
+    }
+
+    fn len_delimited(field: u64, data: &[u8]) -> Vec<u8> {
+        let mut bytes = varint(field << 3 | 2);
+        bytes.extend(varint(data.len() as u64));
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn test_decodes_message() {
+        let file = parse();
+
+        let mut bytes = Vec::new();
+        bytes.extend(varint(1 << 3));
+        bytes.extend(varint(42));
+        bytes.extend(len_delimited(2, b"Alice"));
+        // Packed repeated field
+        bytes.extend(len_delimited(3, &[1, 2, 3]));
+        bytes.extend(varint(4 << 3));
+        bytes.extend(varint(1));
+        bytes.extend(len_delimited(5, &len_delimited(1, b"Paris")));
+
+        let mut entry = len_delimited(1, b"x");
+        entry.extend(varint(2 << 3));
+        entry.extend(varint(7));
+        bytes.extend(len_delimited(6, &entry));
+
+        bytes.extend(len_delimited(7, b"alice@example.com"));
+        bytes.extend(len_delimited(11, &len_delimited(1, b"vip")));
+        // Unknown fields are skipped
+        bytes.extend(varint(99 << 3));
+        bytes.extend(varint(1));
+
+        assert_eq!(
+            file.decode(&[0], &bytes).unwrap(),
+            serde_json::json!({
+                "id": "42",
+                "firstName": "Alice",
+                "scores": [1, 2, 3],
+                "status": "ACTIVE",
+                "address": {"city": "Paris"},
+                "counts": {"x": 7},
+                "email": "alice@example.com",
+                "tag": {"name": "vip"},
+            })
+        );
+    }
+
+    #[test]
+    fn test_decodes_message_by_index() {
+        let file = parse();
+
+        assert_eq!(
+            file.decode(&[1], &[1 << 3, 1]).unwrap(),
+            serde_json::json!({"flag": true})
+        );
+        // Map fields declare nested entry messages, which are counted like any other
+        assert_eq!(
+            file.decode(&[0, 1], &len_delimited(1, b"Paris")).unwrap(),
+            serde_json::json!({"city": "Paris"})
+        );
+        assert!(file.decode(&[2], &[]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_unknown_types() {
+        assert!(ProtoFile::parse("syntax = \"proto3\"; message A { B b = 1; }").is_err());
+        assert!(ProtoFile::parse("syntax = \"proto3\"; message A { int32 a = 1; ").is_err());
+        // Imports must be provided as references
+        assert!(ProtoFile::parse(SCHEMA).is_err());
+    }
+}
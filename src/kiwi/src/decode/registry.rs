@@ -0,0 +1,368 @@
+//! Resolution of writer schemas from a Confluent-compatible schema registry
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use futures::future::{BoxFuture, Shared};
+use futures::FutureExt;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::avro::AvroSchema;
+use super::protobuf::ProtoFile;
+
+/// Time after which requests to the schema registry are abandoned
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time for which a failed lookup is reported to every payload framed with its
+/// schema ID before the schema is fetched again
+const FAILED_LOOKUP_TTL: Duration = Duration::from_secs(30);
+
+/// A parsed writer schema
+#[derive(Debug)]
+pub enum Schema {
+    Avro(AvroSchema),
+    Protobuf(ProtoFile),
+}
+
+impl Schema {
+    /// Parses a schema of the given registry schema type, which defaults to Avro.
+    /// References are ordered such that each follows the references it uses
+    fn parse(
+        schema_type: Option<&str>,
+        schema: &str,
+        references: &[(String, String)],
+    ) -> anyhow::Result<Self> {
+        match schema_type {
+            None | Some("AVRO") => Ok(Schema::Avro(AvroSchema::parse_with_references(
+                schema,
+                references.iter().map(|(_, schema)| schema.as_str()),
+            )?)),
+            Some("PROTOBUF") => Ok(Schema::Protobuf(ProtoFile::parse_with_references(
+                schema,
+                &references.iter().cloned().collect::<BTreeMap<_, _>>(),
+            )?)),
+            Some(other) => Err(anyhow::anyhow!("Unsupported schema type {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Backend {
+    Http(HttpBackend),
+    /// Local directory containing schemas named `<id>.avsc` or `<id>.proto`
+    File {
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct HttpBackend {
+    client: reqwest::Client,
+    url: String,
+    credentials: Option<(String, String)>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SchemaResponse {
+    schema: String,
+    #[serde(default)]
+    schema_type: Option<String>,
+    #[serde(default)]
+    references: Vec<SchemaReference>,
+}
+
+/// Reference to a schema registered under another subject. For Protobuf the name
+/// is the import path of the referenced schema, and for Avro the name of the type
+/// it defines
+#[derive(Debug, Deserialize)]
+struct SchemaReference {
+    name: String,
+    subject: String,
+    version: i32,
+}
+
+impl HttpBackend {
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        let mut request = self
+            .client
+            .get(format!("{}/{}", self.url, path))
+            .header("Accept", "application/vnd.schemaregistry.v1+json");
+
+        if let Some((username, password)) = self.credentials.as_ref() {
+            request = request.basic_auth(username, Some(password));
+        }
+
+        let response = request.send().await?.error_for_status()?;
+
+        Ok(serde_json::from_slice(&response.bytes().await?)?)
+    }
+
+    async fn fetch(&self, id: u32) -> anyhow::Result<Schema> {
+        let response: SchemaResponse = self.get(&format!("schemas/ids/{}", id)).await?;
+        let mut references = Vec::new();
+
+        self.resolve_references(&response.references, &mut references)
+            .await?;
+
+        Schema::parse(
+            response.schema_type.as_deref(),
+            &response.schema,
+            &references,
+        )
+    }
+
+    /// Fetches the referenced schemas along with the schemas they reference in turn,
+    /// appending each after the schemas it references
+    fn resolve_references<'a>(
+        &'a self,
+        references: &'a [SchemaReference],
+        resolved: &'a mut Vec<(String, String)>,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            for reference in references {
+                if resolved.iter().any(|(name, _)| *name == reference.name) {
+                    continue;
+                }
+
+                let response: SchemaResponse = self
+                    .get(&format!(
+                        "subjects/{}/versions/{}",
+                        utf8_percent_encode(&reference.subject, NON_ALPHANUMERIC),
+                        reference.version
+                    ))
+                    .await
+                    .context(format!(
+                        "Failed to resolve reference {} (version {} of subject {})",
+                        reference.name, reference.version, reference.subject
+                    ))?;
+
+                self.resolve_references(&response.references, resolved)
+                    .await?;
+
+                resolved.push((reference.name.clone(), response.schema));
+            }
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// Lookup of a schema, shared by every payload framed with its ID
+type Lookup = Shared<BoxFuture<'static, Result<Arc<Schema>, String>>>;
+
+/// Schema registry client. Schemas are immutable once registered, so they are
+/// cached indefinitely after the first lookup. Concurrent lookups of a schema
+/// share a single request, and failed lookups are cached for a short period
+#[derive(Debug)]
+pub struct SchemaRegistry {
+    backend: Backend,
+    cache: Mutex<HashMap<u32, (Instant, Lookup)>>,
+}
+
+impl SchemaRegistry {
+    pub fn http(url: String, credentials: Option<(String, String)>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("Failed to create schema registry client");
+
+        Self::with_backend(Backend::Http(HttpBackend {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            credentials,
+        }))
+    }
+
+    pub fn file<P: Into<PathBuf>>(path: P) -> Self {
+        Self::with_backend(Backend::File { path: path.into() })
+    }
+
+    fn with_backend(backend: Backend) -> Self {
+        Self {
+            backend,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the schema registered with the specified ID
+    pub async fn schema(&self, id: u32) -> anyhow::Result<Arc<Schema>> {
+        let lookup = {
+            let mut cache = self.cache.lock().expect("poisoned lock");
+
+            match cache.get(&id) {
+                Some((started, lookup))
+                    if !matches!(lookup.peek(), Some(Err(_)))
+                        || started.elapsed() < FAILED_LOOKUP_TTL =>
+                {
+                    lookup.clone()
+                }
+                _ => {
+                    // Schemas are fetched on a separate task, since the HTTP client's
+                    // futures are not `Sync` and decoding happens within `Sync` source
+                    // streams. The task also completes the lookup for other payloads
+                    // if this one is dropped
+                    let fetch = tokio::task::spawn(Self::fetch(self.backend.clone(), id));
+                    let lookup = async move {
+                        match fetch.await {
+                            Ok(Ok(schema)) => Ok(Arc::new(schema)),
+                            Ok(Err(err)) => Err(format!("{:#}", err)),
+                            Err(err) => Err(err.to_string()),
+                        }
+                    }
+                    .boxed()
+                    .shared();
+
+                    cache.insert(id, (Instant::now(), lookup.clone()));
+
+                    lookup
+                }
+            }
+        };
+
+        lookup
+            .await
+            .map_err(|err| anyhow::anyhow!("Failed to resolve schema {}: {}", id, err))
+    }
+
+    async fn fetch(backend: Backend, id: u32) -> anyhow::Result<Schema> {
+        match backend {
+            Backend::Http(backend) => backend.fetch(id).await,
+            Backend::File { path } => {
+                let avro = path.join(format!("{}.avsc", id));
+                let protobuf = path.join(format!("{}.proto", id));
+
+                if let Ok(schema) = tokio::fs::read_to_string(&avro).await {
+                    Schema::parse(Some("AVRO"), &schema, &[])
+                } else if let Ok(schema) = tokio::fs::read_to_string(&protobuf).await {
+                    Schema::parse(Some("PROTOBUF"), &schema, &[])
+                } else {
+                    Err(anyhow::anyhow!(
+                        "Neither {} nor {} could be read",
+                        avro.display(),
+                        protobuf.display()
+                    ))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    /// Serves the given responses by request path, counting the requests made
+    /// for each path. Unknown paths are answered with `404 Not Found`
+    async fn serve(
+        responses: BTreeMap<&'static str, serde_json::Value>,
+    ) -> (String, Arc<Mutex<BTreeMap<String, usize>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(BTreeMap::new()));
+        let counts = Arc::clone(&requests);
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let responses = responses.clone();
+                let requests = Arc::clone(&requests);
+
+                tokio::spawn(async move {
+                    let mut buf = [0; 4096];
+                    let n = stream.read(&mut buf).await.unwrap();
+                    let request = String::from_utf8_lossy(&buf[..n]);
+                    let path = request.split(' ').nth(1).unwrap_or_default().to_string();
+
+                    *requests.lock().unwrap().entry(path.clone()).or_insert(0) += 1;
+
+                    // Concurrent lookups overlap with the request in flight
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+
+                    let (status, body) = match responses.get(path.as_str()) {
+                        Some(body) => ("200 OK", body.to_string()),
+                        None => ("404 Not Found", "{}".to_string()),
+                    };
+
+                    let _ = stream
+                        .write_all(
+                            format!(
+                                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                                status,
+                                body.len(),
+                                body
+                            )
+                            .as_bytes(),
+                        )
+                        .await;
+                });
+            }
+        });
+
+        (url, counts)
+    }
+
+    #[tokio::test]
+    async fn test_resolves_schema_references() {
+        let (url, _) = serve(BTreeMap::from([
+            (
+                "/schemas/ids/1",
+                serde_json::json!({
+                    "schemaType": "PROTOBUF",
+                    "schema": "syntax = \"proto3\"; import \"tag.proto\"; message A { Tag tag = 1; }",
+                    "references": [{"name": "tag.proto", "subject": "tag value", "version": 2}],
+                }),
+            ),
+            (
+                "/subjects/tag%20value/versions/2",
+                serde_json::json!({
+                    "schema": "syntax = \"proto3\"; message Tag { string name = 1; }",
+                }),
+            ),
+        ]))
+        .await;
+
+        let registry = SchemaRegistry::http(url, None);
+
+        match registry.schema(1).await.unwrap().as_ref() {
+            Schema::Protobuf(file) => assert_eq!(
+                file.decode(&[0], &[0x0a, 3, 0x0a, 1, b'x']).unwrap(),
+                serde_json::json!({"tag": {"name": "x"}})
+            ),
+            schema => panic!("Expected Protobuf schema. Received {:?}", schema),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shares_concurrent_and_failed_lookups() {
+        let (url, requests) = serve(BTreeMap::from([(
+            "/schemas/ids/1",
+            serde_json::json!({"schema": "\"string\""}),
+        )]))
+        .await;
+
+        let registry = SchemaRegistry::http(url, None);
+
+        let lookups = futures::future::join_all((0..5).map(|_| registry.schema(1))).await;
+        assert!(lookups.iter().all(Result::is_ok));
+
+        // Schema 2 does not exist, and the failure is cached
+        assert!(registry.schema(2).await.is_err());
+        assert!(registry.schema(2).await.is_err());
+
+        assert_eq!(
+            *requests.lock().unwrap(),
+            BTreeMap::from([
+                ("/schemas/ids/1".to_string(), 1),
+                ("/schemas/ids/2".to_string(), 1)
+            ])
+        );
+    }
+}
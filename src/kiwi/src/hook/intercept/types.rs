@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
 
 use async_trait::async_trait;
@@ -6,6 +7,11 @@ use async_trait::async_trait;
 pub enum TransformedPayload {
     Kafka(Option<Vec<u8>>),
     Counter(u64),
+    Redis(Option<Vec<u8>>),
+    Nats(Vec<u8>),
+    Mqtt(Vec<u8>),
+    Postgres(Vec<u8>),
+    Custom(Vec<u8>),
 }
 
 #[derive(Debug, Clone)]
@@ -16,16 +22,30 @@ pub enum Action {
 }
 
 #[derive(Debug, Clone)]
-/// Context needed to execute a plugin
+/// Context needed to execute a plugin. Fields may be added to contexts and to
+/// the event contexts they carry, so they are built through their constructors
+/// outside of Kiwi, such as when testing natively implemented hooks
+#[non_exhaustive]
 pub struct Context {
-    pub(crate) auth: Option<AuthCtx>,
-    pub(crate) connection: ConnectionCtx,
-    pub(crate) event: EventCtx,
+    pub auth: Option<AuthCtx>,
+    pub connection: ConnectionCtx,
+    pub event: EventCtx,
+}
+
+impl Context {
+    pub fn new(auth: Option<AuthCtx>, connection: ConnectionCtx, event: EventCtx) -> Self {
+        Self {
+            auth,
+            connection,
+            event,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct AuthCtx {
-    pub(crate) raw: Vec<u8>,
+    pub raw: Vec<u8>,
 }
 
 impl AuthCtx {
@@ -35,34 +55,266 @@ impl AuthCtx {
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ConnectionCtx {
     WebSocket(WebSocketConnectionCtx),
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct WebSocketConnectionCtx {
-    pub(crate) addr: SocketAddr,
+    pub addr: SocketAddr,
+}
+
+impl WebSocketConnectionCtx {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self { addr }
+    }
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum EventCtx {
     Kafka(KafkaEventCtx),
     Counter(CounterEventCtx),
+    Redis(RedisEventCtx),
+    Nats(NatsEventCtx),
+    Mqtt(MqttEventCtx),
+    Postgres(PostgresEventCtx),
+    Custom(CustomEventCtx),
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct KafkaEventCtx {
-    pub(crate) payload: Option<Vec<u8>>,
-    pub(crate) topic: String,
-    pub(crate) timestamp: Option<i64>,
-    pub(crate) partition: i32,
-    pub(crate) offset: i64,
+    pub source_id: String,
+    pub key: Option<Vec<u8>>,
+    pub payload: Option<Vec<u8>>,
+    pub headers: Vec<(String, Option<Vec<u8>>)>,
+    pub topic: String,
+    pub timestamp: Option<i64>,
+    pub partition: i32,
+    pub offset: i64,
+}
+
+impl KafkaEventCtx {
+    /// Context of an event without a key, payload, headers or timestamp
+    pub fn new(source_id: String, topic: String, partition: i32, offset: i64) -> Self {
+        Self {
+            source_id,
+            key: None,
+            payload: None,
+            headers: Vec::new(),
+            topic,
+            timestamp: None,
+            partition,
+            offset,
+        }
+    }
+
+    pub fn with_key(mut self, key: Vec<u8>) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    pub fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = Some(payload);
+        self
+    }
+
+    pub fn with_headers(mut self, headers: Vec<(String, Option<Vec<u8>>)>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct CounterEventCtx {
-    pub(crate) source_id: String,
-    pub(crate) count: u64,
+    pub source_id: String,
+    pub count: u64,
+}
+
+impl CounterEventCtx {
+    pub fn new(source_id: String, count: u64) -> Self {
+        Self { source_id, count }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct RedisEventCtx {
+    pub source_id: String,
+    pub channel: String,
+    pub pattern: Option<String>,
+    pub entry_id: Option<String>,
+    pub payload: Option<Vec<u8>>,
+    pub fields: Vec<(String, Vec<u8>)>,
+}
+
+impl RedisEventCtx {
+    /// Context of a message published to a channel. Stream entries carry their
+    /// ID and fields rather than a payload
+    pub fn new(source_id: String, channel: String) -> Self {
+        Self {
+            source_id,
+            channel,
+            pattern: None,
+            entry_id: None,
+            payload: None,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn with_pattern(mut self, pattern: String) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    pub fn with_entry_id(mut self, entry_id: String) -> Self {
+        self.entry_id = Some(entry_id);
+        self
+    }
+
+    pub fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = Some(payload);
+        self
+    }
+
+    pub fn with_fields(mut self, fields: Vec<(String, Vec<u8>)>) -> Self {
+        self.fields = fields;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct NatsEventCtx {
+    pub source_id: String,
+    pub subject: String,
+    pub headers: Vec<(String, String)>,
+    pub payload: Vec<u8>,
+    pub sequence: Option<u64>,
+    pub timestamp: Option<i64>,
+}
+
+impl NatsEventCtx {
+    /// Context of a core NATS message without headers
+    pub fn new(source_id: String, subject: String, payload: Vec<u8>) -> Self {
+        Self {
+            source_id,
+            subject,
+            headers: Vec::new(),
+            payload,
+            sequence: None,
+            timestamp: None,
+        }
+    }
+
+    pub fn with_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Sets the stream sequence and timestamp of a JetStream message
+    pub fn with_sequence(mut self, sequence: u64, timestamp: i64) -> Self {
+        self.sequence = Some(sequence);
+        self.timestamp = Some(timestamp);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct MqttEventCtx {
+    pub source_id: String,
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub qos: u8,
+    pub retain: bool,
+}
+
+impl MqttEventCtx {
+    /// Context of a message delivered at QoS 0 that was not retained
+    pub fn new(source_id: String, topic: String, payload: Vec<u8>) -> Self {
+        Self {
+            source_id,
+            topic,
+            payload,
+            qos: 0,
+            retain: false,
+        }
+    }
+
+    pub fn with_qos(mut self, qos: u8) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    pub fn with_retain(mut self, retain: bool) -> Self {
+        self.retain = retain;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PostgresEventCtx {
+    pub source_id: String,
+    pub channel: String,
+    pub payload: Vec<u8>,
+    pub process_id: i32,
+}
+
+impl PostgresEventCtx {
+    pub fn new(source_id: String, channel: String, payload: Vec<u8>, process_id: i32) -> Self {
+        Self {
+            source_id,
+            channel,
+            payload,
+            process_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct CustomEventCtx {
+    pub source_id: String,
+    pub kind: String,
+    pub key: Option<Vec<u8>>,
+    pub metadata: BTreeMap<String, String>,
+    pub payload: Vec<u8>,
+    pub timestamp: i64,
+}
+
+impl CustomEventCtx {
+    /// Context of an event without a key or metadata
+    pub fn new(source_id: String, kind: String, payload: Vec<u8>, timestamp: i64) -> Self {
+        Self {
+            source_id,
+            kind,
+            key: None,
+            metadata: BTreeMap::new(),
+            payload,
+            timestamp,
+        }
+    }
+
+    pub fn with_key(mut self, key: Vec<u8>) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: BTreeMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
 }
 
 #[async_trait]
@@ -18,6 +18,11 @@ impl From<types::EventCtx> for EventCtx {
         match value {
             types::EventCtx::Kafka(ctx) => Self::Kafka(ctx.into()),
             types::EventCtx::Counter(ctx) => Self::Counter(ctx.into()),
+            types::EventCtx::Redis(ctx) => Self::Redis(ctx.into()),
+            types::EventCtx::Nats(ctx) => Self::Nats(ctx.into()),
+            types::EventCtx::Mqtt(ctx) => Self::Mqtt(ctx.into()),
+            types::EventCtx::Postgres(ctx) => Self::Postgres(ctx.into()),
+            types::EventCtx::Custom(ctx) => Self::Custom(ctx.into()),
         }
     }
 }
@@ -39,10 +44,11 @@ impl From<types::KafkaEventCtx> for KafkaEventCtx {
         let partition = try_conv_bail!(value.partition, "partition conversion must not fail");
         let offset = try_conv_bail!(value.offset, "offset conversion must not fail");
         Self {
+            key: value.key,
             payload: value.payload,
-            topic: value.topic.clone(),
-            // TODO: When Kafka sources include a custom source ID, use it here
-            source_id: value.topic,
+            headers: value.headers,
+            topic: value.topic,
+            source_id: value.source_id,
             timestamp,
             partition,
             offset,
@@ -50,6 +56,72 @@ impl From<types::KafkaEventCtx> for KafkaEventCtx {
     }
 }
 
+impl From<types::RedisEventCtx> for RedisEventCtx {
+    fn from(value: types::RedisEventCtx) -> Self {
+        Self {
+            source_id: value.source_id,
+            channel: value.channel,
+            pattern: value.pattern,
+            entry_id: value.entry_id,
+            payload: value.payload,
+            fields: value.fields,
+        }
+    }
+}
+
+impl From<types::NatsEventCtx> for NatsEventCtx {
+    fn from(value: types::NatsEventCtx) -> Self {
+        let timestamp: Option<u64> = value
+            .timestamp
+            .map(|ts| try_conv_bail!(ts, "timestamp conversion must not fail"));
+        Self {
+            source_id: value.source_id,
+            subject: value.subject,
+            headers: value.headers,
+            payload: value.payload,
+            sequence: value.sequence,
+            timestamp,
+        }
+    }
+}
+
+impl From<types::MqttEventCtx> for MqttEventCtx {
+    fn from(value: types::MqttEventCtx) -> Self {
+        Self {
+            source_id: value.source_id,
+            topic: value.topic,
+            payload: value.payload,
+            qos: value.qos,
+            retain: value.retain,
+        }
+    }
+}
+
+impl From<types::PostgresEventCtx> for PostgresEventCtx {
+    fn from(value: types::PostgresEventCtx) -> Self {
+        Self {
+            source_id: value.source_id,
+            channel: value.channel,
+            payload: value.payload,
+            process_id: value.process_id,
+        }
+    }
+}
+
+impl From<types::CustomEventCtx> for CustomEventCtx {
+    fn from(value: types::CustomEventCtx) -> Self {
+        let timestamp: u64 = try_conv_bail!(value.timestamp, "timestamp conversion must not fail");
+        Self {
+            source_id: value.source_id,
+            kind: value.kind,
+            key: value.key,
+            metadata: value.metadata.into_iter().collect(),
+            payload: value.payload,
+            timestamp,
+        }
+    }
+}
+
 impl From<types::ConnectionCtx> for ConnectionCtx {
     fn from(value: types::ConnectionCtx) -> Self {
         match value {
@@ -81,6 +153,11 @@ impl From<TransformedPayload> for types::TransformedPayload {
         match value {
             TransformedPayload::Kafka(payload) => Self::Kafka(payload),
             TransformedPayload::Counter(count) => Self::Counter(count),
+            TransformedPayload::Redis(payload) => Self::Redis(payload),
+            TransformedPayload::Nats(payload) => Self::Nats(payload),
+            TransformedPayload::Mqtt(payload) => Self::Mqtt(payload),
+            TransformedPayload::Postgres(payload) => Self::Postgres(payload),
+            TransformedPayload::Custom(payload) => Self::Custom(payload),
         }
     }
 }
@@ -1,3 +1,4 @@
 pub mod authenticate;
 pub mod intercept;
+pub mod source;
 pub mod wasm;
@@ -0,0 +1,30 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+
+/// An event emitted by a source plugin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub key: Option<Vec<u8>>,
+    /// Additional information about the event, such as headers
+    pub metadata: BTreeMap<String, String>,
+    pub payload: Vec<u8>,
+    /// Timestamp (in milliseconds since the Unix epoch) of the event, if the
+    /// plugin provided one
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PollResult {
+    /// The plugin has an event ready
+    Ready(Event),
+    /// The plugin has no event ready, and should be polled again later
+    Pending,
+    /// The source has ended
+    Done,
+}
+
+#[async_trait]
+pub trait SourcePlugin {
+    async fn poll(&mut self) -> anyhow::Result<PollResult>;
+}
@@ -0,0 +1,25 @@
+//! Bridge between WIT types and local plugin types
+
+use super::bindgen::kiwi::kiwi::source_types::{Event, PollResult};
+use crate::hook::source::types;
+
+impl From<Event> for types::Event {
+    fn from(value: Event) -> Self {
+        Self {
+            key: value.key,
+            metadata: value.metadata.into_iter().collect(),
+            payload: value.payload,
+            timestamp: value.timestamp,
+        }
+    }
+}
+
+impl From<PollResult> for types::PollResult {
+    fn from(value: PollResult) -> Self {
+        match value {
+            PollResult::Ready(event) => Self::Ready(event.into()),
+            PollResult::Pending => Self::Pending,
+            PollResult::Done => Self::Done,
+        }
+    }
+}
@@ -0,0 +1,2 @@
+pub mod bindgen;
+mod bridge;
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use async_trait::async_trait;
@@ -18,6 +19,9 @@ use super::authenticate::wasm::bindgen::AuthenticateHookPre;
 use super::intercept;
 use super::intercept::types::Intercept;
 use super::intercept::wasm::bindgen::InterceptHookPre;
+use super::source;
+use super::source::types::{PollResult, SourcePlugin};
+use super::source::wasm::bindgen::{SourceHook, SourceHookPre};
 
 static ENGINE: Lazy<Engine> = Lazy::new(|| {
     let mut config = Config::new();
@@ -68,6 +72,7 @@ impl WasiView for Host {
 
 impl authenticate::wasm::bindgen::kiwi::kiwi::authenticate_types::Host for WasiImpl<Host> {}
 impl intercept::wasm::bindgen::kiwi::kiwi::intercept_types::Host for Host {}
+impl source::wasm::bindgen::kiwi::kiwi::source_types::Host for WasiImpl<Host> {}
 
 pub(super) fn get_linker() -> anyhow::Result<Linker<Host>> {
     let mut linker = Linker::new(&ENGINE);
@@ -134,6 +139,71 @@ impl WasmHook for WasmInterceptHook {
     }
 }
 
+pub struct WasmSourceHook {
+    instance_pre: SourceHookPre<Host>,
+    path: std::path::PathBuf,
+}
+
+impl WasmHook for WasmSourceHook {
+    fn from_file<P: AsRef<Path>>(file: P) -> anyhow::Result<Self> {
+        let path = file.as_ref().to_path_buf();
+        let instance_pre = create_instance_pre(file)?;
+        let instance_pre = SourceHookPre::new(instance_pre)?;
+
+        Ok(Self { instance_pre, path })
+    }
+
+    fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+}
+
+impl WasmSourceHook {
+    /// Instantiates the plugin, exposing the given environment variables to it.
+    /// Unlike other hooks, a source plugin keeps its instance (and therefore its
+    /// state) between calls
+    pub async fn instantiate(
+        &self,
+        env: &BTreeMap<String, String>,
+    ) -> anyhow::Result<WasmSourceInstance> {
+        let mut builder = WasiCtxBuilder::new();
+
+        builder.stdout(Stdout);
+
+        for (key, value) in env {
+            builder.env(key, value);
+        }
+
+        let mut store = Store::new(
+            &ENGINE,
+            Host {
+                table: ResourceTable::new(),
+                wasi: builder.build(),
+                http: WasiHttpCtx::new(),
+            },
+        );
+
+        let bindings = self.instance_pre.instantiate_async(&mut store).await?;
+
+        Ok(WasmSourceInstance { store, bindings })
+    }
+}
+
+/// A running instance of a source plugin
+pub struct WasmSourceInstance {
+    store: Store<Host>,
+    bindings: SourceHook,
+}
+
+#[async_trait]
+impl SourcePlugin for WasmSourceInstance {
+    async fn poll(&mut self) -> anyhow::Result<PollResult> {
+        let res = self.bindings.call_poll(&mut self.store).await?;
+
+        Ok(res.into())
+    }
+}
+
 #[async_trait]
 impl Authenticate for WasmAuthenticateHook {
     async fn authenticate(&self, request: HttpRequest<()>) -> anyhow::Result<Outcome> {
@@ -1,7 +1,9 @@
 pub mod config;
 pub mod connection;
+pub mod decode;
 pub mod hook;
 pub mod protocol;
+pub mod server;
 pub mod source;
 pub mod subscription;
 pub mod tls;
@@ -1,3 +1,6 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -14,6 +17,65 @@ pub enum SubscriptionMode {
     Push,
 }
 
+/// How the key and payload of an event are represented in `RESULT` messages
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadEncoding {
+    /// Base64 encoded strings
+    #[default]
+    Base64,
+    /// UTF-8 strings
+    Utf8,
+    /// Payloads are embedded as JSON values, while keys are UTF-8 strings
+    Json,
+}
+
+impl PayloadEncoding {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PayloadEncoding::Base64 => "base64",
+            PayloadEncoding::Utf8 => "utf8",
+            PayloadEncoding::Json => "json",
+        }
+    }
+
+    /// Represents the given bytes as a JSON value according to this encoding.
+    /// Only payloads are parsed as JSON
+    fn encode(&self, bytes: &[u8], is_payload: bool) -> Result<serde_json::Value, String> {
+        match self {
+            PayloadEncoding::Base64 => Ok(serde_json::Value::String(
+                base64::engine::general_purpose::STANDARD.encode(bytes),
+            )),
+            PayloadEncoding::Json if is_payload => {
+                serde_json::from_slice(bytes).map_err(|err| format!("invalid JSON: {}", err))
+            }
+            PayloadEncoding::Utf8 | PayloadEncoding::Json => std::str::from_utf8(bytes)
+                .map(|s| serde_json::Value::String(s.to_string()))
+                .map_err(|err| format!("invalid UTF-8: {}", err)),
+        }
+    }
+
+    /// Recovers the bytes represented by a JSON value produced by
+    /// [`PayloadEncoding::encode`]. JSON payloads are serialized compactly, so
+    /// their formatting may differ from that of the original bytes
+    fn decode(&self, value: serde_json::Value, is_payload: bool) -> Result<Vec<u8>, String> {
+        match (self, value) {
+            (PayloadEncoding::Json, value) if is_payload => {
+                serde_json::to_vec(&value).map_err(|err| err.to_string())
+            }
+            (PayloadEncoding::Base64, serde_json::Value::String(value)) => {
+                base64::engine::general_purpose::STANDARD
+                    .decode(value)
+                    .map_err(|err| format!("invalid base64: {}", err))
+            }
+            (PayloadEncoding::Utf8 | PayloadEncoding::Json, serde_json::Value::String(value)) => {
+                Ok(value.into_bytes())
+            }
+            (_, value) => Err(format!("expected a string, found {}", value)),
+        }
+    }
+}
+
 /// Commands are issued by kiwi clients to the server
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(tag = "type")]
@@ -27,6 +89,24 @@ pub enum Command {
         /// The subscription mode to use
         #[serde(default)]
         mode: SubscriptionMode,
+        /// Replay historic events starting from the specified offset (inclusive)
+        /// of each partition before receiving live events. Only supported by
+        /// Kafka sources
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "deserialize_partition_offsets"
+        )]
+        from_offsets: Option<BTreeMap<i32, i64>>,
+        /// Replay historic events produced within the last `lookback_ms`
+        /// milliseconds before receiving live events. Only supported by Kafka
+        /// sources
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lookback_ms: Option<u64>,
+        /// Only receive events produced from the specified partitions. Only
+        /// supported by Kafka sources
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        partitions: Option<BTreeSet<i32>>,
     },
     /// Unsubscribe from the specified source
     #[serde(rename_all = "camelCase")]
@@ -46,6 +126,33 @@ pub enum Command {
     },
 }
 
+/// Deserializes a map of partition ID -> offset. Since commands are internally tagged,
+/// JSON object keys reach us as strings and must be parsed explicitly
+fn deserialize_partition_offsets<'de, D>(
+    deserializer: D,
+) -> Result<Option<BTreeMap<i32, i64>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let offsets: Option<BTreeMap<String, i64>> = Option::deserialize(deserializer)?;
+
+    offsets
+        .map(|offsets| {
+            offsets
+                .into_iter()
+                .map(|(partition, offset)| {
+                    partition
+                        .parse::<i32>()
+                        .map(|partition| (partition, offset))
+                        .map_err(|_| {
+                            serde::de::Error::custom(format!("invalid partition ID: {}", partition))
+                        })
+                })
+                .collect()
+        })
+        .transpose()
+}
+
 /// Command responses are issued by the server to clients in response to
 /// commands
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -92,6 +199,32 @@ pub enum Notice {
         source_id: SourceId,
         message: Option<String>,
     },
+    /// Indicates that an event could not be represented using the payload
+    /// encoding configured for the source. The event is instead delivered
+    /// with a base64 encoded key and payload
+    #[serde(rename_all = "camelCase")]
+    PayloadEncodingFallback {
+        source_id: SourceId,
+        message: String,
+    },
+    /// Indicates that the connectivity of the source to its upstream system
+    /// has changed. Subscriptions remain open while the source is degraded
+    #[serde(rename_all = "camelCase")]
+    SourceStatus {
+        source_id: SourceId,
+        status: SourceStatus,
+        message: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SourceStatus {
+    /// The source is unable to reach its upstream system. Events may be
+    /// delayed until the source recovers
+    Degraded,
+    /// The source has reestablished connectivity to its upstream system
+    Recovered,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -115,14 +248,20 @@ impl From<source::SourceResult> for Message {
 pub enum SourceResult {
     #[serde(rename_all = "camelCase")]
     Kafka {
-        #[serde(with = "crate::util::serde::base64")]
-        /// Event key
-        key: Option<Vec<u8>>,
-        #[serde(with = "crate::util::serde::base64")]
-        /// base64 encoded event payload
-        payload: Option<Vec<u8>>,
+        /// Event key, represented according to `payload_encoding`
+        key: Option<serde_json::Value>,
+        /// Event payload, represented according to `payload_encoding`
+        payload: Option<serde_json::Value>,
+        /// Encoding used to represent the event key and payload
+        #[serde(default)]
+        payload_encoding: PayloadEncoding,
+        #[serde(default)]
+        /// Event headers, in the order they were produced
+        headers: Vec<KafkaHeader>,
         /// Source ID this event was produced from
         source_id: SourceId,
+        /// Topic this event was produced from
+        topic: String,
         /// Timestamp at which the message was produced
         timestamp: Option<i64>,
         /// Partition ID this event was produced from
@@ -137,25 +276,500 @@ pub enum SourceResult {
         /// Event count
         count: u64,
     },
+    #[serde(rename_all = "camelCase")]
+    Redis {
+        /// Source ID this event was produced from
+        source_id: SourceId,
+        /// Pub/sub channel the message was published to, or key of the stream
+        /// the entry was read from
+        channel: String,
+        /// Pattern matching the channel, for messages received via a pattern
+        /// subscription
+        pattern: Option<String>,
+        /// ID of the stream entry
+        entry_id: Option<String>,
+        /// Pub/sub message payload, represented according to `payload_encoding`
+        payload: Option<serde_json::Value>,
+        /// Field-value pairs of the stream entry, in order
+        #[serde(default)]
+        fields: Vec<RedisField>,
+        /// Encoding used to represent the payload and field values
+        #[serde(default)]
+        payload_encoding: PayloadEncoding,
+    },
+    #[serde(rename_all = "camelCase")]
+    Nats {
+        /// Source ID this event was produced from
+        source_id: SourceId,
+        /// Subject the message was published to
+        subject: String,
+        /// Message headers, sorted by key
+        #[serde(default)]
+        headers: Vec<NatsHeader>,
+        /// Message payload, represented according to `payload_encoding`
+        payload: serde_json::Value,
+        /// Encoding used to represent the payload
+        #[serde(default)]
+        payload_encoding: PayloadEncoding,
+        /// Sequence of the message within its JetStream stream
+        sequence: Option<u64>,
+        /// Timestamp at which JetStream stored the message
+        timestamp: Option<i64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Mqtt {
+        /// Source ID this event was produced from
+        source_id: SourceId,
+        /// Topic the message was published to
+        topic: String,
+        /// Message payload, represented according to `payload_encoding`
+        payload: serde_json::Value,
+        /// Encoding used to represent the payload
+        #[serde(default)]
+        payload_encoding: PayloadEncoding,
+        /// QoS level the message was delivered with
+        qos: u8,
+        /// Whether the message was retained by the broker
+        retain: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    Postgres {
+        /// Source ID this event was produced from
+        source_id: SourceId,
+        /// Channel the notification was sent to
+        channel: String,
+        /// Notification payload, represented according to `payload_encoding`
+        payload: serde_json::Value,
+        /// Encoding used to represent the payload
+        #[serde(default)]
+        payload_encoding: PayloadEncoding,
+        /// Process ID of the server backend that sent the notification
+        process_id: i32,
+    },
+    /// An event from a source without a dedicated result type
+    #[serde(rename_all = "camelCase")]
+    Custom {
+        /// Source ID this event was produced from
+        source_id: SourceId,
+        /// Type of the source this event was produced from
+        kind: String,
+        /// Event key, represented according to `payload_encoding`
+        key: Option<serde_json::Value>,
+        /// Additional information about the event, such as headers
+        #[serde(default)]
+        metadata: BTreeMap<String, String>,
+        /// Event payload, represented according to `payload_encoding`
+        payload: serde_json::Value,
+        /// Encoding used to represent the event key and payload
+        #[serde(default)]
+        payload_encoding: PayloadEncoding,
+        /// Timestamp of the event
+        timestamp: i64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+/// A Kafka event header
+pub struct KafkaHeader {
+    /// Header key
+    pub key: String,
+    #[serde(with = "crate::util::serde::base64")]
+    /// base64 encoded header value
+    pub value: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+/// A field of a Redis stream entry
+pub struct RedisField {
+    /// Field name
+    pub key: String,
+    /// Field value, represented according to the payload encoding of the source
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+/// A NATS message header
+pub struct NatsHeader {
+    /// Header key
+    pub key: String,
+    /// Header value
+    pub value: String,
 }
 
 impl From<source::SourceResult> for SourceResult {
     fn from(value: source::SourceResult) -> Self {
+        Self::encode(value).0
+    }
+}
+
+/// Encodes the parts of an event with the payload encoding of its source. If
+/// the event cannot be represented using that encoding, it is encoded as base64
+/// instead and the reason, naming the event described by `event`, is returned
+/// alongside it
+fn encode_with_fallback<T>(
+    encoding: PayloadEncoding,
+    encode: impl Fn(PayloadEncoding) -> Result<T, String>,
+    event: impl FnOnce() -> String,
+) -> (PayloadEncoding, T, Option<String>) {
+    match encode(encoding) {
+        Ok(encoded) => (encoding, encoded, None),
+        Err(err) => (
+            PayloadEncoding::Base64,
+            encode(PayloadEncoding::Base64).expect("base64 encoding must not fail"),
+            Some(format!(
+                "{} could not be encoded as {} ({}). Falling back to base64",
+                event(),
+                encoding.as_str(),
+                err
+            )),
+        ),
+    }
+}
+
+fn encode_payload(encoding: PayloadEncoding, payload: &[u8]) -> Result<serde_json::Value, String> {
+    encoding
+        .encode(payload, true)
+        .map_err(|err| format!("payload is {}", err))
+}
+
+impl SourceResult {
+    /// Converts a source result into its wire representation. If the event cannot be
+    /// represented using the payload encoding of its source, its key and payload are
+    /// base64 encoded instead and the reason is returned alongside the result
+    pub fn encode(value: source::SourceResult) -> (Self, Option<String>) {
         match value {
-            source::SourceResult::Kafka(kafka) => Self::Kafka {
-                key: kafka.key,
-                payload: kafka.payload,
-                source_id: kafka.id,
-                partition: kafka.partition,
-                offset: kafka.offset,
-                timestamp: kafka.timestamp,
-            },
-            source::SourceResult::Counter(counter) => Self::Counter {
-                source_id: counter.source_id,
-                count: counter.count,
-            },
+            source::SourceResult::Kafka(kafka) => {
+                let (payload_encoding, (key, payload), fallback) = encode_with_fallback(
+                    kafka.payload_encoding,
+                    |encoding| {
+                        let key = kafka
+                            .key
+                            .as_deref()
+                            .map(|key| encoding.encode(key, false))
+                            .transpose()
+                            .map_err(|err| format!("key is {}", err))?;
+                        let payload = kafka
+                            .payload
+                            .as_deref()
+                            .map(|payload| encode_payload(encoding, payload))
+                            .transpose()?;
+
+                        Ok((key, payload))
+                    },
+                    || {
+                        format!(
+                            "Event at partition {} offset {}",
+                            kafka.partition, kafka.offset
+                        )
+                    },
+                );
+
+                (
+                    Self::Kafka {
+                        key,
+                        payload,
+                        payload_encoding,
+                        headers: kafka
+                            .headers
+                            .into_iter()
+                            .map(|(key, value)| KafkaHeader { key, value })
+                            .collect(),
+                        source_id: kafka.id,
+                        topic: kafka.topic,
+                        partition: kafka.partition,
+                        offset: kafka.offset,
+                        timestamp: kafka.timestamp,
+                    },
+                    fallback,
+                )
+            }
+            source::SourceResult::Counter(counter) => (
+                Self::Counter {
+                    source_id: counter.source_id,
+                    count: counter.count,
+                },
+                None,
+            ),
+            source::SourceResult::Redis(redis) => {
+                let (payload_encoding, (payload, fields), fallback) = encode_with_fallback(
+                    redis.payload_encoding,
+                    |encoding| {
+                        let payload = redis
+                            .payload
+                            .as_deref()
+                            .map(|payload| encode_payload(encoding, payload))
+                            .transpose()?;
+                        let fields = redis
+                            .fields
+                            .iter()
+                            .map(|(key, value)| {
+                                Ok(RedisField {
+                                    key: key.clone(),
+                                    value: encoding
+                                        .encode(value, true)
+                                        .map_err(|err| format!("field {} is {}", key, err))?,
+                                })
+                            })
+                            .collect::<Result<Vec<_>, String>>()?;
+
+                        Ok((payload, fields))
+                    },
+                    || match &redis.entry_id {
+                        Some(entry_id) => format!("Entry {} of stream {}", entry_id, redis.channel),
+                        None => format!("Message published to {}", redis.channel),
+                    },
+                );
+
+                (
+                    Self::Redis {
+                        source_id: redis.source_id,
+                        channel: redis.channel,
+                        pattern: redis.pattern,
+                        entry_id: redis.entry_id,
+                        payload,
+                        fields,
+                        payload_encoding,
+                    },
+                    fallback,
+                )
+            }
+            source::SourceResult::Nats(nats) => {
+                let (payload_encoding, payload, fallback) = encode_with_fallback(
+                    nats.payload_encoding,
+                    |encoding| encode_payload(encoding, &nats.payload),
+                    || format!("Message published to {}", nats.subject),
+                );
+
+                (
+                    Self::Nats {
+                        source_id: nats.source_id,
+                        subject: nats.subject,
+                        headers: nats
+                            .headers
+                            .into_iter()
+                            .map(|(key, value)| NatsHeader { key, value })
+                            .collect(),
+                        payload,
+                        payload_encoding,
+                        sequence: nats.sequence,
+                        timestamp: nats.timestamp,
+                    },
+                    fallback,
+                )
+            }
+            source::SourceResult::Mqtt(mqtt) => {
+                let (payload_encoding, payload, fallback) = encode_with_fallback(
+                    mqtt.payload_encoding,
+                    |encoding| encode_payload(encoding, &mqtt.payload),
+                    || format!("Message published to {}", mqtt.topic),
+                );
+
+                (
+                    Self::Mqtt {
+                        source_id: mqtt.source_id,
+                        topic: mqtt.topic,
+                        payload,
+                        payload_encoding,
+                        qos: mqtt.qos,
+                        retain: mqtt.retain,
+                    },
+                    fallback,
+                )
+            }
+            source::SourceResult::Postgres(postgres) => {
+                let (payload_encoding, payload, fallback) = encode_with_fallback(
+                    postgres.payload_encoding,
+                    |encoding| encode_payload(encoding, &postgres.payload),
+                    || format!("Notification sent to {}", postgres.channel),
+                );
+
+                (
+                    Self::Postgres {
+                        source_id: postgres.source_id,
+                        channel: postgres.channel,
+                        payload,
+                        payload_encoding,
+                        process_id: postgres.process_id,
+                    },
+                    fallback,
+                )
+            }
+            source::SourceResult::Custom(custom) => {
+                let (payload_encoding, (key, payload), fallback) = encode_with_fallback(
+                    custom.payload_encoding,
+                    |encoding| {
+                        let key = custom
+                            .key
+                            .as_deref()
+                            .map(|key| encoding.encode(key, false))
+                            .transpose()
+                            .map_err(|err| format!("key is {}", err))?;
+                        let payload = encode_payload(encoding, &custom.payload)?;
+
+                        Ok((key, payload))
+                    },
+                    || format!("Event emitted at {}", custom.timestamp),
+                );
+
+                (
+                    Self::Custom {
+                        source_id: custom.source_id,
+                        kind: custom.kind,
+                        key,
+                        metadata: custom.metadata,
+                        payload,
+                        payload_encoding,
+                        timestamp: custom.timestamp,
+                    },
+                    fallback,
+                )
+            }
         }
     }
+
+    /// Recovers a source result from its wire representation, attributing it to
+    /// the source with the specified ID. This allows results received from
+    /// another Kiwi instance to be delivered as if they were produced locally
+    pub fn decode(self, source_id: SourceId) -> Result<source::SourceResult, String> {
+        let decode_payload = |encoding: PayloadEncoding, payload: serde_json::Value| {
+            encoding
+                .decode(payload, true)
+                .map_err(|err| format!("payload is {}", err))
+        };
+
+        Ok(match self {
+            Self::Kafka {
+                key,
+                payload,
+                payload_encoding,
+                headers,
+                topic,
+                timestamp,
+                partition,
+                offset,
+                ..
+            } => source::SourceResult::Kafka(source::kafka::KafkaSourceResult {
+                id: source_id,
+                key: key
+                    .map(|key| payload_encoding.decode(key, false))
+                    .transpose()
+                    .map_err(|err| format!("key is {}", err))?,
+                payload: payload
+                    .map(|payload| decode_payload(payload_encoding, payload))
+                    .transpose()?,
+                headers: headers
+                    .into_iter()
+                    .map(|header| (header.key, header.value))
+                    .collect(),
+                topic,
+                timestamp,
+                partition,
+                offset,
+                payload_encoding,
+            }),
+            Self::Counter { count, .. } => {
+                source::SourceResult::Counter(source::counter::CounterSourceResult {
+                    source_id,
+                    count,
+                })
+            }
+            Self::Redis {
+                channel,
+                pattern,
+                entry_id,
+                payload,
+                fields,
+                payload_encoding,
+                ..
+            } => source::SourceResult::Redis(source::redis::RedisSourceResult {
+                source_id,
+                channel,
+                pattern,
+                entry_id,
+                payload: payload
+                    .map(|payload| decode_payload(payload_encoding, payload))
+                    .transpose()?,
+                fields: fields
+                    .into_iter()
+                    .map(|field| {
+                        payload_encoding
+                            .decode(field.value, true)
+                            .map(|value| (field.key.clone(), value))
+                            .map_err(|err| format!("field {} is {}", field.key, err))
+                    })
+                    .collect::<Result<_, _>>()?,
+                payload_encoding,
+            }),
+            Self::Nats {
+                subject,
+                headers,
+                payload,
+                payload_encoding,
+                sequence,
+                timestamp,
+                ..
+            } => source::SourceResult::Nats(source::nats::NatsSourceResult {
+                source_id,
+                subject,
+                headers: headers
+                    .into_iter()
+                    .map(|header| (header.key, header.value))
+                    .collect(),
+                payload: decode_payload(payload_encoding, payload)?,
+                sequence,
+                timestamp,
+                payload_encoding,
+            }),
+            Self::Mqtt {
+                topic,
+                payload,
+                payload_encoding,
+                qos,
+                retain,
+                ..
+            } => source::SourceResult::Mqtt(source::mqtt::MqttSourceResult {
+                source_id,
+                topic,
+                payload: decode_payload(payload_encoding, payload)?,
+                qos,
+                retain,
+                payload_encoding,
+            }),
+            Self::Postgres {
+                channel,
+                payload,
+                payload_encoding,
+                process_id,
+                ..
+            } => source::SourceResult::Postgres(source::postgres::PostgresSourceResult {
+                source_id,
+                channel,
+                payload: decode_payload(payload_encoding, payload)?,
+                process_id,
+                payload_encoding,
+            }),
+            Self::Custom {
+                kind,
+                key,
+                metadata,
+                payload,
+                payload_encoding,
+                timestamp,
+                ..
+            } => source::SourceResult::Custom(source::CustomSourceResult {
+                source_id,
+                kind,
+                key: key
+                    .map(|key| payload_encoding.decode(key, false))
+                    .transpose()
+                    .map_err(|err| format!("key is {}", err))?,
+                metadata,
+                payload: decode_payload(payload_encoding, payload)?,
+                timestamp,
+                payload_encoding,
+            }),
+        })
+    }
 }
 
 #[derive(Debug, Error)]
@@ -171,6 +785,434 @@ mod tests {
     use super::*;
     use base64::Engine;
 
+    fn kafka_source_result(
+        key: Option<&[u8]>,
+        payload: Option<&[u8]>,
+        headers: Vec<(String, Option<Vec<u8>>)>,
+        payload_encoding: PayloadEncoding,
+    ) -> source::SourceResult {
+        source::SourceResult::Kafka(source::kafka::KafkaSourceResult {
+            id: "test".into(),
+            key: key.map(|k| k.to_vec()),
+            payload: payload.map(|p| p.to_vec()),
+            headers,
+            topic: "test".into(),
+            timestamp: None,
+            partition: 0,
+            offset: 1,
+            payload_encoding,
+        })
+    }
+
+    #[test]
+    fn test_source_result_payload_encoding() {
+        let payload = |result: &SourceResult| match result {
+            SourceResult::Kafka {
+                key,
+                payload,
+                payload_encoding,
+                ..
+            } => (key.clone(), payload.clone(), *payload_encoding),
+            _ => unreachable!(),
+        };
+
+        let (result, fallback) = SourceResult::encode(kafka_source_result(
+            Some(b"user-1"),
+            Some(br#"{"count":1}"#),
+            Vec::new(),
+            PayloadEncoding::Json,
+        ));
+
+        assert!(fallback.is_none());
+        assert_eq!(
+            payload(&result),
+            (
+                Some(serde_json::json!("user-1")),
+                Some(serde_json::json!({ "count": 1 })),
+                PayloadEncoding::Json
+            )
+        );
+
+        let (result, fallback) = SourceResult::encode(kafka_source_result(
+            Some(b"user-1"),
+            Some(br#"{"count":1}"#),
+            Vec::new(),
+            PayloadEncoding::Utf8,
+        ));
+
+        assert!(fallback.is_none());
+        assert_eq!(
+            payload(&result),
+            (
+                Some(serde_json::json!("user-1")),
+                Some(serde_json::json!(r#"{"count":1}"#)),
+                PayloadEncoding::Utf8
+            )
+        );
+
+        // Payloads that cannot be decoded fall back to base64
+        let (result, fallback) = SourceResult::encode(kafka_source_result(
+            Some(b"user-1"),
+            Some(b"not json"),
+            Vec::new(),
+            PayloadEncoding::Json,
+        ));
+
+        assert!(fallback.is_some());
+        assert_eq!(
+            payload(&result),
+            (
+                Some(serde_json::json!("dXNlci0x")),
+                Some(serde_json::json!("bm90IGpzb24=")),
+                PayloadEncoding::Base64
+            )
+        );
+
+        let (result, fallback) = SourceResult::encode(kafka_source_result(
+            Some(&[0xff]),
+            None,
+            Vec::new(),
+            PayloadEncoding::Utf8,
+        ));
+
+        assert!(fallback.is_some());
+        assert_eq!(
+            payload(&result),
+            (
+                Some(serde_json::json!("/w==")),
+                None,
+                PayloadEncoding::Base64
+            )
+        );
+    }
+
+    #[test]
+    fn test_redis_source_result_payload_encoding() {
+        let redis_source_result = |payload: Option<&[u8]>, fields: Vec<(&str, &[u8])>| {
+            source::SourceResult::Redis(source::redis::RedisSourceResult {
+                source_id: "test".into(),
+                channel: "orders".into(),
+                pattern: None,
+                entry_id: Some("1-0".into()),
+                payload: payload.map(|p| p.to_vec()),
+                fields: fields
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), value.to_vec()))
+                    .collect(),
+                payload_encoding: PayloadEncoding::Json,
+            })
+        };
+
+        let (result, fallback) =
+            SourceResult::encode(redis_source_result(None, vec![("order", br#"{"id":1}"#)]));
+
+        assert!(fallback.is_none());
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            serde_json::json!({
+                "sourceType": "redis",
+                "sourceId": "test",
+                "channel": "orders",
+                "pattern": null,
+                "entryId": "1-0",
+                "payload": null,
+                "fields": [{ "key": "order", "value": { "id": 1 } }],
+                "payloadEncoding": "json",
+            })
+        );
+
+        // A single field that cannot be decoded causes the whole event to fall back
+        let (result, fallback) = SourceResult::encode(redis_source_result(
+            Some(b"1"),
+            vec![("order", br#"{"id":1}"#), ("note", b"not json")],
+        ));
+
+        assert!(fallback.unwrap().contains("Entry 1-0 of stream orders"));
+        assert!(matches!(
+            result,
+            SourceResult::Redis {
+                payload: Some(payload),
+                fields,
+                payload_encoding: PayloadEncoding::Base64,
+                ..
+            } if payload == serde_json::json!("MQ==") && fields[1].value == serde_json::json!("bm90IGpzb24=")
+        ));
+    }
+
+    #[test]
+    fn test_nats_source_result_payload_encoding() {
+        let nats_source_result = |payload: &[u8]| {
+            source::SourceResult::Nats(source::nats::NatsSourceResult {
+                source_id: "test".into(),
+                subject: "orders.eu".into(),
+                headers: vec![("Trace".into(), "abc".into())],
+                payload: payload.to_vec(),
+                sequence: Some(42),
+                timestamp: Some(1_700_000_000_000),
+                payload_encoding: PayloadEncoding::Json,
+            })
+        };
+
+        let (result, fallback) = SourceResult::encode(nats_source_result(br#"{"id":1}"#));
+
+        assert!(fallback.is_none());
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            serde_json::json!({
+                "sourceType": "nats",
+                "sourceId": "test",
+                "subject": "orders.eu",
+                "headers": [{ "key": "Trace", "value": "abc" }],
+                "payload": { "id": 1 },
+                "payloadEncoding": "json",
+                "sequence": 42,
+                "timestamp": 1_700_000_000_000i64,
+            })
+        );
+
+        let (result, fallback) = SourceResult::encode(nats_source_result(b"not json"));
+
+        assert!(fallback.is_some());
+        assert!(matches!(
+            result,
+            SourceResult::Nats {
+                payload,
+                payload_encoding: PayloadEncoding::Base64,
+                ..
+            } if payload == serde_json::json!("bm90IGpzb24=")
+        ));
+    }
+
+    #[test]
+    fn test_mqtt_source_result_payload_encoding() {
+        let mqtt_source_result = |payload: &[u8]| {
+            source::SourceResult::Mqtt(source::mqtt::MqttSourceResult {
+                source_id: "test".into(),
+                topic: "devices/1/telemetry".into(),
+                payload: payload.to_vec(),
+                qos: 1,
+                retain: true,
+                payload_encoding: PayloadEncoding::Json,
+            })
+        };
+
+        let (result, fallback) = SourceResult::encode(mqtt_source_result(br#"{"temp":21}"#));
+
+        assert!(fallback.is_none());
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            serde_json::json!({
+                "sourceType": "mqtt",
+                "sourceId": "test",
+                "topic": "devices/1/telemetry",
+                "payload": { "temp": 21 },
+                "payloadEncoding": "json",
+                "qos": 1,
+                "retain": true,
+            })
+        );
+
+        let (result, fallback) = SourceResult::encode(mqtt_source_result(b"not json"));
+
+        assert!(fallback.unwrap().contains("devices/1/telemetry"));
+        assert!(matches!(
+            result,
+            SourceResult::Mqtt {
+                payload,
+                payload_encoding: PayloadEncoding::Base64,
+                ..
+            } if payload == serde_json::json!("bm90IGpzb24=")
+        ));
+    }
+
+    #[test]
+    fn test_postgres_source_result_payload_encoding() {
+        let postgres_source_result = |payload: &[u8]| {
+            source::SourceResult::Postgres(source::postgres::PostgresSourceResult {
+                source_id: "test".into(),
+                channel: "orders".into(),
+                payload: payload.to_vec(),
+                process_id: 12345,
+                payload_encoding: PayloadEncoding::Json,
+            })
+        };
+
+        let (result, fallback) = SourceResult::encode(postgres_source_result(br#"{"id":1}"#));
+
+        assert!(fallback.is_none());
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            serde_json::json!({
+                "sourceType": "postgres",
+                "sourceId": "test",
+                "channel": "orders",
+                "payload": { "id": 1 },
+                "payloadEncoding": "json",
+                "processId": 12345,
+            })
+        );
+
+        let (result, fallback) = SourceResult::encode(postgres_source_result(b"not json"));
+
+        assert!(fallback.unwrap().contains("Notification sent to orders"));
+        assert!(matches!(
+            result,
+            SourceResult::Postgres {
+                payload,
+                payload_encoding: PayloadEncoding::Base64,
+                ..
+            } if payload == serde_json::json!("bm90IGpzb24=")
+        ));
+    }
+
+    #[test]
+    fn test_custom_source_result_payload_encoding() {
+        let custom_source_result = |key: &[u8], payload: &[u8]| {
+            source::SourceResult::Custom(source::CustomSourceResult {
+                source_id: "test".into(),
+                kind: "plugin".into(),
+                key: Some(key.to_vec()),
+                metadata: BTreeMap::from([("region".into(), "eu".into())]),
+                payload: payload.to_vec(),
+                timestamp: 1700000000000,
+                payload_encoding: PayloadEncoding::Json,
+            })
+        };
+
+        let (result, fallback) = SourceResult::encode(custom_source_result(b"k", br#"{"n":1}"#));
+
+        assert!(fallback.is_none());
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            serde_json::json!({
+                "sourceType": "custom",
+                "sourceId": "test",
+                "kind": "plugin",
+                "key": "k",
+                "metadata": { "region": "eu" },
+                "payload": { "n": 1 },
+                "payloadEncoding": "json",
+                "timestamp": 1700000000000i64,
+            })
+        );
+
+        // Falling back to base64 applies to both the key and the payload
+        let (result, fallback) = SourceResult::encode(custom_source_result(b"k", b"not json"));
+
+        assert!(fallback.unwrap().contains("Event emitted at 1700000000000"));
+        assert!(matches!(
+            result,
+            SourceResult::Custom {
+                key: Some(key),
+                payload,
+                payload_encoding: PayloadEncoding::Base64,
+                ..
+            } if key == serde_json::json!("aw==") && payload == serde_json::json!("bm90IGpzb24=")
+        ));
+    }
+
+    #[test]
+    fn test_decodes_source_results() {
+        let roundtrip = |result: source::SourceResult| {
+            let (encoded, _) = SourceResult::encode(result);
+            // Results are decoded from their wire representation
+            let encoded: SourceResult =
+                serde_json::from_value(serde_json::to_value(&encoded).unwrap()).unwrap();
+
+            encoded.decode("edge".into()).unwrap()
+        };
+
+        let kafka_source_result =
+            |payload: &[u8], payload_encoding| source::kafka::KafkaSourceResult {
+                id: "test".into(),
+                key: Some(b"key".to_vec()),
+                payload: Some(payload.to_vec()),
+                headers: vec![
+                    ("trace".into(), Some(b"abc".to_vec())),
+                    ("empty".into(), None),
+                ],
+                topic: "topic".into(),
+                timestamp: Some(1700000000000),
+                partition: 2,
+                offset: 7,
+                payload_encoding,
+            };
+
+        for (payload, payload_encoding) in [
+            (br#"{"a":1}"#.as_slice(), PayloadEncoding::Json),
+            (b"hello".as_slice(), PayloadEncoding::Utf8),
+            (b"\xff\x00".as_slice(), PayloadEncoding::Base64),
+        ] {
+            assert_eq!(
+                roundtrip(source::SourceResult::Kafka(kafka_source_result(
+                    payload,
+                    payload_encoding
+                ))),
+                source::SourceResult::Kafka(source::kafka::KafkaSourceResult {
+                    id: "edge".into(),
+                    ..kafka_source_result(payload, payload_encoding)
+                })
+            );
+        }
+
+        // Payloads that fell back to base64 are delivered as such
+        assert!(matches!(
+            roundtrip(source::SourceResult::Kafka(kafka_source_result(
+                b"not json",
+                PayloadEncoding::Json
+            ))),
+            source::SourceResult::Kafka(source::kafka::KafkaSourceResult {
+                payload: Some(payload),
+                payload_encoding: PayloadEncoding::Base64,
+                ..
+            }) if payload == b"not json"
+        ));
+
+        assert_eq!(
+            roundtrip(source::SourceResult::Counter(
+                source::counter::CounterSourceResult {
+                    source_id: "test".into(),
+                    count: 3,
+                }
+            )),
+            source::SourceResult::Counter(source::counter::CounterSourceResult {
+                source_id: "edge".into(),
+                count: 3,
+            })
+        );
+
+        let custom_source_result = source::CustomSourceResult {
+            source_id: "test".into(),
+            kind: "plugin".into(),
+            key: Some(b"key".to_vec()),
+            metadata: BTreeMap::from([("region".into(), "eu".into())]),
+            payload: vec![0, 159, 146, 150],
+            timestamp: 1700000000000,
+            payload_encoding: PayloadEncoding::Base64,
+        };
+
+        assert_eq!(
+            roundtrip(source::SourceResult::Custom(custom_source_result.clone())),
+            source::SourceResult::Custom(source::CustomSourceResult {
+                source_id: "edge".into(),
+                ..custom_source_result
+            })
+        );
+
+        // Payloads must match their declared encoding
+        let invalid: SourceResult = serde_json::from_value(serde_json::json!({
+            "sourceType": "custom",
+            "kind": "http",
+            "sourceId": "test",
+            "payload": "%%%",
+            "payloadEncoding": "base64",
+            "timestamp": 0,
+        }))
+        .unwrap();
+
+        assert!(invalid.decode("edge".into()).is_err());
+    }
+
     #[test]
     fn test_command_de() {
         let command = r#"{"type":"SUBSCRIBE","sourceId":"test"}"#;
@@ -179,7 +1221,49 @@ mod tests {
             deserialized,
             Command::Subscribe {
                 source_id: "test".into(),
-                mode: SubscriptionMode::Push
+                mode: SubscriptionMode::Push,
+                from_offsets: None,
+                lookback_ms: None,
+                partitions: None,
+            }
+        );
+
+        let command = r#"{"type":"SUBSCRIBE","sourceId":"test","fromOffsets":{"0":10,"2":3}}"#;
+        let deserialized: Command = serde_json::from_str(command).unwrap();
+        assert_eq!(
+            deserialized,
+            Command::Subscribe {
+                source_id: "test".into(),
+                mode: SubscriptionMode::Push,
+                from_offsets: Some(BTreeMap::from([(0, 10), (2, 3)])),
+                lookback_ms: None,
+                partitions: None,
+            }
+        );
+
+        let command = r#"{"type":"SUBSCRIBE","sourceId":"test","mode":"pull","lookbackMs":60000}"#;
+        let deserialized: Command = serde_json::from_str(command).unwrap();
+        assert_eq!(
+            deserialized,
+            Command::Subscribe {
+                source_id: "test".into(),
+                mode: SubscriptionMode::Pull,
+                from_offsets: None,
+                lookback_ms: Some(60000),
+                partitions: None,
+            }
+        );
+
+        let command = r#"{"type":"SUBSCRIBE","sourceId":"test","partitions":[2,0]}"#;
+        let deserialized: Command = serde_json::from_str(command).unwrap();
+        assert_eq!(
+            deserialized,
+            Command::Subscribe {
+                source_id: "test".into(),
+                mode: SubscriptionMode::Push,
+                from_offsets: None,
+                lookback_ms: None,
+                partitions: Some(BTreeSet::from([0, 2])),
             }
         );
 
@@ -259,20 +1343,31 @@ mod tests {
             r#"{"type":"NOTICE","data":{"type":"SUBSCRIPTION_CLOSED","sourceId":"test","message":"New partition added"}}"#
         );
 
-        let message = Message::Result(SourceResult::Kafka {
-            payload: Some("test".into()),
+        let message: Message = Message::Notice(Notice::SourceStatus {
             source_id: "test".into(),
-            key: None,
-            timestamp: None,
-            partition: 0,
-            offset: 1,
+            status: SourceStatus::Degraded,
+            message: Some("All Kafka brokers are down".to_string()),
         });
 
+        let serialized = serde_json::to_string(&message).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"NOTICE","data":{"type":"SOURCE_STATUS","sourceId":"test","status":"DEGRADED","message":"All Kafka brokers are down"}}"#
+        );
+
+        let message: Message = kafka_source_result(
+            None,
+            Some(b"test"),
+            vec![("tenant-id".into(), Some(b"test".to_vec()))],
+            PayloadEncoding::Base64,
+        )
+        .into();
+
         let serialized = serde_json::to_string(&message).unwrap();
         let encoded = base64::engine::general_purpose::STANDARD.encode("test".as_bytes());
         assert_eq!(
             serialized,
-            r#"{"type":"RESULT","data":{"sourceType":"kafka","key":null,"payload":"$encoded","sourceId":"test","timestamp":null,"partition":0,"offset":1}}"#.replace("$encoded", encoded.as_str())
+            r#"{"type":"RESULT","data":{"sourceType":"kafka","key":null,"payload":"$encoded","payloadEncoding":"base64","headers":[{"key":"tenant-id","value":"$encoded"}],"sourceId":"test","topic":"test","timestamp":null,"partition":0,"offset":1}}"#.replace("$encoded", encoded.as_str())
         );
 
         let message = Message::Result(SourceResult::Counter {
@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use arc_swap::ArcSwapOption;
+use async_trait::async_trait;
+
+use crate::config::{Config, ConfigReconciler, Server, SourcesDefaults, Subscriber};
+use crate::hook::authenticate::types::{Authenticate, Outcome};
+use crate::hook::intercept::types::{Action, Intercept};
+use crate::hook::wasm::{WasmAuthenticateHook, WasmInterceptHook};
+use crate::source::http::{HttpPublisher, HttpSource};
+use crate::source::kafka::start_partition_discovery;
+use crate::source::registry::SourceRegistry;
+use crate::source::{Source, SourceId};
+use crate::tls::tls_acceptor;
+use crate::ws::{self, Healthcheck};
+
+type Sources = Arc<Mutex<BTreeMap<SourceId, Box<dyn Source + Send + Sync + 'static>>>>;
+
+/// Intercept hook of an embedded server. A hook implemented natively by the
+/// embedding application takes the place of any hook in the configuration
+enum InterceptHook {
+    Native(Box<dyn Intercept + Send + Sync>),
+    Configured(Arc<ArcSwapOption<WasmInterceptHook>>),
+}
+
+#[async_trait]
+impl Intercept for InterceptHook {
+    async fn intercept(
+        &self,
+        context: &crate::hook::intercept::types::Context,
+    ) -> anyhow::Result<Action> {
+        match self {
+            InterceptHook::Native(hook) => hook.intercept(context).await,
+            InterceptHook::Configured(hook) => match hook.load_full() {
+                Some(hook) => hook.intercept(context).await,
+                None => Ok(Action::Forward),
+            },
+        }
+    }
+}
+
+/// Authenticate hook of an embedded server. A hook implemented natively by the
+/// embedding application takes the place of any hook in the configuration
+enum AuthenticateHook {
+    Native(Box<dyn Authenticate + Send + Sync>),
+    Configured(Arc<ArcSwapOption<WasmAuthenticateHook>>),
+}
+
+#[async_trait]
+impl Authenticate for AuthenticateHook {
+    async fn authenticate(&self, request: http::Request<()>) -> anyhow::Result<Outcome> {
+        match self {
+            AuthenticateHook::Native(hook) => hook.authenticate(request).await,
+            AuthenticateHook::Configured(hook) => match hook.load_full() {
+                Some(hook) => hook.authenticate(request).await,
+                None => Ok(Outcome::Authenticate),
+            },
+        }
+    }
+}
+
+/// A Kiwi server running within the current Tokio runtime
+pub struct KiwiServer {
+    local_addr: SocketAddr,
+    sources: Sources,
+    task: Option<tokio::task::JoinHandle<anyhow::Result<()>>>,
+    watcher: Option<tokio::task::JoinHandle<()>>,
+    shutdown_trigger: Option<tokio::sync::oneshot::Sender<()>>,
+}
+
+impl KiwiServer {
+    /// Returns a builder for a server without any sources, which listens on an
+    /// unused port of the loopback interface unless configured otherwise
+    pub fn builder() -> KiwiServerBuilder {
+        KiwiServerBuilder::default()
+    }
+
+    /// Returns the address the server is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Calls `f` with the source with the specified ID, if it is of type `S`.
+    /// This gives the application access to sources it added through
+    /// [`KiwiServerBuilder::source`], such as to publish events into them
+    pub fn with_source<S: 'static, R>(
+        &self,
+        source_id: &str,
+        f: impl FnOnce(&S) -> R,
+    ) -> Option<R> {
+        self.sources
+            .lock()
+            .expect("poisoned lock")
+            .get(source_id)
+            .and_then(|source| source.as_any().downcast_ref::<S>())
+            .map(f)
+    }
+
+    /// Returns a publisher for the HTTP source with the specified ID, through
+    /// which events can be published without going through the HTTP endpoint
+    pub fn publisher(&self, source_id: &str) -> Option<HttpPublisher> {
+        self.with_source(source_id, HttpSource::publisher)
+    }
+
+    /// Waits for the server to stop, returning the error that stopped it. The
+    /// server only stops by itself if it fails to accept connections
+    pub async fn wait(&mut self) -> anyhow::Result<()> {
+        let Some(task) = self.task.as_mut() else {
+            return Ok(());
+        };
+
+        let result = task.await;
+        self.task = None;
+
+        result.context("Server task panicked")?
+    }
+
+    /// Stops accepting connections, sends connected WebSocket clients a close
+    /// frame and waits for every connection to finish before removing every
+    /// source
+    pub async fn shutdown(mut self) -> anyhow::Result<()> {
+        if let Some(shutdown_trigger) = self.shutdown_trigger.take() {
+            let _ = shutdown_trigger.send(());
+        }
+
+        if let Some(watcher) = self.watcher.take() {
+            watcher.abort();
+        }
+
+        let result = self.wait().await;
+
+        self.sources.lock().expect("poisoned lock").clear();
+
+        result
+    }
+}
+
+/// Builds a [`KiwiServer`] from a configuration, along with hooks and source
+/// types implemented natively by the embedding application
+pub struct KiwiServerBuilder {
+    config: Config,
+    config_path: Option<PathBuf>,
+    address: Option<SocketAddr>,
+    intercept: Option<Box<dyn Intercept + Send + Sync>>,
+    authenticate: Option<Box<dyn Authenticate + Send + Sync>>,
+    registry: SourceRegistry,
+    sources: Vec<Box<dyn Source + Send + Sync + 'static>>,
+}
+
+impl Default for KiwiServerBuilder {
+    fn default() -> Self {
+        Self {
+            config: Config {
+                sources: Vec::new(),
+                hooks: None,
+                server: Server {
+                    address: "127.0.0.1:0".into(),
+                    tls: None,
+                    healthcheck: true,
+                    healthcheck_degraded_threshold_ms: None,
+                },
+                kafka: None,
+                subscriber: Subscriber::default(),
+                sources_defaults: SourcesDefaults::default(),
+            },
+            config_path: None,
+            address: None,
+            intercept: None,
+            authenticate: None,
+            registry: SourceRegistry::default(),
+            sources: Vec::new(),
+        }
+    }
+}
+
+impl KiwiServerBuilder {
+    /// Uses the specified configuration
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self.config_path = None;
+        self
+    }
+
+    /// Uses the configuration at the specified path. Sources and hooks are
+    /// reconciled with the configuration as the file changes
+    pub fn config_file(mut self, path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+
+        self.config = Config::parse(&path)?;
+        self.config_path = Some(path);
+
+        Ok(self)
+    }
+
+    /// Listens on the specified address rather than the configured one
+    pub fn address(mut self, address: SocketAddr) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Uses the specified subscriber configuration rather than the configured one
+    pub fn subscriber(mut self, subscriber: Subscriber) -> Self {
+        self.config.subscriber = subscriber;
+        self
+    }
+
+    /// Intercepts events with a natively implemented hook, in place of any
+    /// configured intercept hook
+    pub fn intercept(mut self, hook: impl Intercept + Send + Sync + 'static) -> Self {
+        self.intercept = Some(Box::new(hook));
+        self
+    }
+
+    /// Authenticates connections with a natively implemented hook, in place of
+    /// any configured authenticate hook
+    pub fn authenticate(mut self, hook: impl Authenticate + Send + Sync + 'static) -> Self {
+        self.authenticate = Some(Box::new(hook));
+        self
+    }
+
+    /// Builds configured sources of types that are not built into Kiwi with the
+    /// specified registry
+    pub fn source_registry(mut self, registry: SourceRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Serves a source built by the application alongside the configured ones.
+    /// The source is kept as the configuration changes, and configured sources
+    /// may not use its ID
+    pub fn source(mut self, source: impl Source + Send + Sync + 'static) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    /// Builds the configured sources and hooks, and starts serving connections
+    pub async fn start(self) -> anyhow::Result<KiwiServer> {
+        let config = self.config;
+
+        let mut added = BTreeMap::new();
+
+        for source in self.sources {
+            let id = source.source_id().clone();
+
+            if added.insert(id.clone(), source).is_some() {
+                anyhow::bail!("Found duplicate source ID among added sources: {}", id);
+            }
+        }
+
+        let retained = added.keys().cloned().collect::<Vec<_>>();
+        let sources: Sources = Arc::new(Mutex::new(added));
+        let wasm_intercept = Arc::new(ArcSwapOption::new(None));
+        let wasm_authenticate = Arc::new(ArcSwapOption::new(None));
+
+        let config_reconciler: ConfigReconciler = ConfigReconciler::new(
+            Arc::clone(&sources),
+            Arc::clone(&wasm_intercept),
+            Arc::clone(&wasm_authenticate),
+        )
+        .with_registry(self.registry)
+        .with_retained_sources(retained);
+
+        config_reconciler.reconcile_sources(&config)?;
+        config_reconciler.reconcile_hooks(&config)?;
+
+        let intercept = match self.intercept {
+            Some(hook) => InterceptHook::Native(hook),
+            None => InterceptHook::Configured(wasm_intercept),
+        };
+        let authenticate = match self.authenticate {
+            Some(hook) => AuthenticateHook::Native(hook),
+            None => AuthenticateHook::Configured(wasm_authenticate),
+        };
+
+        if let Some(kafka_config) = config.kafka.as_ref() {
+            if kafka_config.partition_discovery_enabled {
+                start_partition_discovery(
+                    &kafka_config.client_properties()?,
+                    Arc::clone(&sources),
+                    std::time::Duration::from_millis(
+                        kafka_config.partition_discovery_interval_ms.into(),
+                    ),
+                )?;
+            }
+        }
+
+        let acceptor = match config.server.tls.as_ref() {
+            Some(tls) => {
+                Some(tls_acceptor(&tls.cert, &tls.key).context("Failed to build TLS acceptor")?)
+            }
+            None => None,
+        };
+
+        let address = match self.address {
+            Some(address) => address,
+            None => config
+                .server
+                .address
+                .parse()
+                .context(format!("Invalid server address {}", config.server.address))?,
+        };
+
+        let listener = tokio::net::TcpListener::bind(address)
+            .await
+            .context(format!("Failed to listen on {}", address))?;
+        let local_addr = listener.local_addr()?;
+
+        let watcher = self.config_path.map(|path| {
+            tokio::spawn(async move {
+                if let Err(e) = config_reconciler.watch(path).await {
+                    tracing::error!(
+                        "Configuration watcher exited unexpectedly with the error: {}",
+                        e
+                    );
+                }
+            })
+        });
+
+        let healthcheck = config.server.healthcheck.then_some(Healthcheck {
+            degraded_threshold: config
+                .server
+                .healthcheck_degraded_threshold_ms
+                .map(std::time::Duration::from_millis),
+        });
+
+        let (shutdown_trigger, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let served_sources = Arc::clone(&sources);
+
+        // The server keeps running if its handle is dropped without being shut
+        // down, as for any other spawned task
+        let task = tokio::spawn(ws::serve_listener(
+            listener,
+            acceptor,
+            served_sources,
+            Arc::new(ArcSwapOption::new(Some(Arc::new(intercept)))),
+            Arc::new(ArcSwapOption::new(Some(Arc::new(authenticate)))),
+            config.subscriber,
+            healthcheck,
+            async move {
+                if shutdown_rx.await.is_err() {
+                    std::future::pending::<()>().await;
+                }
+            },
+        ));
+
+        Ok(KiwiServer {
+            local_addr,
+            sources,
+            task: Some(task),
+            watcher,
+            shutdown_trigger: Some(shutdown_trigger),
+        })
+    }
+}
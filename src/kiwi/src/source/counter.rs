@@ -5,7 +5,9 @@ use tokio::sync::broadcast::{Receiver, Sender};
 
 use crate::hook;
 
-use super::{Source, SourceId, SourceMessage, SourceMetadata, SourceResult, SubscribeError};
+use super::{
+    ChannelUsage, Source, SourceId, SourceMessage, SourceMetadata, SourceResult, SubscribeError,
+};
 
 type ShutdownTrigger = tokio::sync::oneshot::Sender<()>;
 type ShutdownReceiver = tokio::sync::oneshot::Receiver<()>;
@@ -28,6 +30,7 @@ impl From<CounterSourceResult> for hook::intercept::types::CounterEventCtx {
 pub struct CounterSource {
     id: String,
     tx: Weak<Sender<SourceMessage>>,
+    channel_capacity: usize,
     initial_subscription_tx: Option<tokio::sync::oneshot::Sender<()>>,
     _shutdown_trigger: ShutdownTrigger,
 }
@@ -39,8 +42,9 @@ impl CounterSource {
         max: Option<u64>,
         interval: std::time::Duration,
         lazy: bool,
+        channel_capacity: usize,
     ) -> Self {
-        let (tx, _) = tokio::sync::broadcast::channel(1_000);
+        let (tx, _) = tokio::sync::broadcast::channel(channel_capacity);
         let (shutdown_trigger, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
         let (initial_subscription_tx, initial_subscription_rx) =
             tokio::sync::oneshot::channel::<()>();
@@ -70,6 +74,7 @@ impl CounterSource {
             id,
             _shutdown_trigger: shutdown_trigger,
             tx: weak_tx,
+            channel_capacity,
             initial_subscription_tx: Some(initial_subscription_tx),
         }
     }
@@ -90,6 +95,12 @@ impl Source for CounterSource {
         }
     }
 
+    fn channel_usage(&self) -> Option<ChannelUsage> {
+        self.tx
+            .upgrade()
+            .map(|tx| ChannelUsage::new(&tx, self.channel_capacity))
+    }
+
     fn source_id(&self) -> &SourceId {
         &self.id
     }
@@ -155,8 +166,16 @@ pub trait CounterSourceBuilder {
         max: Option<u64>,
         interval: std::time::Duration,
         lazy: bool,
+        channel_capacity: usize,
     ) -> Box<dyn Source + Send + Sync + 'static> {
-        Box::new(CounterSource::new(id, min, max, interval, lazy))
+        Box::new(CounterSource::new(
+            id,
+            min,
+            max,
+            interval,
+            lazy,
+            channel_capacity,
+        ))
     }
 }
 
@@ -172,6 +191,7 @@ mod tests {
             Some(3),
             std::time::Duration::from_millis(5),
             false,
+            1_000,
         );
 
         tokio::time::sleep(std::time::Duration::from_millis(25)).await;
@@ -190,6 +210,7 @@ mod tests {
             Some(3),
             std::time::Duration::from_millis(1),
             true,
+            1_000,
         );
 
         let mut rx = source.subscribe().unwrap();
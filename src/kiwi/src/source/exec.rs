@@ -0,0 +1,586 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use futures_util::{future::Fuse, FutureExt};
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+use tokio::sync::broadcast::{Receiver, Sender};
+use tokio::sync::Notify;
+
+use crate::protocol::PayloadEncoding;
+use crate::util::backoff::Backoff;
+
+use super::file::{LineBuffer, MAX_LINE_LEN};
+use super::{
+    ChannelUsage, CustomSourceResult, Source, SourceHealth, SourceId, SourceMessage,
+    SourceMetadata, SourceResult, SubscribeError,
+};
+
+type ShutdownTrigger = tokio::sync::oneshot::Sender<()>;
+type ShutdownReceiver = tokio::sync::oneshot::Receiver<()>;
+
+/// Maximum length of a length-delimited frame
+const MAX_FRAME_LEN: usize = MAX_LINE_LEN;
+
+/// Interval at which a lazy source checks whether its command still has
+/// subscribers
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Kind reported for events emitted by exec sources
+pub const EXEC_SOURCE_KIND: &str = "exec";
+
+/// How the output of a command is split into events
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecFraming {
+    /// Each line is an event
+    #[default]
+    Lines,
+    /// Each event is preceded by its length, as a 32-bit big-endian integer
+    LengthDelimited,
+}
+
+/// A command run by an exec source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecCommand {
+    /// Program to run, which is looked up in `PATH` unless it is a path
+    pub program: String,
+    pub args: Vec<String>,
+    /// Environment variables set in addition to those of the Kiwi process
+    pub env: BTreeMap<String, String>,
+    /// Working directory of the command. Defaults to that of the Kiwi process
+    pub working_dir: Option<PathBuf>,
+}
+
+impl ExecCommand {
+    fn spawn(&self) -> std::io::Result<tokio::process::Child> {
+        let mut command = tokio::process::Command::new(&self.program);
+
+        command
+            .args(&self.args)
+            .envs(&self.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        if let Some(working_dir) = &self.working_dir {
+            command.current_dir(working_dir);
+        }
+
+        command.spawn()
+    }
+}
+
+/// Splits the output of a command into frames
+struct FrameReader<R> {
+    reader: R,
+    framing: ExecFraming,
+    lines: LineBuffer,
+    pending: VecDeque<Vec<u8>>,
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    fn new(reader: R, framing: ExecFraming) -> Self {
+        Self {
+            reader,
+            framing,
+            lines: LineBuffer::default(),
+            pending: VecDeque::new(),
+            eof: false,
+        }
+    }
+
+    /// Reads the next frame, returning `None` once the output has ended
+    async fn next_frame(&mut self) -> anyhow::Result<Option<Vec<u8>>> {
+        match self.framing {
+            ExecFraming::Lines => loop {
+                if let Some(line) = self.pending.pop_front() {
+                    return Ok(Some(line));
+                }
+
+                if self.eof {
+                    return Ok(None);
+                }
+
+                let mut chunk = [0; 8 * 1024];
+                let read = self.reader.read(&mut chunk).await?;
+
+                if read == 0 {
+                    // The final line may not be terminated
+                    self.eof = true;
+                    self.pending
+                        .extend(self.lines.flush().map(|(_, line)| line));
+                } else {
+                    self.pending.extend(
+                        self.lines
+                            .push(&chunk[..read])
+                            .into_iter()
+                            .map(|(_, line)| line),
+                    );
+                }
+            },
+            ExecFraming::LengthDelimited => {
+                let mut len = [0; 4];
+
+                match self.reader.read_exact(&mut len).await {
+                    Ok(_) => {}
+                    Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(err) => return Err(err.into()),
+                }
+
+                let len = u32::from_be_bytes(len) as usize;
+
+                if len > MAX_FRAME_LEN {
+                    bail!(
+                        "Frame of {} bytes exceeds the maximum of {} bytes",
+                        len,
+                        MAX_FRAME_LEN
+                    );
+                }
+
+                let mut frame = vec![0; len];
+                self.reader
+                    .read_exact(&mut frame)
+                    .await
+                    .context("Output ended in the middle of a frame")?;
+
+                Ok(Some(frame))
+            }
+        }
+    }
+}
+
+/// A source that runs a command, emitting each line (or frame) it writes to
+/// stdout as an event
+pub struct ExecSource {
+    id: SourceId,
+    tx: Sender<SourceMessage>,
+    channel_capacity: usize,
+    health: SourceHealth,
+    subscribed: Arc<Notify>,
+    _shutdown_trigger: ShutdownTrigger,
+}
+
+impl ExecSource {
+    pub fn new(
+        id: SourceId,
+        command: ExecCommand,
+        framing: ExecFraming,
+        lazy: bool,
+        payload_encoding: PayloadEncoding,
+        channel_capacity: usize,
+    ) -> Self {
+        let (tx, _) = tokio::sync::broadcast::channel(channel_capacity);
+        let (shutdown_trigger, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let health = SourceHealth::new(id.clone(), tx.clone());
+        let subscribed = Arc::new(Notify::new());
+
+        let task = ExecTask {
+            source_id: id.clone(),
+            command,
+            framing,
+            lazy,
+            payload_encoding,
+            tx: tx.clone(),
+            health: health.clone(),
+            subscribed: Arc::clone(&subscribed),
+        };
+
+        tokio::spawn(task.run(shutdown_rx.fuse()));
+
+        Self {
+            id,
+            tx,
+            channel_capacity,
+            health,
+            subscribed,
+            _shutdown_trigger: shutdown_trigger,
+        }
+    }
+}
+
+impl Source for ExecSource {
+    fn subscribe(&mut self) -> Result<Receiver<SourceMessage>, SubscribeError> {
+        let rx = self.tx.subscribe();
+        self.subscribed.notify_one();
+
+        Ok(rx)
+    }
+
+    fn degraded_since(&self) -> Option<std::time::Instant> {
+        self.health.degraded_since()
+    }
+
+    fn degraded_reason(&self) -> Option<String> {
+        self.health.degraded_reason()
+    }
+
+    fn channel_usage(&self) -> Option<ChannelUsage> {
+        Some(ChannelUsage::new(&self.tx, self.channel_capacity))
+    }
+
+    fn source_id(&self) -> &SourceId {
+        &self.id
+    }
+
+    fn metadata_tx(&self) -> &Option<tokio::sync::mpsc::UnboundedSender<SourceMetadata>> {
+        &None
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Why a command stopped running without failing
+enum Stopped {
+    /// The command exited successfully
+    Exited,
+    /// The command was stopped, as the source no longer has subscribers
+    NoSubscribers,
+}
+
+struct ExecTask {
+    source_id: SourceId,
+    command: ExecCommand,
+    framing: ExecFraming,
+    lazy: bool,
+    payload_encoding: PayloadEncoding,
+    tx: Sender<SourceMessage>,
+    health: SourceHealth,
+    subscribed: Arc<Notify>,
+}
+
+impl ExecTask {
+    async fn run(self, mut shutdown_rx: Fuse<ShutdownReceiver>) {
+        let mut backoff = Backoff::default();
+
+        loop {
+            if self.lazy {
+                tokio::select! {
+                    _ = &mut shutdown_rx => break,
+                    _ = self.subscribers() => {}
+                }
+            }
+
+            let result = tokio::select! {
+                _ = &mut shutdown_rx => break,
+                result = self.run_command(&mut backoff) => result,
+            };
+
+            match result {
+                Ok(Stopped::NoSubscribers) => {
+                    tracing::debug!(
+                        source_id = self.source_id,
+                        "Stopped command as the source has no subscribers"
+                    );
+                    continue;
+                }
+                Ok(Stopped::Exited) => {
+                    tracing::debug!(source_id = self.source_id, "Command exited");
+                }
+                Err(err) => self.health.degraded(format!("{:#}", err)),
+            }
+
+            tokio::select! {
+                _ = &mut shutdown_rx => break,
+                _ = tokio::time::sleep(backoff.next_delay()) => {}
+            }
+        }
+
+        tracing::debug!("Exec task for source {} shutting down", self.source_id);
+    }
+
+    /// Waits until the source has at least one subscriber
+    async fn subscribers(&self) {
+        while self.tx.receiver_count() == 0 {
+            self.subscribed.notified().await;
+        }
+    }
+
+    /// Runs the command until it exits, or until the source no longer has
+    /// subscribers if it is lazy
+    async fn run_command(&self, backoff: &mut Backoff) -> anyhow::Result<Stopped> {
+        let mut child = self
+            .command
+            .spawn()
+            .context(format!("Failed to run {}", self.command.program))?;
+
+        let stdout = child.stdout.take().expect("stdout must be piped");
+        let stderr = child.stderr.take().expect("stderr must be piped");
+
+        // Output written to stderr is logged, rather than inherited, so that it
+        // can be attributed to the source
+        let source_id = self.source_id.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::warn!(source_id, "{}", line);
+            }
+        });
+
+        let emit = async {
+            let mut frames = FrameReader::new(stdout, self.framing);
+
+            while let Some(payload) = frames.next_frame().await? {
+                self.health.recovered();
+                backoff.reset();
+
+                let timestamp = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_millis() as i64)
+                    .unwrap_or_default();
+
+                let _ = self.tx.send(SourceMessage::Result(SourceResult::Custom(
+                    CustomSourceResult {
+                        source_id: self.source_id.clone(),
+                        kind: EXEC_SOURCE_KIND.into(),
+                        key: None,
+                        metadata: BTreeMap::new(),
+                        payload,
+                        timestamp,
+                        payload_encoding: self.payload_encoding,
+                    },
+                )));
+            }
+
+            Ok::<_, anyhow::Error>(())
+        };
+
+        let idle = async {
+            if !self.lazy {
+                return futures::future::pending().await;
+            }
+
+            let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+
+            while self.tx.receiver_count() > 0 {
+                interval.tick().await;
+            }
+        };
+
+        tokio::select! {
+            result = emit => {
+                if let Err(err) = result {
+                    let _ = child.kill().await;
+
+                    return Err(err.context("Failed to read output"));
+                }
+
+                let status = child.wait().await?;
+
+                if status.success() {
+                    Ok(Stopped::Exited)
+                } else {
+                    Err(anyhow!("Command exited with {}", status))
+                }
+            }
+            _ = idle => {
+                let _ = child.kill().await;
+
+                Ok(Stopped::NoSubscribers)
+            }
+        }
+    }
+}
+
+pub trait ExecSourceBuilder {
+    fn build_source(
+        id: SourceId,
+        command: ExecCommand,
+        framing: ExecFraming,
+        lazy: bool,
+        payload_encoding: PayloadEncoding,
+        channel_capacity: usize,
+    ) -> Box<dyn Source + Send + Sync + 'static> {
+        Box::new(ExecSource::new(
+            id,
+            command,
+            framing,
+            lazy,
+            payload_encoding,
+            channel_capacity,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str) -> ExecCommand {
+        ExecCommand {
+            program: "sh".into(),
+            args: vec!["-c".into(), script.into()],
+            env: BTreeMap::new(),
+            working_dir: None,
+        }
+    }
+
+    async fn recv_payload(rx: &mut Receiver<SourceMessage>) -> Vec<u8> {
+        match tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+        {
+            SourceMessage::Result(SourceResult::Custom(result)) => {
+                assert_eq!(result.source_id, "test");
+                assert_eq!(result.kind, EXEC_SOURCE_KIND);
+                result.payload
+            }
+            msg => panic!("Expected exec result. Received {:?}", msg),
+        }
+    }
+
+    async fn frames(output: &[u8], framing: ExecFraming) -> anyhow::Result<Vec<Vec<u8>>> {
+        let mut reader = FrameReader::new(output, framing);
+        let mut frames = Vec::new();
+
+        while let Some(frame) = reader.next_frame().await? {
+            frames.push(frame);
+        }
+
+        Ok(frames)
+    }
+
+    #[tokio::test]
+    async fn test_reads_line_frames() {
+        assert_eq!(
+            frames(b"first\r\nsecond\n\nthird", ExecFraming::Lines)
+                .await
+                .unwrap(),
+            vec![
+                b"first".to_vec(),
+                b"second".to_vec(),
+                b"".to_vec(),
+                b"third".to_vec()
+            ]
+        );
+        assert!(frames(b"", ExecFraming::Lines).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reads_length_delimited_frames() {
+        assert_eq!(
+            frames(
+                b"\x00\x00\x00\x03abc\x00\x00\x00\x00\x00\x00\x00\x02\n\n",
+                ExecFraming::LengthDelimited
+            )
+            .await
+            .unwrap(),
+            vec![b"abc".to_vec(), b"".to_vec(), b"\n\n".to_vec()]
+        );
+        // Output ending in the middle of a frame
+        assert!(frames(b"\x00\x00\x00\x05abc", ExecFraming::LengthDelimited)
+            .await
+            .is_err());
+        assert!(frames(b"\xff\xff\xff\xffabc", ExecFraming::LengthDelimited)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restarts_command() {
+        let mut source = ExecSource::new(
+            "test".into(),
+            shell("printf 'one\\ntwo\\n'"),
+            ExecFraming::Lines,
+            false,
+            PayloadEncoding::Utf8,
+            10,
+        );
+        let mut rx = source.subscribe().unwrap();
+
+        for _ in 0..2 {
+            assert_eq!(recv_payload(&mut rx).await, b"one");
+            assert_eq!(recv_payload(&mut rx).await, b"two");
+        }
+
+        assert!(source.degraded_since().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_degrades_when_command_fails() {
+        let mut source = ExecSource::new(
+            "test".into(),
+            shell("echo partial; exit 3"),
+            ExecFraming::Lines,
+            false,
+            PayloadEncoding::Utf8,
+            10,
+        );
+        let mut rx = source.subscribe().unwrap();
+
+        assert_eq!(recv_payload(&mut rx).await, b"partial");
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            SourceMessage::StatusChanged(super::super::SourceStatus::Degraded(reason))
+                if reason.contains("exit status: 3")
+        ));
+
+        let mut source = ExecSource::new(
+            "test".into(),
+            ExecCommand {
+                program: "/nonexistent/command".into(),
+                args: vec![],
+                env: BTreeMap::new(),
+                working_dir: None,
+            },
+            ExecFraming::Lines,
+            false,
+            PayloadEncoding::Utf8,
+            10,
+        );
+        let mut rx = source.subscribe().unwrap();
+
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            SourceMessage::StatusChanged(super::super::SourceStatus::Degraded(reason))
+                if reason.contains("Failed to run /nonexistent/command")
+        ));
+        assert!(source.degraded_since().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_runs_lazily_while_subscribed() {
+        let dir = tempfile::tempdir().unwrap();
+        let runs = dir.path().join("runs");
+
+        let mut command = shell("echo run >> \"$RUNS\"; echo started; exec sleep 30");
+        command
+            .env
+            .insert("RUNS".into(), runs.display().to_string());
+
+        let mut source = ExecSource::new(
+            "test".into(),
+            command,
+            ExecFraming::Lines,
+            true,
+            PayloadEncoding::Utf8,
+            10,
+        );
+
+        // The command isn't run until the source is subscribed to
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!runs.exists());
+
+        let mut rx = source.subscribe().unwrap();
+        assert_eq!(recv_payload(&mut rx).await, b"started");
+
+        // The command is stopped once the source no longer has subscribers,
+        // and run again upon the next subscription
+        drop(rx);
+        tokio::time::sleep(IDLE_CHECK_INTERVAL * 2).await;
+
+        let mut rx = source.subscribe().unwrap();
+        assert_eq!(recv_payload(&mut rx).await, b"started");
+        assert_eq!(std::fs::read_to_string(&runs).unwrap(), "run\nrun\n");
+    }
+}
@@ -0,0 +1,823 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use anyhow::Context;
+use futures_util::{future::Fuse, FutureExt};
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, BufReader};
+use tokio::sync::broadcast::{Receiver, Sender};
+use tokio::time::Instant;
+
+use crate::protocol::PayloadEncoding;
+
+use super::{
+    ChannelUsage, CustomSourceResult, Source, SourceHealth, SourceId, SourceMessage,
+    SourceMetadata, SourceResult, SubscribeError,
+};
+
+type ShutdownTrigger = tokio::sync::oneshot::Sender<()>;
+type ShutdownReceiver = tokio::sync::oneshot::Receiver<()>;
+
+/// Maximum length of a line. Longer lines are discarded
+pub(super) const MAX_LINE_LEN: usize = 1024 * 1024;
+
+/// Interval at which a tailed file is checked for new lines and rotation
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Kind reported for events emitted by file sources
+pub const FILE_SOURCE_KIND: &str = "file";
+
+/// Metadata entry holding the path of the file a line was read from
+pub const FILE_PATH_METADATA: &str = "path";
+
+/// Metadata entry holding the byte offset of a line within its file
+pub const FILE_OFFSET_METADATA: &str = "offset";
+
+/// Replays a recording of newline-delimited JSON events, pacing them by the
+/// time at which each was recorded
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FileReplay {
+    /// Field of each event holding the time (in milliseconds since the Unix
+    /// epoch) at which it was recorded
+    pub timestamp_field: String,
+    /// Factor by which the replay is sped up relative to the recording
+    #[serde(default = "FileReplay::default_speed")]
+    pub speed: f64,
+    /// Whether the recording is replayed from the start once it completes.
+    /// Otherwise, the source ends
+    #[serde(default, rename = "loop")]
+    pub repeat: bool,
+}
+
+impl FileReplay {
+    fn default_speed() -> f64 {
+        1.0
+    }
+
+    /// Returns the time at which an event was recorded, if it is a JSON object
+    /// with a numeric timestamp field
+    fn recorded_at(&self, line: &[u8]) -> Option<f64> {
+        serde_json::from_slice::<serde_json::Value>(line)
+            .ok()?
+            .get(&self.timestamp_field)?
+            .as_f64()
+    }
+}
+
+/// Splits bytes read from a file (or any other stream) into lines
+#[derive(Debug, Default)]
+pub(super) struct LineBuffer {
+    buf: Vec<u8>,
+    /// Offset within the file of the first byte of `buf`
+    offset: u64,
+    /// Whether the rest of the current line is discarded, as it is too long
+    discarding: bool,
+}
+
+impl LineBuffer {
+    /// Resets the buffer to start reading lines at the specified offset
+    pub(super) fn reset(&mut self, offset: u64) {
+        self.buf.clear();
+        self.offset = offset;
+        self.discarding = false;
+    }
+
+    /// Appends a chunk, returning each line completed by it along with its
+    /// offset. Line endings are not included
+    pub(super) fn push(&mut self, chunk: &[u8]) -> Vec<(u64, Vec<u8>)> {
+        let mut lines = Vec::new();
+        let mut rest = chunk;
+
+        while let Some(pos) = rest.iter().position(|byte| *byte == b'\n') {
+            let (line, remaining) = rest.split_at(pos);
+            rest = &remaining[1..];
+
+            if self.discarding {
+                self.discarding = false;
+                self.offset += line.len() as u64 + 1;
+                self.buf.clear();
+                continue;
+            }
+
+            self.buf.extend_from_slice(line);
+
+            let len = self.buf.len() as u64 + 1;
+            let mut line = std::mem::take(&mut self.buf);
+
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+
+            if line.len() <= MAX_LINE_LEN {
+                lines.push((self.offset, line));
+            }
+
+            self.offset += len;
+        }
+
+        if self.discarding {
+            self.offset += rest.len() as u64;
+        } else {
+            self.buf.extend_from_slice(rest);
+
+            if self.buf.len() > MAX_LINE_LEN {
+                tracing::warn!(
+                    "Discarding line at offset {} exceeding {} bytes",
+                    self.offset,
+                    MAX_LINE_LEN
+                );
+                self.offset += self.buf.len() as u64;
+                self.buf.clear();
+                self.discarding = true;
+            }
+        }
+
+        lines
+    }
+
+    /// Returns the incomplete line remaining in the buffer, if any
+    pub(super) fn flush(&mut self) -> Option<(u64, Vec<u8>)> {
+        if self.buf.is_empty() {
+            return None;
+        }
+
+        let offset = self.offset;
+        let line = std::mem::take(&mut self.buf);
+        self.offset += line.len() as u64;
+
+        Some((offset, line))
+    }
+}
+
+/// Identifies the file found at a path, so that it can be detected when the
+/// file is replaced
+#[cfg(unix)]
+fn file_identity(metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(_metadata: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// A source that emits each line of a file as an event, either by following
+/// lines appended to the file or by replaying a recording of events
+pub struct FileSource {
+    id: SourceId,
+    tx: Weak<Sender<SourceMessage>>,
+    channel_capacity: usize,
+    // Only tailed files are monitored, as a replay ends if its recording can't
+    // be read. Holding a sender for the lifetime of a replay would prevent
+    // subscribers from detecting that it has ended
+    health: Option<SourceHealth>,
+    initial_subscription_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    _shutdown_trigger: ShutdownTrigger,
+}
+
+impl FileSource {
+    pub fn new(
+        id: SourceId,
+        path: PathBuf,
+        from_start: bool,
+        replay: Option<FileReplay>,
+        lazy: bool,
+        payload_encoding: PayloadEncoding,
+        channel_capacity: usize,
+    ) -> Self {
+        let (tx, _) = tokio::sync::broadcast::channel(channel_capacity);
+        let (shutdown_trigger, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let (initial_subscription_tx, initial_subscription_rx) =
+            tokio::sync::oneshot::channel::<()>();
+        let health = replay
+            .is_none()
+            .then(|| SourceHealth::new(id.clone(), tx.clone()));
+
+        let tx = Arc::new(tx);
+
+        // As for counter sources, the file task holds the only strong reference
+        // to the sender so that subscribers can detect when a replay has ended
+        let weak_tx = Arc::downgrade(&tx);
+
+        let task = FileTask {
+            source_id: id.clone(),
+            path,
+            from_start,
+            replay,
+            lazy,
+            payload_encoding,
+            tx,
+            health: health.clone(),
+            initial_subscription_rx,
+            shutdown_rx: shutdown_rx.fuse(),
+        };
+
+        tokio::spawn(task.run());
+
+        Self {
+            id,
+            tx: weak_tx,
+            channel_capacity,
+            health,
+            initial_subscription_tx: Some(initial_subscription_tx),
+            _shutdown_trigger: shutdown_trigger,
+        }
+    }
+}
+
+impl Source for FileSource {
+    fn subscribe(&mut self) -> Result<Receiver<SourceMessage>, SubscribeError> {
+        if let Some(tx) = self.initial_subscription_tx.take() {
+            let _ = tx.send(());
+        }
+
+        // If the file task (our sole sender) has ended, the replay has completed
+        if let Some(tx) = self.tx.upgrade() {
+            Ok(tx.subscribe())
+        } else {
+            Err(SubscribeError::FiniteSourceEnded)
+        }
+    }
+
+    fn degraded_since(&self) -> Option<std::time::Instant> {
+        self.health
+            .as_ref()
+            .and_then(|health| health.degraded_since())
+    }
+
+    fn degraded_reason(&self) -> Option<String> {
+        self.health
+            .as_ref()
+            .and_then(|health| health.degraded_reason())
+    }
+
+    fn channel_usage(&self) -> Option<ChannelUsage> {
+        self.tx
+            .upgrade()
+            .map(|tx| ChannelUsage::new(&tx, self.channel_capacity))
+    }
+
+    fn source_id(&self) -> &SourceId {
+        &self.id
+    }
+
+    fn metadata_tx(&self) -> &Option<tokio::sync::mpsc::UnboundedSender<SourceMetadata>> {
+        &None
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// A file opened for tailing
+struct TailedFile {
+    file: tokio::fs::File,
+    identity: Option<(u64, u64)>,
+    position: u64,
+}
+
+struct FileTask {
+    source_id: SourceId,
+    path: PathBuf,
+    from_start: bool,
+    replay: Option<FileReplay>,
+    lazy: bool,
+    payload_encoding: PayloadEncoding,
+    tx: Arc<Sender<SourceMessage>>,
+    health: Option<SourceHealth>,
+    initial_subscription_rx: tokio::sync::oneshot::Receiver<()>,
+    shutdown_rx: Fuse<ShutdownReceiver>,
+}
+
+impl FileTask {
+    async fn run(mut self) {
+        if self.lazy {
+            tokio::select! {
+                _ = &mut self.shutdown_rx => return,
+                _ = &mut self.initial_subscription_rx => {}
+            }
+        }
+
+        let mut shutdown_rx = std::mem::replace(&mut self.shutdown_rx, Fuse::terminated());
+
+        match self.replay.clone() {
+            Some(replay) => {
+                tokio::select! {
+                    _ = &mut shutdown_rx => {}
+                    result = self.replay(&replay) => {
+                        if let Err(err) = result {
+                            tracing::error!(
+                                source_id = self.source_id,
+                                "Failed to replay {}: {:#}",
+                                self.path.display(),
+                                err
+                            );
+                        }
+                    }
+                }
+            }
+            None => {
+                tokio::select! {
+                    _ = &mut shutdown_rx => {}
+                    _ = self.tail() => {}
+                }
+            }
+        }
+
+        tracing::debug!("File task for source {} shutting down", self.source_id);
+    }
+
+    fn emit(&self, offset: u64, payload: Vec<u8>) {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as i64)
+            .unwrap_or_default();
+
+        let _ = self.tx.send(SourceMessage::Result(SourceResult::Custom(
+            CustomSourceResult {
+                source_id: self.source_id.clone(),
+                kind: FILE_SOURCE_KIND.into(),
+                key: None,
+                metadata: BTreeMap::from([
+                    (FILE_PATH_METADATA.into(), self.path.display().to_string()),
+                    (FILE_OFFSET_METADATA.into(), offset.to_string()),
+                ]),
+                payload,
+                timestamp,
+                payload_encoding: self.payload_encoding,
+            },
+        )));
+    }
+
+    /// Replays the recording until it completes, or indefinitely if it loops
+    async fn replay(&self, replay: &FileReplay) -> anyhow::Result<()> {
+        loop {
+            let emitted = self.replay_once(replay).await?;
+
+            // Looping over a recording without events would never yield
+            if !replay.repeat || emitted == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Replays the recording once, returning the number of events emitted
+    async fn replay_once(&self, replay: &FileReplay) -> anyhow::Result<usize> {
+        let file = tokio::fs::File::open(&self.path)
+            .await
+            .context(format!("Failed to open {}", self.path.display()))?;
+        let mut reader = BufReader::new(file);
+        let mut line = Vec::new();
+        let mut offset = 0;
+        let mut emitted = 0;
+        // Instant at which the replay started and the time the first event
+        // was recorded at. Events are scheduled relative to these, so that
+        // delays don't accumulate over the course of the replay
+        let mut origin: Option<(Instant, f64)> = None;
+
+        loop {
+            line.clear();
+
+            let read = reader
+                .read_until(b'\n', &mut line)
+                .await
+                .context(format!("Failed to read {}", self.path.display()))?;
+
+            if read == 0 {
+                return Ok(emitted);
+            }
+
+            let line_offset = offset;
+            offset += read as u64;
+
+            while matches!(line.last(), Some(b'\n' | b'\r')) {
+                line.pop();
+            }
+
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            if line.len() > MAX_LINE_LEN {
+                tracing::warn!(
+                    source_id = self.source_id,
+                    "Discarding line at offset {} exceeding {} bytes",
+                    line_offset,
+                    MAX_LINE_LEN
+                );
+                continue;
+            }
+
+            // Events without a recorded time are emitted straight away
+            if let Some(recorded_at) = replay.recorded_at(&line) {
+                let (started_at, first_recorded_at) =
+                    *origin.get_or_insert((Instant::now(), recorded_at));
+                let elapsed = (recorded_at - first_recorded_at).max(0.0) / replay.speed;
+
+                tokio::time::sleep_until(started_at + Duration::from_secs_f64(elapsed / 1000.0))
+                    .await;
+            }
+
+            self.emit(line_offset, line.clone());
+            emitted += 1;
+        }
+    }
+
+    /// Follows lines appended to the file, reopening it when it is replaced
+    async fn tail(&self) {
+        let mut lines = LineBuffer::default();
+        let mut tailed: Option<TailedFile> = None;
+        // Only lines appended after the source is built are emitted, unless
+        // the file is created (or replaced) afterwards
+        let mut from_end = !self.from_start;
+
+        loop {
+            if tailed.is_none() {
+                let result = self.open(from_end).await;
+                from_end = false;
+
+                match result {
+                    Ok(file) => {
+                        self.recovered();
+                        lines.reset(file.position);
+                        tailed = Some(file);
+                    }
+                    Err(err) => self.degraded(err),
+                }
+            }
+
+            if let Some(file) = tailed.as_mut() {
+                match self.read_appended(file, &mut lines).await {
+                    Ok(true) => self.recovered(),
+                    Ok(false) => {
+                        // The file was replaced, so the next one is read from
+                        // the start without waiting
+                        if let Some((offset, line)) = lines.flush() {
+                            self.emit(offset, line);
+                        }
+
+                        tailed = None;
+                        continue;
+                    }
+                    Err(err) => self.degraded(err),
+                }
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    fn degraded(&self, err: anyhow::Error) {
+        if let Some(health) = &self.health {
+            health.degraded(format!("{:#}", err));
+        }
+    }
+
+    fn recovered(&self) {
+        if let Some(health) = &self.health {
+            health.recovered();
+        }
+    }
+
+    async fn open(&self, from_end: bool) -> anyhow::Result<TailedFile> {
+        let mut file = tokio::fs::File::open(&self.path)
+            .await
+            .context(format!("Failed to open {}", self.path.display()))?;
+        let metadata = file.metadata().await?;
+
+        let position = if from_end {
+            file.seek(std::io::SeekFrom::End(0)).await?
+        } else {
+            0
+        };
+
+        Ok(TailedFile {
+            file,
+            identity: file_identity(&metadata),
+            position,
+        })
+    }
+
+    /// Emits lines appended to the file since it was last read. Returns whether
+    /// the file is still found at the tailed path
+    async fn read_appended(
+        &self,
+        tailed: &mut TailedFile,
+        lines: &mut LineBuffer,
+    ) -> anyhow::Result<bool> {
+        let mut chunk = vec![0; 64 * 1024];
+        let metadata = tailed
+            .file
+            .metadata()
+            .await
+            .context(format!("Failed to read {}", self.path.display()))?;
+
+        // Files truncated in place (e.g. by `copytruncate` rotation) are read
+        // from the start
+        if metadata.len() < tailed.position {
+            tracing::info!(
+                source_id = self.source_id,
+                "{} was truncated",
+                self.path.display()
+            );
+
+            tailed.position = tailed.file.seek(std::io::SeekFrom::Start(0)).await?;
+            lines.reset(0);
+        }
+
+        loop {
+            let read = tailed
+                .file
+                .read(&mut chunk)
+                .await
+                .context(format!("Failed to read {}", self.path.display()))?;
+
+            if read == 0 {
+                break;
+            }
+
+            tailed.position += read as u64;
+
+            for (offset, line) in lines.push(&chunk[..read]) {
+                self.emit(offset, line);
+            }
+        }
+
+        Ok(is_same_file(&self.path, tailed.identity).await)
+    }
+}
+
+/// Returns whether the file found at a path is the one with the specified
+/// identity
+async fn is_same_file(path: &Path, identity: Option<(u64, u64)>) -> bool {
+    match tokio::fs::metadata(path).await {
+        Ok(metadata) => identity.is_none() || file_identity(&metadata) == identity,
+        Err(_) => false,
+    }
+}
+
+pub trait FileSourceBuilder {
+    fn build_source(
+        id: SourceId,
+        path: PathBuf,
+        from_start: bool,
+        replay: Option<FileReplay>,
+        lazy: bool,
+        payload_encoding: PayloadEncoding,
+        channel_capacity: usize,
+    ) -> Box<dyn Source + Send + Sync + 'static> {
+        Box::new(FileSource::new(
+            id,
+            path,
+            from_start,
+            replay,
+            lazy,
+            payload_encoding,
+            channel_capacity,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    async fn recv_payload(rx: &mut Receiver<SourceMessage>) -> (u64, Vec<u8>) {
+        loop {
+            match tokio::time::timeout(Duration::from_secs(5), rx.recv())
+                .await
+                .unwrap()
+                .unwrap()
+            {
+                SourceMessage::Result(SourceResult::Custom(result)) => {
+                    assert_eq!(result.source_id, "test");
+                    assert_eq!(result.kind, FILE_SOURCE_KIND);
+
+                    let offset = result.metadata[FILE_OFFSET_METADATA].parse().unwrap();
+                    return (offset, result.payload);
+                }
+                // The file may briefly be missing while it is rotated
+                SourceMessage::StatusChanged(_) => {}
+                msg => panic!("Expected file result. Received {:?}", msg),
+            }
+        }
+    }
+
+    #[test]
+    fn test_splits_lines() {
+        let mut lines = LineBuffer::default();
+
+        assert_eq!(lines.push(b"first\r\nsec"), vec![(0, b"first".to_vec())]);
+        assert_eq!(
+            lines.push(b"ond\n\nthird"),
+            vec![(7, b"second".to_vec()), (14, b"".to_vec())]
+        );
+        assert_eq!(lines.flush(), Some((15, b"third".to_vec())));
+        assert_eq!(lines.flush(), None);
+
+        // Lines exceeding the maximum length are discarded
+        lines.reset(0);
+        assert!(lines.push(&vec![b'a'; MAX_LINE_LEN + 1]).is_empty());
+        assert_eq!(
+            lines.push(b"aaa\nnext\n"),
+            vec![(MAX_LINE_LEN as u64 + 5, b"next".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_reads_recorded_time() {
+        let replay = FileReplay {
+            timestamp_field: "ts".into(),
+            speed: 1.0,
+            repeat: false,
+        };
+
+        assert_eq!(replay.recorded_at(br#"{"ts":1700000000000}"#), Some(1.7e12));
+        assert_eq!(replay.recorded_at(br#"{"ts":"yesterday"}"#), None);
+        assert_eq!(replay.recorded_at(br#"{"time":1}"#), None);
+        assert_eq!(replay.recorded_at(b"not json"), None);
+    }
+
+    #[tokio::test]
+    async fn test_tails_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "existing").unwrap();
+
+        let mut source = FileSource::new(
+            "test".into(),
+            file.path().to_path_buf(),
+            false,
+            None,
+            false,
+            PayloadEncoding::Utf8,
+            10,
+        );
+        let mut rx = source.subscribe().unwrap();
+
+        // Wait for the task to open the file, so that only appended lines are
+        // emitted
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        write!(file, "first\nsec").unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(recv_payload(&mut rx).await, (9, b"first".to_vec()));
+
+        writeln!(file, "ond").unwrap();
+
+        assert_eq!(recv_payload(&mut rx).await, (15, b"second".to_vec()));
+
+        // Files truncated in place are read from the start
+        file.as_file().set_len(0).unwrap();
+        std::fs::write(file.path(), "after truncation\n").unwrap();
+
+        assert_eq!(
+            recv_payload(&mut rx).await,
+            (0, b"after truncation".to_vec())
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_follows_rotated_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.log");
+        std::fs::write(&path, "first\n").unwrap();
+
+        let mut source = FileSource::new(
+            "test".into(),
+            path.clone(),
+            true,
+            None,
+            false,
+            PayloadEncoding::Utf8,
+            10,
+        );
+        let mut rx = source.subscribe().unwrap();
+
+        assert_eq!(recv_payload(&mut rx).await, (0, b"first".to_vec()));
+
+        std::fs::rename(&path, dir.path().join("events.log.1")).unwrap();
+        std::fs::write(&path, "second\n").unwrap();
+
+        assert_eq!(recv_payload(&mut rx).await, (0, b"second".to_vec()));
+        assert!(source.degraded_since().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_degrades_while_file_is_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.log");
+
+        let mut source = FileSource::new(
+            "test".into(),
+            path.clone(),
+            false,
+            None,
+            false,
+            PayloadEncoding::Utf8,
+            10,
+        );
+        let mut rx = source.subscribe().unwrap();
+
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            SourceMessage::StatusChanged(super::super::SourceStatus::Degraded(_))
+        ));
+        assert!(source.degraded_since().is_some());
+
+        // Files created after the source is built are read from the start
+        std::fs::write(&path, "created\n").unwrap();
+
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            SourceMessage::StatusChanged(super::super::SourceStatus::Recovered)
+        );
+        assert_eq!(recv_payload(&mut rx).await, (0, b"created".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_replays_recording() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"{\"ts\":1000,\"n\":1}\n{\"ts\":1100,\"n\":2}\n\n{\"n\":3}")
+            .unwrap();
+
+        let replay = FileReplay {
+            timestamp_field: "ts".into(),
+            speed: 2.0,
+            repeat: false,
+        };
+        let mut source = FileSource::new(
+            "test".into(),
+            file.path().to_path_buf(),
+            false,
+            Some(replay),
+            true,
+            PayloadEncoding::Json,
+            10,
+        );
+
+        let started_at = std::time::Instant::now();
+        let mut rx = source.subscribe().unwrap();
+
+        assert_eq!(
+            recv_payload(&mut rx).await,
+            (0, br#"{"ts":1000,"n":1}"#.to_vec())
+        );
+        assert_eq!(
+            recv_payload(&mut rx).await,
+            (18, br#"{"ts":1100,"n":2}"#.to_vec())
+        );
+        // Events are paced by the time they were recorded at, scaled by the
+        // speed of the replay
+        assert!(started_at.elapsed() >= Duration::from_millis(50));
+        assert_eq!(recv_payload(&mut rx).await, (37, br#"{"n":3}"#.to_vec()));
+
+        // The source ends once the replay completes
+        assert!(matches!(
+            rx.recv().await,
+            Err(tokio::sync::broadcast::error::RecvError::Closed)
+        ));
+        assert!(matches!(
+            source.subscribe(),
+            Err(SubscribeError::FiniteSourceEnded)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_loops_recording() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, r#"{{"ts":0}}"#).unwrap();
+        writeln!(file, r#"{{"ts":100}}"#).unwrap();
+
+        let replay = FileReplay {
+            timestamp_field: "ts".into(),
+            speed: 1.0,
+            repeat: true,
+        };
+        let mut source = FileSource::new(
+            "test".into(),
+            file.path().to_path_buf(),
+            false,
+            Some(replay),
+            true,
+            PayloadEncoding::Json,
+            10,
+        );
+        let mut rx = source.subscribe().unwrap();
+
+        for _ in 0..2 {
+            assert_eq!(recv_payload(&mut rx).await.0, 0);
+            assert_eq!(recv_payload(&mut rx).await.0, 9);
+        }
+
+        assert!(source.subscribe().is_ok());
+    }
+}
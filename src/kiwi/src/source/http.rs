@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+
+use tokio::sync::broadcast::{Receiver, Sender};
+
+use crate::protocol::PayloadEncoding;
+
+use super::{
+    ChannelUsage, CustomSourceResult, Source, SourceId, SourceMessage, SourceMetadata,
+    SourceResult, SubscribeError,
+};
+
+/// Content type of request bodies containing a batch of events, one per line
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Kind reported for events emitted by HTTP sources
+pub const HTTP_SOURCE_KIND: &str = "http";
+
+/// Splits a request body into event payloads. Bodies sent as NDJSON contain an
+/// event per non-empty line, while any other body is a single event
+pub fn split_events(body: &[u8], content_type: Option<&str>) -> Vec<Vec<u8>> {
+    let is_ndjson = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case(NDJSON_CONTENT_TYPE));
+
+    if !is_ndjson {
+        return vec![body.to_vec()];
+    }
+
+    body.split(|byte| *byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+        .map(|line| line.to_vec())
+        .collect()
+}
+
+/// Compares two byte strings in constant time with respect to their contents
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Publishes events received over HTTP to the subscribers of a source
+#[derive(Debug, Clone)]
+pub struct HttpPublisher {
+    source_id: SourceId,
+    tx: Sender<SourceMessage>,
+    payload_encoding: PayloadEncoding,
+}
+
+impl HttpPublisher {
+    /// Broadcasts each payload as an event, returning the number of events
+    /// delivered to at least one subscriber. Events are dropped if the source
+    /// has no subscribers, as for any other source
+    pub fn publish(&self, payloads: Vec<Vec<u8>>) -> usize {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as i64)
+            .unwrap_or_default();
+
+        payloads
+            .into_iter()
+            .map(|payload| {
+                self.tx
+                    .send(SourceMessage::Result(SourceResult::Custom(
+                        CustomSourceResult {
+                            source_id: self.source_id.clone(),
+                            kind: HTTP_SOURCE_KIND.into(),
+                            key: None,
+                            metadata: BTreeMap::new(),
+                            payload,
+                            timestamp,
+                            payload_encoding: self.payload_encoding,
+                        },
+                    )))
+                    .is_ok()
+            })
+            .filter(|delivered| *delivered)
+            .count()
+    }
+}
+
+/// A source whose events are published by `POST`ing them to
+/// `/sources/{id}/events`
+pub struct HttpSource {
+    id: SourceId,
+    tokens: Vec<String>,
+    max_body_bytes: usize,
+    publisher: HttpPublisher,
+    channel_capacity: usize,
+}
+
+impl HttpSource {
+    pub fn new(
+        id: SourceId,
+        tokens: Vec<String>,
+        max_body_bytes: usize,
+        payload_encoding: PayloadEncoding,
+        channel_capacity: usize,
+    ) -> Self {
+        let (tx, _) = tokio::sync::broadcast::channel(channel_capacity);
+
+        Self {
+            publisher: HttpPublisher {
+                source_id: id.clone(),
+                tx,
+                payload_encoding,
+            },
+            id,
+            tokens,
+            max_body_bytes,
+            channel_capacity,
+        }
+    }
+
+    /// Returns whether the value of an `Authorization` header carries one of the
+    /// bearer tokens accepted by the source
+    pub fn authorize(&self, authorization: Option<&str>) -> bool {
+        let Some(token) = authorization.and_then(|value| {
+            value
+                .split_once(' ')
+                .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+                .map(|(_, token)| token.trim())
+        }) else {
+            return false;
+        };
+
+        self.tokens
+            .iter()
+            .any(|accepted| constant_time_eq(accepted.as_bytes(), token.as_bytes()))
+    }
+
+    /// Maximum size of a request body accepted by the source
+    pub fn max_body_bytes(&self) -> usize {
+        self.max_body_bytes
+    }
+
+    pub fn publisher(&self) -> HttpPublisher {
+        self.publisher.clone()
+    }
+}
+
+impl Source for HttpSource {
+    fn subscribe(&mut self) -> Result<Receiver<SourceMessage>, SubscribeError> {
+        Ok(self.publisher.tx.subscribe())
+    }
+
+    fn channel_usage(&self) -> Option<ChannelUsage> {
+        Some(ChannelUsage::new(&self.publisher.tx, self.channel_capacity))
+    }
+
+    fn source_id(&self) -> &SourceId {
+        &self.id
+    }
+
+    fn metadata_tx(&self) -> &Option<tokio::sync::mpsc::UnboundedSender<SourceMetadata>> {
+        &None
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+pub trait HttpSourceBuilder {
+    fn build_source(
+        id: SourceId,
+        tokens: Vec<String>,
+        max_body_bytes: usize,
+        payload_encoding: PayloadEncoding,
+        channel_capacity: usize,
+    ) -> Box<dyn Source + Send + Sync + 'static> {
+        Box::new(HttpSource::new(
+            id,
+            tokens,
+            max_body_bytes,
+            payload_encoding,
+            channel_capacity,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits_events() {
+        assert_eq!(
+            split_events(b"{\"id\":1}\n{\"id\":2}", Some("application/json")),
+            vec![b"{\"id\":1}\n{\"id\":2}".to_vec()]
+        );
+        assert_eq!(split_events(b"hello", None), vec![b"hello".to_vec()]);
+        assert_eq!(
+            split_events(
+                b"{\"id\":1}\r\n\n  \n{\"id\":2}\n",
+                Some("application/x-ndjson; charset=utf-8")
+            ),
+            vec![b"{\"id\":1}".to_vec(), b"{\"id\":2}".to_vec()]
+        );
+        assert!(split_events(b"", Some(NDJSON_CONTENT_TYPE)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_authorizes_bearer_tokens() {
+        let source = HttpSource::new(
+            "test".into(),
+            vec!["first".into(), "second".into()],
+            1024,
+            PayloadEncoding::Utf8,
+            10,
+        );
+
+        assert!(source.authorize(Some("Bearer first")));
+        assert!(source.authorize(Some("bearer second")));
+        assert!(!source.authorize(Some("Bearer third")));
+        assert!(!source.authorize(Some("Bearer firs")));
+        assert!(!source.authorize(Some("Basic first")));
+        assert!(!source.authorize(Some("first")));
+        assert!(!source.authorize(None));
+    }
+
+    #[tokio::test]
+    async fn test_publishes_events() {
+        let mut source = HttpSource::new(
+            "test".into(),
+            vec!["token".into()],
+            1024,
+            PayloadEncoding::Utf8,
+            10,
+        );
+        let mut rx = source.subscribe().unwrap();
+
+        assert_eq!(
+            source
+                .publisher()
+                .publish(vec![b"a".to_vec(), b"b".to_vec()]),
+            2
+        );
+
+        for expected in [b"a", b"b"] {
+            match rx.recv().await.unwrap() {
+                SourceMessage::Result(SourceResult::Custom(result)) => {
+                    assert_eq!(result.source_id, "test");
+                    assert_eq!(result.kind, HTTP_SOURCE_KIND);
+                    assert_eq!(result.payload, expected);
+                    assert!(result.timestamp > 0);
+                }
+                msg => panic!("Expected HTTP result. Received {:?}", msg),
+            }
+        }
+    }
+
+    #[test]
+    fn test_counts_only_delivered_events() {
+        let source = HttpSource::new(
+            "test".into(),
+            vec!["token".into()],
+            1024,
+            PayloadEncoding::Utf8,
+            10,
+        );
+
+        assert_eq!(source.publisher().publish(vec![b"a".to_vec()]), 0);
+    }
+}
@@ -1,25 +1,38 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::ops::Range;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
+use futures::future::{Either, Fuse};
 use futures::stream::StreamExt;
-use futures::{future::Fuse, FutureExt};
+use futures::{FutureExt, Stream};
 use maplit::btreemap;
 use rdkafka::client::{Client, DefaultClientContext};
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::message::Headers;
 use rdkafka::{
-    consumer::{Consumer, StreamConsumer},
-    ClientConfig,
+    consumer::{BaseConsumer, Consumer, ConsumerContext, Rebalance, StreamConsumer},
+    statistics::Statistics,
+    ClientConfig, ClientContext,
 };
 use rdkafka::{Message, TopicPartitionList};
+use regex::Regex;
+use serde::Deserialize;
 use tokio::sync::{
     broadcast::{Receiver, Sender},
     oneshot,
 };
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
 
+use crate::decode::Decoder;
 use crate::hook;
+use crate::protocol::PayloadEncoding;
 
-use super::{Source, SourceId, SourceMessage, SourceMetadata, SourceResult, SubscribeError};
+use super::{
+    ChannelUsage, PendingReplay, Replay, Source, SourceHealth, SourceId, SourceMessage,
+    SourceMetadata, SourceResult, SourceStream, SubscribeError,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct KafkaSourceResult {
@@ -29,6 +42,8 @@ pub struct KafkaSourceResult {
     pub key: Option<Vec<u8>>,
     /// Event payload
     pub payload: Option<Vec<u8>>,
+    /// Event headers, in the order they were produced. Header keys may repeat
+    pub headers: Vec<(String, Option<Vec<u8>>)>,
     /// Topic this event was produced from
     pub topic: String,
     /// Timestamp at which the message was produced
@@ -37,34 +52,388 @@ pub struct KafkaSourceResult {
     pub partition: i32,
     /// Offset at which the message was produced
     pub offset: i64,
+    /// How the key and payload are represented when delivered to clients
+    pub payload_encoding: PayloadEncoding,
+}
+
+impl KafkaSourceResult {
+    fn from_message<M: Message>(
+        id: SourceId,
+        payload_encoding: PayloadEncoding,
+        message: &M,
+    ) -> Self {
+        Self {
+            id,
+            key: message.key().map(|k| k.to_owned()),
+            payload: message.payload().map(|p| p.to_owned()),
+            headers: message
+                .headers()
+                .map(|headers| {
+                    headers
+                        .iter()
+                        .map(|header| (header.key.to_string(), header.value.map(|v| v.to_owned())))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            topic: message.topic().to_string(),
+            timestamp: message.timestamp().to_millis(),
+            partition: message.partition(),
+            offset: message.offset(),
+            payload_encoding,
+        }
+    }
+}
+
+/// Converts consumed messages into source results, decoding their payloads
+/// if the source is configured with a decoder
+#[derive(Clone)]
+pub struct ResultBuilder {
+    source_id: SourceId,
+    payload_encoding: PayloadEncoding,
+    decoder: Option<Arc<Decoder>>,
 }
 
+impl ResultBuilder {
+    pub fn new(
+        source_id: SourceId,
+        payload_encoding: PayloadEncoding,
+        decoder: Option<Arc<Decoder>>,
+    ) -> Self {
+        Self {
+            source_id,
+            payload_encoding,
+            decoder,
+        }
+    }
+
+    fn build<M: Message>(&self, message: &M) -> KafkaSourceResult {
+        KafkaSourceResult::from_message(self.source_id.clone(), self.payload_encoding, message)
+    }
+
+    /// Replaces the payload of the result with its decoded JSON representation.
+    /// Payloads that cannot be decoded are left untouched
+    async fn decode(&self, mut result: KafkaSourceResult) -> KafkaSourceResult {
+        if let (Some(decoder), Some(payload)) = (self.decoder.as_ref(), result.payload.as_ref()) {
+            match decoder.decode(payload).await {
+                Ok(value) => {
+                    result.payload =
+                        Some(serde_json::to_vec(&value).expect("JSON value is serializable"));
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        source_id = result.id,
+                        partition = result.partition,
+                        offset = result.offset,
+                        "Failed to decode Kafka payload: {:#}",
+                        err
+                    );
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// The position within each partition that a Kafka source starts consuming from
+/// when it is created
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartFrom {
+    /// Start from the earliest offset retained by the broker
+    Earliest,
+    /// Start from the high watermark, observing only events produced after
+    /// the source is created
+    #[default]
+    Latest,
+    /// Start from the earliest offset whose timestamp (in milliseconds since
+    /// the Unix epoch) is greater than or equal to the given timestamp
+    TimestampMs(i64),
+    /// Start `n` events before the high watermark of each partition
+    LastN(u64),
+}
+
+impl StartFrom {
+    /// Resolves the starting offset for the given partition from its watermarks.
+    /// Returns `None` if the offset cannot be determined from watermarks alone
+    fn offset_from_watermarks(&self, partition: &PartitionMetadata) -> Option<i64> {
+        match *self {
+            StartFrom::Earliest => Some(partition.lo_watermark),
+            StartFrom::Latest => Some(partition.hi_watermark),
+            StartFrom::LastN(n) => Some(
+                partition
+                    .hi_watermark
+                    .saturating_sub(n.try_into().unwrap_or(i64::MAX))
+                    .max(partition.lo_watermark),
+            ),
+            StartFrom::TimestampMs(_) => None,
+        }
+    }
+}
+
+/// Topics consumed by a Kafka source. Following the `librdkafka` convention,
+/// topics starting with `^` are regular expressions matched against topic names
 #[derive(Debug, Clone)]
+pub enum TopicSelector {
+    Name(String),
+    Pattern(Regex),
+}
+
+impl TopicSelector {
+    pub fn parse(topic: &str) -> anyhow::Result<Self> {
+        if topic.starts_with('^') {
+            let pattern = Regex::new(topic).context(format!("Invalid topic pattern {}", topic))?;
+
+            Ok(TopicSelector::Pattern(pattern))
+        } else {
+            Ok(TopicSelector::Name(topic.to_string()))
+        }
+    }
+
+    /// Returns the topic name if the selector identifies a single topic
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            TopicSelector::Name(name) => Some(name.as_str()),
+            TopicSelector::Pattern(_) => None,
+        }
+    }
+
+    pub fn matches(&self, topic: &str) -> bool {
+        match self {
+            TopicSelector::Name(name) => name == topic,
+            TopicSelector::Pattern(pattern) => pattern.is_match(topic),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            TopicSelector::Name(name) => name.as_str(),
+            TopicSelector::Pattern(pattern) => pattern.as_str(),
+        }
+    }
+}
+
+/// How the consumers of a Kafka source are grouped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsumerGroup {
+    /// Every partition of the topic is assigned to this instance, using a unique
+    /// group ID with the specified prefix
+    Unique { prefix: String },
+    /// Partitions are balanced across all members of the specified consumer
+    /// group, allowing multiple instances to share a topic
+    Shared(String),
+}
+
+/// Snapshot of the topics consumed by a source
+#[derive(Debug, Clone, Default)]
 pub struct KafkaSourceMetadata {
-    partitions: Vec<PartitionMetadata>,
+    /// Partitions of each selected topic that currently exists
+    topics: BTreeMap<String, Vec<PartitionMetadata>>,
+    /// Selected topics whose metadata could not be fetched. Consumers for these
+    /// topics are left untouched until their metadata is available again
+    unavailable: BTreeSet<String>,
+}
+
+impl KafkaSourceMetadata {
+    /// Returns the partitions of each selected topic that currently exists
+    pub fn topics(&self) -> &BTreeMap<String, Vec<PartitionMetadata>> {
+        &self.topics
+    }
+
+    /// Returns whether the metadata of the topic could not be fetched, in which
+    /// case the topic is omitted from [`KafkaSourceMetadata::topics`]
+    pub fn is_unavailable(&self, topic: &str) -> bool {
+        self.unavailable.contains(topic)
+    }
+
+    /// Returns the partitions of each topic which are being consumed but no longer
+    /// exist, keyed by topic name. Topics that have been deleted have all of their
+    /// partitions listed
+    fn removed_partitions<'a, I>(&self, consumed: I) -> BTreeMap<String, BTreeSet<i32>>
+    where
+        I: IntoIterator<Item = &'a (String, i32)>,
+    {
+        let mut removed: BTreeMap<String, BTreeSet<i32>> = BTreeMap::new();
+
+        for (topic, partition) in consumed {
+            if self.unavailable.contains(topic) {
+                continue;
+            }
+
+            let exists = self
+                .topics
+                .get(topic)
+                .is_some_and(|partitions| partitions.iter().any(|p| p.partition == *partition));
+
+            if !exists {
+                removed.entry(topic.clone()).or_default().insert(*partition);
+            }
+        }
+
+        removed
+    }
+}
+
+/// How often clients report statistics, which are used to detect that a
+/// degraded source has recovered
+const STATISTICS_INTERVAL_MS: &str = "5000";
+
+/// Tracks the health of a source from the errors and statistics reported by
+/// its clients
+pub struct HealthContext {
+    health: SourceHealth,
+}
+
+impl ClientContext for HealthContext {
+    fn stats(&self, statistics: Statistics) {
+        // Bootstrap and internal brokers do not serve any requests on behalf of
+        // the consumer, so only learned brokers are considered
+        let connected = statistics
+            .brokers
+            .values()
+            .any(|broker| broker.nodeid >= 0 && broker.state == "UP");
+
+        if connected {
+            self.health.recovered();
+        }
+    }
+
+    fn error(&self, error: KafkaError, reason: &str) {
+        tracing::error!("librdkafka: {}: {}", error, reason);
+
+        if let KafkaError::Global(RDKafkaErrorCode::AllBrokersDown) = error {
+            self.health
+                .degraded(format!("All Kafka brokers are down: {}", reason));
+        }
+    }
 }
 
-pub struct PartitionConsumer {
+impl ConsumerContext for HealthContext {}
+
+/// Tracks the partitions assigned to a source by its consumer group
+pub struct GroupContext {
     source_id: SourceId,
-    consumer: StreamConsumer,
+    topics: TopicSelector,
+    assignment: Assignment,
+    tx: Sender<SourceMessage>,
+    health: HealthContext,
+}
+
+impl GroupContext {
+    /// Returns the topic/partitions of the list selected by the source. Pattern
+    /// subscriptions are assigned partitions of the concrete topics they match
+    fn partitions(&self, tpl: &TopicPartitionList) -> BTreeSet<(String, i32)> {
+        tpl.elements()
+            .iter()
+            .filter(|elem| self.topics.matches(elem.topic()))
+            .map(|elem| (elem.topic().to_string(), elem.partition()))
+            .collect()
+    }
+}
+
+impl ClientContext for GroupContext {
+    fn stats(&self, statistics: Statistics) {
+        self.health.stats(statistics)
+    }
+
+    fn error(&self, error: KafkaError, reason: &str) {
+        self.health.error(error, reason)
+    }
+}
+
+impl ConsumerContext for GroupContext {
+    fn pre_rebalance(&self, rebalance: &Rebalance) {
+        if let Rebalance::Revoke(tpl) = rebalance {
+            let revoked = self.partitions(tpl);
+
+            if revoked.is_empty() {
+                return;
+            }
+
+            self.assignment
+                .lock()
+                .expect("poisoned lock")
+                .retain(|partition| !revoked.contains(partition));
+
+            tracing::info!(
+                source_id = self.source_id,
+                topic = self.topics.as_str(),
+                "Partitions {:?} revoked by consumer group",
+                revoked
+            );
+
+            // Subscriptions are only routed to partitions of single topic sources,
+            // so the revoked partitions are identified by their ID alone
+            let revoked = revoked
+                .into_iter()
+                .map(|(_, partition)| partition)
+                .collect();
+
+            let _ = self.tx.send(SourceMessage::PartitionsRevoked(revoked));
+        }
+    }
+
+    fn post_rebalance(&self, rebalance: &Rebalance) {
+        match rebalance {
+            Rebalance::Assign(tpl) => {
+                let assigned = self.partitions(tpl);
+
+                if assigned.is_empty() {
+                    return;
+                }
+
+                tracing::info!(
+                    source_id = self.source_id,
+                    topic = self.topics.as_str(),
+                    "Partitions {:?} assigned by consumer group",
+                    assigned
+                );
+
+                self.assignment
+                    .lock()
+                    .expect("poisoned lock")
+                    .extend(assigned);
+            }
+            Rebalance::Error(err) => {
+                tracing::error!(
+                    source_id = self.source_id,
+                    topic = self.topics.as_str(),
+                    "Consumer group rebalance failed: {}",
+                    err
+                );
+            }
+            Rebalance::Revoke(_) => (),
+        }
+    }
+}
+
+/// Relays messages from a consumer to the source's subscribers. The consumer is
+/// either assigned a single partition or is a member of a consumer group
+pub struct PartitionConsumer<C: ConsumerContext + 'static = HealthContext> {
+    results: ResultBuilder,
+    consumer: StreamConsumer<C>,
     shutdown_rx: Fuse<oneshot::Receiver<()>>,
     tx: Sender<SourceMessage>,
 }
 
 impl PartitionConsumer {
+    #[allow(clippy::too_many_arguments)]
     pub fn new<'a>(
-        source_id: SourceId,
+        results: ResultBuilder,
         topic: &'a str,
         partition: i32,
         offset: rdkafka::Offset,
         client_config: &'a ClientConfig,
         shutdown_rx: Fuse<oneshot::Receiver<()>>,
         tx: Sender<SourceMessage>,
+        health: SourceHealth,
     ) -> anyhow::Result<Self> {
-        let consumer: StreamConsumer = client_config.create().context(format!(
-            "Failed to create stream consumer for topic/partition {}/{}",
-            topic, partition,
-        ))?;
+        let consumer: StreamConsumer<HealthContext> = client_config
+            .create_with_context(HealthContext { health })
+            .context(format!(
+                "Failed to create stream consumer for topic/partition {}/{}",
+                topic, partition,
+            ))?;
 
         let mut tpl = TopicPartitionList::new();
 
@@ -80,13 +449,15 @@ impl PartitionConsumer {
         ))?;
 
         Ok(Self {
-            source_id,
+            results,
             consumer,
             shutdown_rx,
             tx,
         })
     }
+}
 
+impl<C: ConsumerContext + 'static> PartitionConsumer<C> {
     pub async fn run(mut self) {
         let mut stream = self.consumer.stream();
 
@@ -94,7 +465,7 @@ impl PartitionConsumer {
             tokio::select! {
                 _ = &mut self.shutdown_rx => break,
                 next = stream.next() => {
-                    match next {
+                    let result = match next {
                         Some(message) => {
                             match message {
                                 Err(err) => {
@@ -102,26 +473,20 @@ impl PartitionConsumer {
                                         "Encountered Kafka error while yielding messages: {}",
                                         err
                                     );
+                                    continue;
                                 }
-                                Ok(borrowed_message) => {
-                                    let owned_message = borrowed_message.detach();
-                                    // An error here does not mean future calls will fail, since new subscribers
-                                    // may be created. If there are no subscribers, we simply discard the message
-                                    // and move on
-                                    let _ = self.tx.send(SourceMessage::Result(SourceResult::Kafka(KafkaSourceResult {
-                                        id: self.source_id.clone(),
-                                        key: owned_message.key().map(|k| k.to_owned()),
-                                        payload: owned_message.payload().map(|p| p.to_owned()),
-                                        topic: owned_message.topic().to_string(),
-                                        timestamp: owned_message.timestamp().to_millis(),
-                                        partition: owned_message.partition(),
-                                        offset: owned_message.offset(),
-                                    })));
-                                }
-                            };
+                                Ok(borrowed_message) => self.results.build(&borrowed_message),
+                            }
                         },
                         None => break,
-                    }
+                    };
+
+                    let result = self.results.decode(result).await;
+
+                    // An error here does not mean future calls will fail, since new subscribers
+                    // may be created. If there are no subscribers, we simply discard the message
+                    // and move on
+                    let _ = self.tx.send(SourceMessage::Result(SourceResult::Kafka(result)));
                 }
             }
         }
@@ -130,13 +495,23 @@ impl PartitionConsumer {
 
 type ShutdownTrigger = oneshot::Sender<()>;
 
+/// Topic/partitions assigned to a balanced source by its consumer group
+type Assignment = Arc<Mutex<BTreeSet<(String, i32)>>>;
+
 pub struct KafkaTopicSource {
     id: SourceId,
-    topic: String,
-    // Map of partition ID -> shutdown trigger
-    _partition_consumers: Arc<Mutex<BTreeMap<i32, ShutdownTrigger>>>,
+    topics: TopicSelector,
+    // Map of (topic, partition ID) -> shutdown trigger
+    partition_consumers: Arc<Mutex<BTreeMap<(String, i32), ShutdownTrigger>>>,
+    // Shutdown trigger for the consumer group member of a balanced source
+    _group_consumer: Option<ShutdownTrigger>,
+    assignment: Option<Assignment>,
     tx: Sender<SourceMessage>,
+    channel_capacity: usize,
     metadata_tx: Option<tokio::sync::mpsc::UnboundedSender<SourceMetadata>>,
+    client_config: ClientConfig,
+    results: ResultBuilder,
+    health: SourceHealth,
 }
 
 impl Source for KafkaTopicSource {
@@ -144,6 +519,80 @@ impl Source for KafkaTopicSource {
         Ok(self.tx.subscribe())
     }
 
+    fn subscribe_with_replay(&mut self, replay: Replay) -> Result<PendingReplay, SubscribeError> {
+        // Subscribe to live events before resolving the replay ranges. Every event
+        // below the resolved high watermarks is then delivered by the replay consumer,
+        // and every event at or above them is delivered by the live stream
+        //
+        // Offsets are per partition, so replay is only supported by single topic sources
+        let topic = self
+            .topics
+            .name()
+            .ok_or(SubscribeError::ReplayUnsupported)?
+            .to_string();
+        let live = BroadcastStream::new(self.tx.subscribe());
+
+        let assigned = self.partitions().unwrap_or_default();
+        let client_config = self.client_config.clone();
+        let results = self.results.clone();
+        let capacity = self.channel_capacity;
+
+        Ok(Box::pin(async move {
+            // Resolving the replay ranges blocks on metadata, watermark and offset
+            // lookups against the cluster
+            let (consumer, ranges) = tokio::task::spawn_blocking(move || {
+                create_replay_consumer(&topic, &replay, &client_config, &assigned)
+            })
+            .await
+            .map_err(|err| SubscribeError::ReplayFailed(err.to_string()))?
+            .map_err(|err| SubscribeError::ReplayFailed(format!("{:#}", err)))?;
+
+            let catch_up = replay_stream(results, consumer, ranges.clone());
+
+            Ok(Box::pin(handover(catch_up, live, ranges, capacity)) as SourceStream)
+        }))
+    }
+
+    fn kafka_topics(&self) -> Option<&TopicSelector> {
+        Some(&self.topics)
+    }
+
+    fn partitions(&self) -> Option<BTreeSet<i32>> {
+        // Partition IDs are ambiguous across topics, so sources consuming multiple
+        // topics are not considered partitioned
+        self.topics.name()?;
+
+        let partitions = match self.assignment.as_ref() {
+            Some(assignment) => assignment
+                .lock()
+                .expect("poisoned lock")
+                .iter()
+                .map(|(_, partition)| *partition)
+                .collect(),
+            None => self
+                .partition_consumers
+                .lock()
+                .expect("poisoned lock")
+                .keys()
+                .map(|(_, partition)| *partition)
+                .collect(),
+        };
+
+        Some(partitions)
+    }
+
+    fn degraded_since(&self) -> Option<Instant> {
+        self.health.degraded_since()
+    }
+
+    fn degraded_reason(&self) -> Option<String> {
+        self.health.degraded_reason()
+    }
+
+    fn channel_usage(&self) -> Option<ChannelUsage> {
+        Some(ChannelUsage::new(&self.tx, self.channel_capacity))
+    }
+
     fn source_id(&self) -> &SourceId {
         &self.id
     }
@@ -158,69 +607,124 @@ impl Source for KafkaTopicSource {
 }
 
 impl KafkaTopicSource {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: SourceId,
         topic: String,
-        bootstrap_servers: &[String],
+        client_properties: &BTreeMap<String, String>,
+        consumer_group: ConsumerGroup,
+        start_from: StartFrom,
+        payload_encoding: PayloadEncoding,
+        decoder: Option<Arc<Decoder>>,
+        channel_capacity: usize,
+    ) -> anyhow::Result<Self> {
+        let results = ResultBuilder::new(id.clone(), payload_encoding, decoder);
+        let topics = TopicSelector::parse(topic.as_str())?;
+
+        match consumer_group {
+            ConsumerGroup::Unique { prefix } => Self::assigned(
+                id,
+                topics,
+                client_properties,
+                &prefix,
+                start_from,
+                results,
+                channel_capacity,
+            ),
+            ConsumerGroup::Shared(group_id) => Self::balanced(
+                id,
+                topics,
+                client_properties,
+                &group_id,
+                start_from,
+                results,
+                channel_capacity,
+            ),
+        }
+    }
+
+    /// Creates a source that consumes every partition of the selected topics,
+    /// with one consumer per partition
+    fn assigned(
+        id: SourceId,
+        topics: TopicSelector,
+        client_properties: &BTreeMap<String, String>,
         group_id_prefix: &str,
+        start_from: StartFrom,
+        results: ResultBuilder,
+        channel_capacity: usize,
     ) -> anyhow::Result<Self> {
-        // TODO: make this capacity configurable
-        let (tx, _) = tokio::sync::broadcast::channel::<SourceMessage>(100);
+        let (tx, _) = tokio::sync::broadcast::channel::<SourceMessage>(channel_capacity);
+        let health = SourceHealth::new(id.clone(), tx.clone());
         let (metadata_tx, mut metadata_rx) =
             tokio::sync::mpsc::unbounded_channel::<SourceMetadata>();
         let consumer_tasks = Arc::new(Mutex::new(BTreeMap::new()));
 
         // Transient client used to fetch metadata and watermarks
-        let metadata_client = create_metadata_client(bootstrap_servers)?;
+        let metadata_client = create_metadata_client(client_properties)?;
 
         let mut client_config = ClientConfig::new();
 
         let group_id = format!("{}{}", group_id_prefix, nanoid::nanoid!());
 
+        // A friendly label to present to Kafka
+        client_config.set("client.id", "kiwi");
+        client_config.set("statistics.interval.ms", STATISTICS_INTERVAL_MS);
+        client_config.extend(client_properties.clone());
+        // Settings the partition consumers rely on cannot be overridden
         client_config.extend(btreemap! {
             "group.id".to_string() => group_id,
             // We don't care about offset committing, since we are just relaying the latest messages.
             "enable.auto.commit".to_string() => "true".to_string(),
             "enable.partition.eof".to_string() => "false".to_string(),
-            // A friendly label to present to Kafka
-            "client.id".to_string() => "kiwi".to_string(),
-            "bootstrap.servers".to_string() => bootstrap_servers.join(","),
             "topic.metadata.refresh.interval.ms".to_string() => (-1).to_string(),
         });
 
-        for partition_metadata in fetch_partition_metadata(topic.as_str(), &metadata_client)? {
-            let (shutdown_trigger, shutdown_rx) = oneshot::channel::<()>();
+        for (topic, partitions) in fetch_topic_metadata(&topics, &metadata_client)?.topics {
+            let start_offsets =
+                resolve_start_offsets(topic.as_str(), &partitions, start_from, &client_config)?;
 
-            let partition_consumer = PartitionConsumer::new(
-                id.clone(),
-                topic.as_str(),
-                partition_metadata.partition,
-                rdkafka::Offset::Offset(partition_metadata.hi_watermark),
-                &client_config,
-                shutdown_rx.fuse(),
-                tx.clone(),
-            )
-            .context(format!(
-                "Failed to create partition consumer for topic/partition {}/{}",
-                topic, partition_metadata.partition
-            ))?;
+            for partition_metadata in partitions {
+                let (shutdown_trigger, shutdown_rx) = oneshot::channel::<()>();
 
-            tokio::task::spawn(partition_consumer.run());
+                let partition_consumer = PartitionConsumer::new(
+                    results.clone(),
+                    topic.as_str(),
+                    partition_metadata.partition,
+                    rdkafka::Offset::Offset(start_offsets[&partition_metadata.partition]),
+                    &client_config,
+                    shutdown_rx.fuse(),
+                    tx.clone(),
+                    health.clone(),
+                )
+                .context(format!(
+                    "Failed to create partition consumer for topic/partition {}/{}",
+                    topic, partition_metadata.partition
+                ))?;
 
-            consumer_tasks
-                .lock()
-                .expect("poisoned lock")
-                .insert(partition_metadata.partition, shutdown_trigger);
+                tokio::task::spawn(partition_consumer.run());
+
+                consumer_tasks.lock().expect("poisoned lock").insert(
+                    (topic.clone(), partition_metadata.partition),
+                    shutdown_trigger,
+                );
+            }
         }
 
         let weak_tasks = Arc::downgrade(&consumer_tasks);
 
         let result = Self {
             id: id.clone(),
-            topic: topic.clone(),
-            _partition_consumers: consumer_tasks,
+            topics: topics.clone(),
+            partition_consumers: consumer_tasks,
+            _group_consumer: None,
+            assignment: None,
             tx: tx.clone(),
+            channel_capacity,
             metadata_tx: Some(metadata_tx),
+            client_config: client_config.clone(),
+            results: results.clone(),
+            health: health.clone(),
         };
 
         let client_config = client_config.clone();
@@ -229,64 +733,134 @@ impl KafkaTopicSource {
             while let Some(metadata) = metadata_rx.recv().await {
                 if let Some(tasks) = weak_tasks.upgrade() {
                     match metadata {
-                        SourceMetadata::Kafka(topic_metadata) => {
-                            for PartitionMetadata {
-                                partition,
-                                hi_watermark,
-                                ..
-                            } in topic_metadata.partitions
-                            {
+                        SourceMetadata::Kafka(metadata) => {
+                            let new_topics = {
                                 let mut tasks = tasks.lock().expect("poisoned lock");
 
-                                match tasks.entry(partition) {
-                                    std::collections::btree_map::Entry::Vacant(entry) => {
-                                        let (shutdown_trigger, shutdown_rx) =
-                                            oneshot::channel::<()>();
-
-                                        match PartitionConsumer::new(
-                                            id.clone(),
-                                            topic.as_str(),
-                                            partition,
-                                            rdkafka::Offset::Offset(hi_watermark),
-                                            &client_config,
-                                            shutdown_rx.fuse(),
-                                            tx.clone(),
-                                        ) {
-                                            Ok(partition_consumer) => {
-                                                let _ = tx.send(SourceMessage::MetadataChanged(
-                                                    format!(
-                                                        "New partition ({}) observed for topic {}",
-                                                        topic, partition
-                                                    ),
-                                                ));
-
-                                                tokio::task::spawn(partition_consumer.run());
-                                                entry.insert(shutdown_trigger);
-
-                                                tracing::debug!(
+                                // Consumers of partitions that no longer exist (e.g. because their topic
+                                // was deleted) are shut down. If the topic is recreated, it is treated as
+                                // a newly observed topic below
+                                for (topic, partitions) in metadata.removed_partitions(tasks.keys())
+                                {
+                                    for partition in partitions.iter() {
+                                        if let Some(shutdown_trigger) =
+                                            tasks.remove(&(topic.clone(), *partition))
+                                        {
+                                            let _ = shutdown_trigger.send(());
+                                        }
+                                    }
+
+                                    let message = removed_partitions_message(
+                                        &topic,
+                                        &partitions,
+                                        metadata.topics.contains_key(&topic),
+                                    );
+
+                                    tracing::info!(
+                                        topic = topic.as_str(),
+                                        partitions = ?partitions,
+                                        "Shut down consumers of removed partitions"
+                                    );
+
+                                    let _ = tx.send(SourceMessage::MetadataChanged(message));
+                                }
+
+                                metadata
+                                    .topics
+                                    .iter()
+                                    .filter(|(topic, _)| !tasks.keys().any(|(t, _)| t == *topic))
+                                    .map(|(topic, partitions)| (topic.clone(), partitions.clone()))
+                                    .collect::<Vec<_>>()
+                            };
+
+                            // Topics observed after the source is created (e.g. topics newly matching
+                            // a pattern) start from the configured position, whereas partitions added
+                            // to known topics always start from the high watermark
+                            let start_offsets = resolve_discovered_start_offsets(
+                                new_topics,
+                                start_from,
+                                &client_config,
+                            )
+                            .await;
+
+                            let mut tasks = tasks.lock().expect("poisoned lock");
+
+                            for (topic, partitions) in metadata.topics {
+                                let new_topic = !tasks.keys().any(|(t, _)| *t == topic);
+
+                                for PartitionMetadata {
+                                    partition,
+                                    hi_watermark,
+                                    ..
+                                } in partitions
+                                {
+                                    match tasks.entry((topic.clone(), partition)) {
+                                        std::collections::btree_map::Entry::Vacant(entry) => {
+                                            let (shutdown_trigger, shutdown_rx) =
+                                                oneshot::channel::<()>();
+
+                                            let offset = if new_topic {
+                                                // Topics whose starting position could not be resolved
+                                                // are retried once they are discovered again
+                                                match start_offsets
+                                                    .get(&topic)
+                                                    .and_then(|offsets| offsets.get(&partition))
+                                                {
+                                                    Some(offset) => *offset,
+                                                    None => continue,
+                                                }
+                                            } else {
+                                                hi_watermark
+                                            };
+
+                                            match PartitionConsumer::new(
+                                                results.clone(),
+                                                topic.as_str(),
+                                                partition,
+                                                rdkafka::Offset::Offset(offset),
+                                                &client_config,
+                                                shutdown_rx.fuse(),
+                                                tx.clone(),
+                                                health.clone(),
+                                            ) {
+                                                Ok(partition_consumer) => {
+                                                    if !new_topic {
+                                                        let message = new_partition_message(
+                                                            &topic, partition,
+                                                        );
+                                                        let _ = tx.send(
+                                                            SourceMessage::MetadataChanged(message),
+                                                        );
+                                                    }
+
+                                                    tokio::task::spawn(partition_consumer.run());
+                                                    entry.insert(shutdown_trigger);
+
+                                                    tracing::debug!(
                                                     topic = topic.as_str(),
                                                     partition = partition,
                                                     "Observed new partition. Created new partition consumer"
                                                 );
-                                            }
-                                            Err(err) => {
-                                                tracing::error!(
-                                                    topic = topic,
-                                                    partition = partition,
-                                                    error = ?err,
-                                                    "Failed to create partition consumer",
-                                                );
+                                                }
+                                                Err(err) => {
+                                                    tracing::error!(
+                                                        topic = topic,
+                                                        partition = partition,
+                                                        error = ?err,
+                                                        "Failed to create partition consumer",
+                                                    );
+                                                }
                                             }
                                         }
+                                        std::collections::btree_map::Entry::Occupied(_) => (),
                                     }
-                                    std::collections::btree_map::Entry::Occupied(_) => (),
                                 }
                             }
                         }
                     }
                 } else {
                     tracing::debug!(
-                        topic = topic.as_str(),
+                        topic = topics.as_str(),
                         "Topic source has been dropped. Exiting partition creation task"
                     );
                     // The absence of the consumer map indicates that the source
@@ -298,6 +872,98 @@ impl KafkaTopicSource {
 
         Ok(result)
     }
+
+    /// Creates a source that joins the specified consumer group, consuming only
+    /// the partitions assigned to it by the group
+    fn balanced(
+        id: SourceId,
+        topics: TopicSelector,
+        client_properties: &BTreeMap<String, String>,
+        group_id: &str,
+        start_from: StartFrom,
+        results: ResultBuilder,
+        channel_capacity: usize,
+    ) -> anyhow::Result<Self> {
+        // Offsets are resolved by the group, so the starting position only applies
+        // to partitions without committed offsets
+        let auto_offset_reset = match start_from {
+            StartFrom::Earliest => "earliest",
+            StartFrom::Latest => "latest",
+            _ => anyhow::bail!(
+                "Kafka sources that join a consumer group can only start from the earliest or latest offset"
+            ),
+        };
+
+        let (tx, _) = tokio::sync::broadcast::channel::<SourceMessage>(channel_capacity);
+        let health = SourceHealth::new(id.clone(), tx.clone());
+        let assignment = Arc::new(Mutex::new(BTreeSet::new()));
+
+        let mut client_config = ClientConfig::new();
+
+        client_config.set("client.id", "kiwi");
+        client_config.set("statistics.interval.ms", STATISTICS_INTERVAL_MS);
+        // Cooperative rebalancing only revokes the partitions that move between
+        // members, leaving subscriptions to other partitions intact
+        client_config.set("partition.assignment.strategy", "cooperative-sticky");
+        client_config.extend(client_properties.clone());
+        client_config.extend(btreemap! {
+            "group.id".to_string() => group_id.to_string(),
+            // Committed offsets allow partitions to resume where their previous owner left off
+            "enable.auto.commit".to_string() => "true".to_string(),
+            "auto.offset.reset".to_string() => auto_offset_reset.to_string(),
+            "enable.partition.eof".to_string() => "false".to_string(),
+        });
+
+        let context = GroupContext {
+            source_id: id.clone(),
+            topics: topics.clone(),
+            assignment: Arc::clone(&assignment),
+            tx: tx.clone(),
+            health: HealthContext {
+                health: health.clone(),
+            },
+        };
+
+        let consumer: StreamConsumer<GroupContext> =
+            client_config.create_with_context(context).context(format!(
+                "Failed to create group consumer for topic {}",
+                topics.as_str()
+            ))?;
+
+        // Patterns are natively supported by consumer group subscriptions
+        consumer.subscribe(&[topics.as_str()]).context(format!(
+            "Failed to subscribe group consumer to topic {}",
+            topics.as_str()
+        ))?;
+
+        let (shutdown_trigger, shutdown_rx) = oneshot::channel::<()>();
+
+        tokio::task::spawn(
+            PartitionConsumer {
+                results: results.clone(),
+                consumer,
+                shutdown_rx: shutdown_rx.fuse(),
+                tx: tx.clone(),
+            }
+            .run(),
+        );
+
+        Ok(Self {
+            id,
+            topics,
+            partition_consumers: Default::default(),
+            _group_consumer: Some(shutdown_trigger),
+            assignment: Some(assignment),
+            tx,
+            channel_capacity,
+            // New partitions are assigned by the consumer group, so partition
+            // discovery does not apply
+            metadata_tx: None,
+            client_config,
+            results,
+            health,
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -307,13 +973,33 @@ pub struct PartitionMetadata {
     pub lo_watermark: i64,
 }
 
-fn create_metadata_client(bootstrap_servers: &[String]) -> anyhow::Result<Client> {
+/// Describes partitions that were removed from a topic, or the deletion of the
+/// topic if none of its partitions remain
+fn removed_partitions_message(
+    topic: &str,
+    partitions: &BTreeSet<i32>,
+    topic_exists: bool,
+) -> String {
+    if topic_exists {
+        format!(
+            "Partitions {:?} were removed from topic {}",
+            partitions, topic
+        )
+    } else {
+        format!("Topic {} was deleted", topic)
+    }
+}
+
+/// Describes a partition that was added to a known topic
+fn new_partition_message(topic: &str, partition: i32) -> String {
+    format!("New partition ({}) observed for topic {}", partition, topic)
+}
+
+fn create_metadata_client(client_properties: &BTreeMap<String, String>) -> anyhow::Result<Client> {
     let mut client_config = ClientConfig::new();
 
-    client_config.extend(btreemap! {
-        "client.id".to_string() => "kiwi-metadata".to_string(),
-        "bootstrap.servers".to_string() => bootstrap_servers.join(","),
-    });
+    client_config.set("client.id", "kiwi-metadata");
+    client_config.extend(client_properties.clone());
 
     let native_config = client_config.create_native_config()?;
 
@@ -329,68 +1015,472 @@ fn create_metadata_client(bootstrap_servers: &[String]) -> anyhow::Result<Client
     Ok(client)
 }
 
-fn fetch_partition_metadata(
-    topic: &str,
+/// Fetches partition metadata for each of the selected topics
+fn fetch_topic_metadata(
+    topics: &TopicSelector,
     client: &Client,
-) -> anyhow::Result<Vec<PartitionMetadata>> {
-    let mut result = Vec::new();
-
-    let metadata = client.fetch_metadata(Some(topic), Duration::from_millis(5000))?;
-    let topic = &metadata.topics()[0];
-
-    for partition in topic.partitions() {
-        let (low, hi) =
-            client.fetch_watermarks(topic.name(), partition.id(), Duration::from_millis(5000))?;
-        result.push(PartitionMetadata {
-            partition: partition.id(),
-            hi_watermark: hi,
-            lo_watermark: low,
-        });
+) -> anyhow::Result<KafkaSourceMetadata> {
+    let mut result = KafkaSourceMetadata::default();
+
+    // Patterns are matched against the metadata of every topic in the cluster
+    let metadata = client.fetch_metadata(topics.name(), Duration::from_millis(5000))?;
+
+    for topic in metadata.topics() {
+        if !topics.matches(topic.name()) {
+            continue;
+        }
+
+        match topic.error().map(RDKafkaErrorCode::from) {
+            None => (),
+            // Topics that do not exist are omitted from the result
+            Some(RDKafkaErrorCode::UnknownTopicOrPartition) => continue,
+            Some(err) => {
+                tracing::warn!(
+                    topic = topic.name(),
+                    error = ?err,
+                    "Failed to fetch metadata for topic"
+                );
+                result.unavailable.insert(topic.name().to_string());
+                continue;
+            }
+        }
+
+        let mut partitions = Vec::new();
+
+        for partition in topic.partitions() {
+            let (low, hi) = client.fetch_watermarks(
+                topic.name(),
+                partition.id(),
+                Duration::from_millis(5000),
+            )?;
+            partitions.push(PartitionMetadata {
+                partition: partition.id(),
+                hi_watermark: hi,
+                lo_watermark: low,
+            });
+        }
+
+        result.topics.insert(topic.name().to_string(), partitions);
     }
 
     Ok(result)
 }
 
+/// Resolves the offset each partition consumer should start from, keyed by
+/// partition ID
+fn resolve_start_offsets(
+    topic: &str,
+    partitions: &[PartitionMetadata],
+    start_from: StartFrom,
+    client_config: &ClientConfig,
+) -> anyhow::Result<BTreeMap<i32, i64>> {
+    let timestamp = match start_from {
+        StartFrom::TimestampMs(timestamp) => timestamp,
+        _ => {
+            return Ok(partitions
+                .iter()
+                .filter_map(|partition| {
+                    start_from
+                        .offset_from_watermarks(partition)
+                        .map(|offset| (partition.partition, offset))
+                })
+                .collect());
+        }
+    };
+
+    // Looking up offsets by timestamp requires a consumer, so we create a transient
+    // one for the lookup
+    let consumer: BaseConsumer = client_config
+        .create()
+        .context("Failed to create consumer for offset lookup")?;
+
+    let mut tpl = TopicPartitionList::new();
+
+    for partition in partitions {
+        tpl.add_partition_offset(
+            topic,
+            partition.partition,
+            rdkafka::Offset::Offset(timestamp),
+        )?;
+    }
+
+    let resolved = consumer
+        .offsets_for_times(tpl, Duration::from_millis(5000))
+        .context(format!(
+            "Failed to look up offsets for topic {} at timestamp {}",
+            topic, timestamp
+        ))?;
+
+    Ok(partitions
+        .iter()
+        .map(|partition| {
+            let offset = match resolved
+                .find_partition(topic, partition.partition)
+                .map(|elem| elem.offset())
+            {
+                Some(rdkafka::Offset::Offset(offset)) => offset,
+                // No event has been produced at or after the timestamp, so there
+                // is nothing to replay
+                _ => partition.hi_watermark,
+            };
+
+            (partition.partition, offset)
+        })
+        .collect())
+}
+
+/// Resolves the starting offsets of topics discovered after a source is created,
+/// keyed by topic name. Topics whose offsets cannot be resolved are omitted
+async fn resolve_discovered_start_offsets(
+    topics: Vec<(String, Vec<PartitionMetadata>)>,
+    start_from: StartFrom,
+    client_config: &ClientConfig,
+) -> BTreeMap<String, BTreeMap<i32, i64>> {
+    if topics.is_empty() {
+        return BTreeMap::new();
+    }
+
+    let client_config = client_config.clone();
+
+    // Offsets looked up by timestamp block on the cluster
+    tokio::task::spawn_blocking(move || {
+        topics
+            .into_iter()
+            .filter_map(|(topic, partitions)| {
+                match resolve_start_offsets(&topic, &partitions, start_from, &client_config) {
+                    Ok(offsets) => Some((topic, offsets)),
+                    Err(err) => {
+                        tracing::error!(
+                            topic = topic,
+                            error = ?err,
+                            "Failed to resolve start offsets of discovered topic",
+                        );
+                        None
+                    }
+                }
+            })
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Creates a consumer assigned to the offset ranges that should be replayed for a
+/// subscription. Partitions with nothing to replay are omitted from the returned
+/// ranges
+fn create_replay_consumer(
+    topic: &str,
+    replay: &Replay,
+    client_config: &ClientConfig,
+    assigned: &BTreeSet<i32>,
+) -> anyhow::Result<(StreamConsumer, BTreeMap<i32, Range<i64>>)> {
+    let mut client_config = client_config.clone();
+    // Replay consumers are never committed and must be able to tell when they
+    // have reached the end of a partition. They also must not join the group of
+    // a balanced source
+    client_config.set("group.id", format!("kiwi-replay-{}", nanoid::nanoid!()));
+    client_config.set("enable.auto.commit", "false");
+    client_config.set("enable.partition.eof", "true");
+    // Replay consumers are short-lived and do not contribute to the health of the source
+    client_config.remove("statistics.interval.ms");
+
+    let consumer: StreamConsumer = client_config
+        .create()
+        .context("Failed to create replay consumer")?;
+
+    let metadata = consumer
+        .fetch_metadata(Some(topic), Duration::from_millis(5000))
+        .context(format!("Failed to fetch metadata for topic {}", topic))?;
+
+    let mut partitions = Vec::new();
+
+    if let Some(topic_metadata) = metadata.topics().first() {
+        for partition in topic_metadata.partitions() {
+            let (lo, hi) = consumer
+                .fetch_watermarks(topic, partition.id(), Duration::from_millis(5000))
+                .context(format!(
+                    "Failed to fetch watermarks for topic/partition {}/{}",
+                    topic,
+                    partition.id()
+                ))?;
+
+            partitions.push(PartitionMetadata {
+                partition: partition.id(),
+                hi_watermark: hi,
+                lo_watermark: lo,
+            });
+        }
+    }
+
+    let start_offsets = match replay {
+        Replay::Offsets(offsets) => {
+            for partition in offsets.keys() {
+                if !partitions.iter().any(|p| p.partition == *partition) {
+                    anyhow::bail!("Partition {} does not exist for topic {}", partition, topic);
+                }
+
+                if !assigned.contains(partition) {
+                    anyhow::bail!(
+                        "Partition {} of topic {} is not assigned to this instance",
+                        partition,
+                        topic
+                    );
+                }
+            }
+
+            offsets.clone()
+        }
+        Replay::Lookback(window) => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .context("System clock is set before the Unix epoch")?;
+            let timestamp = now.saturating_sub(*window).as_millis() as i64;
+
+            resolve_start_offsets(
+                topic,
+                &partitions,
+                StartFrom::TimestampMs(timestamp),
+                &client_config,
+            )?
+        }
+    };
+
+    // Only partitions consumed by this instance are replayed, since no live events
+    // would follow for other partitions
+    let ranges = partitions
+        .iter()
+        .filter(|partition| assigned.contains(&partition.partition))
+        .filter_map(|partition| {
+            start_offsets.get(&partition.partition).map(|start| {
+                let start = (*start).max(partition.lo_watermark);
+                (
+                    partition.partition,
+                    start..partition.hi_watermark.max(start),
+                )
+            })
+        })
+        .filter(|(_, range)| !range.is_empty())
+        .collect::<BTreeMap<_, _>>();
+
+    let mut tpl = TopicPartitionList::new();
+
+    for (partition, range) in ranges.iter() {
+        tpl.add_partition_offset(topic, *partition, rdkafka::Offset::Offset(range.start))?;
+    }
+
+    consumer.assign(&tpl).context(format!(
+        "Failed to assign replay ranges for topic {}",
+        topic
+    ))?;
+
+    Ok((consumer, ranges))
+}
+
+/// Number of consecutive Kafka errors after which a replay is abandoned
+const MAX_REPLAY_ERRORS: usize = 10;
+
+/// Yields every event within the given offset ranges, ending once each range
+/// has been exhausted. If the replay is abandoned because of repeated errors, a
+/// metadata change is yielded last, which closes the subscription
+fn replay_stream(
+    results: ResultBuilder,
+    consumer: StreamConsumer,
+    ranges: BTreeMap<i32, Range<i64>>,
+) -> impl Stream<Item = SourceMessage> + Send + Sync + 'static {
+    async_stream::stream! {
+        let mut remaining = ranges;
+        let mut stream = consumer.stream();
+        let mut errors = 0;
+
+        while !remaining.is_empty() {
+            let result = match stream.next().await {
+                Some(Ok(message)) => {
+                    errors = 0;
+
+                    let partition = message.partition();
+                    let offset = message.offset();
+
+                    let end = match remaining.get(&partition) {
+                        Some(range) => range.end,
+                        None => continue,
+                    };
+
+                    if offset + 1 >= end {
+                        remaining.remove(&partition);
+                    }
+
+                    if offset < end {
+                        results.build(&message)
+                    } else {
+                        continue;
+                    }
+                }
+                Some(Err(KafkaError::PartitionEOF(partition))) => {
+                    remaining.remove(&partition);
+                    continue;
+                }
+                Some(Err(err)) => {
+                    tracing::error!(
+                        source_id = results.source_id,
+                        "Encountered Kafka error while replaying messages: {}",
+                        err
+                    );
+
+                    errors += 1;
+
+                    if errors >= MAX_REPLAY_ERRORS {
+                        yield SourceMessage::MetadataChanged(format!(
+                            "Replay failed after {} consecutive errors: {}",
+                            errors, err
+                        ));
+                        break;
+                    }
+
+                    continue;
+                }
+                None => break,
+            };
+
+            yield SourceMessage::Result(SourceResult::Kafka(results.decode(result).await));
+        }
+    }
+}
+
+/// Returns whether the live message is an event that was already delivered by
+/// the replay. Once the live stream of a partition passes the end of its replayed
+/// range, later events of the partition can no longer have been replayed, even if
+/// the topic is recreated
+fn replayed(
+    ranges: &mut BTreeMap<i32, Range<i64>>,
+    message: &Result<SourceMessage, BroadcastStreamRecvError>,
+) -> bool {
+    match message {
+        Ok(SourceMessage::Result(SourceResult::Kafka(result))) => {
+            match ranges.get(&result.partition) {
+                Some(range) if result.offset < range.end => true,
+                Some(_) => {
+                    ranges.remove(&result.partition);
+                    false
+                }
+                None => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Yields all replayed events followed by the live stream. Live events are read
+/// while the replay is in progress and buffered, up to `capacity` events, so that
+/// they do not lag behind in the source channel. Events dropped from a full buffer
+/// are reported as lag. Live events that fall within the replayed ranges are
+/// discarded on hand-off so that no event is delivered twice
+fn handover<C, L>(
+    catch_up: C,
+    live: L,
+    ranges: BTreeMap<i32, Range<i64>>,
+    capacity: usize,
+) -> impl Stream<Item = Result<SourceMessage, BroadcastStreamRecvError>> + Send + Sync + 'static
+where
+    C: Stream<Item = SourceMessage> + Send + Sync + 'static,
+    L: Stream<Item = Result<SourceMessage, BroadcastStreamRecvError>> + Send + Sync + 'static,
+{
+    async_stream::stream! {
+        let mut ranges = ranges;
+        let mut catch_up = Box::pin(catch_up);
+        let mut live = Box::pin(live.fuse());
+        let mut buffered = VecDeque::new();
+        let mut dropped = 0;
+
+        loop {
+            let next = tokio::select! {
+                biased;
+
+                message = catch_up.next() => Either::Left(message),
+                Some(message) = live.next() => Either::Right(message),
+            };
+
+            match next {
+                Either::Left(Some(message)) => {
+                    let failed = matches!(message, SourceMessage::MetadataChanged(_));
+
+                    yield Ok(message);
+
+                    // The subscription is closed once the replay fails
+                    if failed {
+                        return;
+                    }
+                }
+                Either::Left(None) => break,
+                Either::Right(message) => {
+                    if buffered.len() >= capacity {
+                        buffered.pop_front();
+                        dropped += 1;
+                    }
+
+                    buffered.push_back(message);
+                }
+            }
+        }
+
+        if dropped > 0 {
+            yield Err(BroadcastStreamRecvError::Lagged(dropped));
+        }
+
+        for message in buffered {
+            if !replayed(&mut ranges, &message) {
+                yield message;
+            }
+        }
+
+        while let Some(message) = live.next().await {
+            if !replayed(&mut ranges, &message) {
+                yield message;
+            }
+        }
+    }
+}
+
 pub fn start_partition_discovery(
-    bootstrap_servers: &[String],
+    client_properties: &BTreeMap<String, String>,
     sources: Arc<Mutex<BTreeMap<SourceId, Box<dyn Source + Send + Sync + 'static>>>>,
     poll_interval: Duration,
 ) -> anyhow::Result<()> {
-    let client = create_metadata_client(bootstrap_servers)?;
+    let client = create_metadata_client(client_properties)?;
+    // Discovery ends once the sources are dropped, such as when an embedded
+    // server is shut down
+    let sources = Arc::downgrade(&sources);
 
     std::thread::spawn(move || loop {
         std::thread::sleep(poll_interval);
 
+        let Some(sources) = sources.upgrade() else {
+            break;
+        };
+
+        // Sources other than Kafka topic sources, such as those registered by
+        // applications embedding Kiwi, may also consume Kafka topics
         let kafka_sources = sources
             .lock()
             .expect("poisoned lock")
-            .iter()
-            .filter_map(|(_, source)| {
+            .values()
+            .filter_map(|source| {
                 source
-                    .as_any()
-                    .downcast_ref::<KafkaTopicSource>()
-                    .map(|source| (source.id.clone(), source.topic.clone()))
+                    .kafka_topics()
+                    .map(|topics| (source.source_id().clone(), topics.clone()))
             })
             .collect::<Vec<_>>();
 
-        for (id, topic) in kafka_sources.iter() {
-            match fetch_partition_metadata(topic.as_str(), &client) {
+        for (id, topics) in kafka_sources.iter() {
+            match fetch_topic_metadata(topics, &client) {
                 Ok(metadata) => {
                     if let Some(source) = sources.lock().expect("poisoned lock").get(id) {
-                        for partition_metadata in metadata {
-                            let metadata_tx = source.metadata_tx();
-
-                            if let Some(metadata_tx) = metadata_tx {
-                                let _ =
-                                    metadata_tx.send(SourceMetadata::Kafka(KafkaSourceMetadata {
-                                        partitions: vec![partition_metadata],
-                                    }));
-                            }
+                        if let Some(metadata_tx) = source.metadata_tx() {
+                            let _ = metadata_tx.send(SourceMetadata::Kafka(metadata));
                         }
                     }
                 }
                 Err(err) => {
-                    tracing::error!(topic = topic.as_str(), error = ?err, "Failed to fetch partition metadata for topic");
+                    tracing::error!(topic = topics.as_str(), error = ?err, "Failed to fetch partition metadata for topic");
                 }
             }
         }
@@ -400,17 +1490,26 @@ pub fn start_partition_discovery(
 }
 
 pub trait KafkaSourceBuilder {
+    #[allow(clippy::too_many_arguments)]
     fn build_source(
         id: SourceId,
         topic: String,
-        bootstrap_servers: &[String],
-        group_id_prefix: &str,
+        client_properties: &BTreeMap<String, String>,
+        consumer_group: ConsumerGroup,
+        start_from: StartFrom,
+        payload_encoding: PayloadEncoding,
+        decoder: Option<Arc<Decoder>>,
+        channel_capacity: usize,
     ) -> anyhow::Result<Box<dyn Source + Send + Sync + 'static>> {
         Ok(Box::new(KafkaTopicSource::new(
             id,
             topic,
-            bootstrap_servers,
-            group_id_prefix,
+            client_properties,
+            consumer_group,
+            start_from,
+            payload_encoding,
+            decoder,
+            channel_capacity,
         )?))
     }
 }
@@ -418,7 +1517,10 @@ pub trait KafkaSourceBuilder {
 impl From<KafkaSourceResult> for hook::intercept::types::KafkaEventCtx {
     fn from(value: KafkaSourceResult) -> Self {
         Self {
+            source_id: value.id,
+            key: value.key,
             payload: value.payload,
+            headers: value.headers,
             topic: value.topic,
             timestamp: value.timestamp,
             partition: value.partition,
@@ -426,3 +1528,300 @@ impl From<KafkaSourceResult> for hook::intercept::types::KafkaEventCtx {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partition(lo_watermark: i64, hi_watermark: i64) -> PartitionMetadata {
+        PartitionMetadata {
+            partition: 0,
+            hi_watermark,
+            lo_watermark,
+        }
+    }
+
+    #[test]
+    fn test_start_from_resolves_against_watermarks() {
+        let p = partition(10, 100);
+
+        assert_eq!(StartFrom::Earliest.offset_from_watermarks(&p), Some(10));
+        assert_eq!(StartFrom::Latest.offset_from_watermarks(&p), Some(100));
+        assert_eq!(StartFrom::LastN(5).offset_from_watermarks(&p), Some(95));
+        assert_eq!(StartFrom::TimestampMs(0).offset_from_watermarks(&p), None);
+    }
+
+    fn kafka_message(partition: i32, offset: i64) -> SourceMessage {
+        SourceMessage::Result(SourceResult::Kafka(KafkaSourceResult {
+            id: "test".into(),
+            key: None,
+            payload: None,
+            headers: Vec::new(),
+            topic: "test".into(),
+            timestamp: None,
+            partition,
+            offset,
+            payload_encoding: PayloadEncoding::Base64,
+        }))
+    }
+
+    fn offsets(messages: Vec<Result<SourceMessage, BroadcastStreamRecvError>>) -> Vec<(i32, i64)> {
+        messages
+            .into_iter()
+            .map(|message| match message {
+                Ok(SourceMessage::Result(SourceResult::Kafka(result))) => {
+                    (result.partition, result.offset)
+                }
+                _ => panic!("Expected Kafka result"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_event_ctx_uses_source_id() {
+        let result = KafkaSourceResult {
+            id: "my-source".into(),
+            key: Some(b"user-1".to_vec()),
+            payload: None,
+            headers: vec![("tenant-id".into(), Some(b"acme".to_vec()))],
+            topic: "topic1".into(),
+            timestamp: None,
+            partition: 0,
+            offset: 0,
+            payload_encoding: PayloadEncoding::Base64,
+        };
+
+        let ctx: hook::intercept::types::KafkaEventCtx = result.into();
+
+        assert_eq!(ctx.source_id, "my-source");
+        assert_eq!(ctx.topic, "topic1");
+        assert_eq!(ctx.key, Some(b"user-1".to_vec()));
+        assert_eq!(
+            ctx.headers,
+            vec![("tenant-id".into(), Some(b"acme".to_vec()))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handover_deduplicates_replayed_events() {
+        let (tx, rx) = tokio::sync::broadcast::channel(16);
+
+        // The live stream has already observed the tail of the replayed range
+        // as well as events produced after the replay was resolved
+        tx.send(kafka_message(0, 8)).unwrap();
+        tx.send(kafka_message(0, 9)).unwrap();
+        tx.send(kafka_message(0, 10)).unwrap();
+        tx.send(kafka_message(1, 3)).unwrap();
+        drop(tx);
+
+        let catch_up = futures::stream::iter((7..10).map(|offset| kafka_message(0, offset)));
+        let ranges = btreemap! { 0 => 7..10 };
+
+        let messages = handover(catch_up, BroadcastStream::new(rx), ranges, 16)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(
+            offsets(messages),
+            vec![(0, 7), (0, 8), (0, 9), (0, 10), (1, 3)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handover_forwards_live_events_after_replay() {
+        let (tx, rx) = tokio::sync::broadcast::channel(16);
+
+        let stream = handover(
+            futures::stream::iter(vec![kafka_message(0, 0)]),
+            BroadcastStream::new(rx),
+            btreemap! { 0 => 0..1 },
+            16,
+        );
+        tokio::pin!(stream);
+
+        assert_eq!(offsets(vec![stream.next().await.unwrap()]), vec![(0, 0)]);
+
+        tx.send(kafka_message(0, 1)).unwrap();
+
+        assert_eq!(offsets(vec![stream.next().await.unwrap()]), vec![(0, 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_handover_stops_deduplicating_past_replayed_ranges() {
+        let (tx, rx) = tokio::sync::broadcast::channel(16);
+
+        // Once the live stream has passed the replayed range, events with lower
+        // offsets, such as those of a recreated topic, are no longer discarded
+        tx.send(kafka_message(0, 9)).unwrap();
+        tx.send(kafka_message(0, 10)).unwrap();
+        tx.send(kafka_message(0, 0)).unwrap();
+        tx.send(kafka_message(0, 1)).unwrap();
+        drop(tx);
+
+        let catch_up = futures::stream::iter((8..10).map(|offset| kafka_message(0, offset)));
+        let ranges = btreemap! { 0 => 8..10 };
+
+        let messages = handover(catch_up, BroadcastStream::new(rx), ranges, 16)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(
+            offsets(messages),
+            vec![(0, 8), (0, 9), (0, 10), (0, 0), (0, 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handover_buffers_live_events_during_replay() {
+        // The source channel is smaller than the number of live events produced
+        // while the replay is in progress
+        let (tx, rx) = tokio::sync::broadcast::channel(2);
+        let (replay_tx, replay_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let stream = handover(
+            tokio_stream::wrappers::UnboundedReceiverStream::new(replay_rx),
+            BroadcastStream::new(rx),
+            btreemap! { 0 => 0..2 },
+            16,
+        );
+        tokio::pin!(stream);
+
+        replay_tx.send(kafka_message(0, 0)).unwrap();
+
+        assert_eq!(offsets(vec![stream.next().await.unwrap()]), vec![(0, 0)]);
+
+        for offset in 1..6 {
+            tx.send(kafka_message(0, offset)).unwrap();
+
+            // Live events are read while the replay has nothing to yield
+            assert!(
+                tokio::time::timeout(Duration::from_millis(10), stream.next())
+                    .await
+                    .is_err()
+            );
+        }
+
+        replay_tx.send(kafka_message(0, 1)).unwrap();
+        drop(replay_tx);
+        drop(tx);
+
+        assert_eq!(
+            offsets(stream.collect::<Vec<_>>().await),
+            vec![(0, 1), (0, 2), (0, 3), (0, 4), (0, 5)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handover_reports_events_dropped_from_full_buffer() {
+        let (tx, rx) = tokio::sync::broadcast::channel(16);
+        let (replay_tx, replay_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let stream = handover(
+            tokio_stream::wrappers::UnboundedReceiverStream::new(replay_rx),
+            BroadcastStream::new(rx),
+            btreemap! { 0 => 0..1 },
+            2,
+        );
+        tokio::pin!(stream);
+
+        for offset in 1..5 {
+            tx.send(kafka_message(0, offset)).unwrap();
+        }
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), stream.next())
+                .await
+                .is_err()
+        );
+
+        replay_tx.send(kafka_message(0, 0)).unwrap();
+        drop(replay_tx);
+        drop(tx);
+
+        let mut messages = stream.collect::<Vec<_>>().await;
+
+        assert!(matches!(
+            messages.remove(1),
+            Err(BroadcastStreamRecvError::Lagged(2))
+        ));
+        assert_eq!(offsets(messages), vec![(0, 0), (0, 3), (0, 4)]);
+    }
+
+    #[tokio::test]
+    async fn test_handover_closes_subscription_on_failed_replay() {
+        let (tx, rx) = tokio::sync::broadcast::channel(16);
+
+        tx.send(kafka_message(0, 1)).unwrap();
+
+        let catch_up = futures::stream::iter(vec![
+            kafka_message(0, 0),
+            SourceMessage::MetadataChanged("Replay failed".into()),
+        ]);
+
+        let messages = handover(
+            catch_up,
+            BroadcastStream::new(rx),
+            btreemap! { 0 => 0..2 },
+            16,
+        )
+        .collect::<Vec<_>>()
+        .await;
+
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(messages[1], Ok(SourceMessage::MetadataChanged(_))));
+    }
+
+    #[test]
+    fn test_start_from_last_n_clamps_to_low_watermark() {
+        let p = partition(10, 100);
+
+        assert_eq!(StartFrom::LastN(1000).offset_from_watermarks(&p), Some(10));
+        assert_eq!(
+            StartFrom::LastN(u64::MAX).offset_from_watermarks(&p),
+            Some(10)
+        );
+    }
+
+    #[test]
+    fn test_topic_selector_patterns() {
+        let name = TopicSelector::parse("orders.tenant-1").unwrap();
+
+        assert_eq!(name.name(), Some("orders.tenant-1"));
+        assert!(name.matches("orders.tenant-1"));
+        assert!(!name.matches("orders.tenant-10"));
+
+        let pattern = TopicSelector::parse(r"^orders\.tenant-.*").unwrap();
+
+        assert_eq!(pattern.name(), None);
+        assert!(pattern.matches("orders.tenant-1"));
+        assert!(pattern.matches("orders.tenant-acme"));
+        assert!(!pattern.matches("payments.tenant-1"));
+
+        assert!(TopicSelector::parse("^orders.(").is_err());
+    }
+
+    #[test]
+    fn test_removed_partitions_detects_deleted_topics() {
+        let consumed = [
+            ("orders".to_string(), 0),
+            ("orders".to_string(), 1),
+            ("payments".to_string(), 0),
+            ("refunds".to_string(), 0),
+        ];
+
+        let metadata = KafkaSourceMetadata {
+            topics: btreemap! {
+                "orders".to_string() => vec![partition(0, 0)],
+            },
+            unavailable: BTreeSet::from(["refunds".to_string()]),
+        };
+
+        assert_eq!(
+            metadata.removed_partitions(consumed.iter()),
+            btreemap! {
+                "orders".to_string() => BTreeSet::from([1]),
+                "payments".to_string() => BTreeSet::from([0]),
+            }
+        );
+    }
+}
@@ -0,0 +1,488 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use futures_util::{future::Fuse, FutureExt, SinkExt, StreamExt};
+use tokio::sync::broadcast::{Receiver, Sender};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use crate::protocol::{self, Command, CommandResponse, Message, Notice, SubscriptionMode};
+use crate::util::backoff::Backoff;
+
+use super::relay::{connect_websocket, RelayConnectionInfo};
+use super::{
+    ChannelUsage, Source, SourceHealth, SourceId, SourceMessage, SourceMetadata, SubscribeError,
+};
+
+type ShutdownTrigger = tokio::sync::oneshot::Sender<()>;
+type ShutdownReceiver = tokio::sync::oneshot::Receiver<()>;
+
+/// Interval at which the upstream is pinged. The connection is considered lost
+/// if the upstream has not responded by the time the next ping is due
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A source that subscribes to a source on another Kiwi instance. Events are
+/// delivered with the type and metadata of the upstream source, but are
+/// attributed to this source
+pub struct KiwiSource {
+    id: SourceId,
+    tx: Sender<SourceMessage>,
+    channel_capacity: usize,
+    health: SourceHealth,
+    _shutdown_trigger: ShutdownTrigger,
+}
+
+impl KiwiSource {
+    pub fn new(
+        id: SourceId,
+        connection: RelayConnectionInfo,
+        upstream_source_id: SourceId,
+        channel_capacity: usize,
+    ) -> Self {
+        let (tx, _) = tokio::sync::broadcast::channel(channel_capacity);
+        let (shutdown_trigger, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let health = SourceHealth::new(id.clone(), tx.clone());
+
+        let task = KiwiTask {
+            source_id: id.clone(),
+            connection,
+            upstream_source_id,
+            tx: tx.clone(),
+            health: health.clone(),
+            ping_interval: PING_INTERVAL,
+        };
+
+        tokio::spawn(task.run(shutdown_rx.fuse()));
+
+        Self {
+            id,
+            tx,
+            channel_capacity,
+            health,
+            _shutdown_trigger: shutdown_trigger,
+        }
+    }
+}
+
+impl Source for KiwiSource {
+    fn subscribe(&mut self) -> Result<Receiver<SourceMessage>, SubscribeError> {
+        Ok(self.tx.subscribe())
+    }
+
+    fn degraded_since(&self) -> Option<std::time::Instant> {
+        self.health.degraded_since()
+    }
+
+    fn degraded_reason(&self) -> Option<String> {
+        self.health.degraded_reason()
+    }
+
+    fn channel_usage(&self) -> Option<ChannelUsage> {
+        Some(ChannelUsage::new(&self.tx, self.channel_capacity))
+    }
+
+    fn source_id(&self) -> &SourceId {
+        &self.id
+    }
+
+    fn metadata_tx(&self) -> &Option<tokio::sync::mpsc::UnboundedSender<SourceMetadata>> {
+        &None
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+struct KiwiTask {
+    source_id: SourceId,
+    connection: RelayConnectionInfo,
+    upstream_source_id: SourceId,
+    tx: Sender<SourceMessage>,
+    health: SourceHealth,
+    ping_interval: Duration,
+}
+
+impl KiwiTask {
+    async fn run(self, mut shutdown_rx: Fuse<ShutdownReceiver>) {
+        let mut backoff = Backoff::default();
+
+        loop {
+            let result = tokio::select! {
+                _ = &mut shutdown_rx => break,
+                result = self.consume(&mut backoff) => result,
+            };
+
+            if let Err(err) = result {
+                tracing::debug!(
+                    source_id = self.source_id,
+                    "Lost subscription to upstream Kiwi source: {:?}",
+                    err
+                );
+                self.health.degraded(format!("{:#}", err));
+            }
+
+            tokio::select! {
+                _ = &mut shutdown_rx => break,
+                _ = tokio::time::sleep(backoff.next_delay()) => {}
+            }
+        }
+
+        tracing::debug!("Kiwi task for source {} shutting down", self.source_id);
+    }
+
+    /// Connects to the upstream instance, subscribes to the upstream source and
+    /// relays its events until the subscription is lost
+    async fn consume(&self, backoff: &mut Backoff) -> anyhow::Result<()> {
+        // Pings from the upstream are answered, and its close frame acknowledged,
+        // as messages are read
+        let (mut writer, mut reader) = connect_websocket(&self.connection).await?.split();
+
+        let subscribe = serde_json::to_string(&Command::Subscribe {
+            source_id: self.upstream_source_id.clone(),
+            mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
+        })?;
+
+        writer
+            .send(WsMessage::text(subscribe))
+            .await
+            .context("Failed to send subscribe command to upstream")?;
+
+        let mut awaiting_pong = false;
+        let mut ping = tokio::time::interval(self.ping_interval);
+        ping.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // The first tick completes immediately
+        ping.tick().await;
+
+        loop {
+            tokio::select! {
+                message = reader.next() => {
+                    let message = message
+                        .ok_or_else(|| anyhow!("Connection closed by upstream"))?
+                        .context("Failed to read message from upstream")?;
+
+                    match message {
+                        WsMessage::Text(text) => {
+                            let message = serde_json::from_str::<Message>(&text)
+                                .context("Received invalid message from upstream")?;
+
+                            self.handle_message(message, backoff)?;
+                        }
+                        WsMessage::Pong(_) => awaiting_pong = false,
+                        WsMessage::Close(_) => bail!("Connection closed by upstream"),
+                        _ => {}
+                    }
+                }
+                _ = ping.tick() => {
+                    if awaiting_pong {
+                        bail!("Upstream did not respond to ping within {:?}", self.ping_interval);
+                    }
+
+                    writer
+                        .send(WsMessage::Ping(Default::default()))
+                        .await
+                        .context("Failed to ping upstream")?;
+                    awaiting_pong = true;
+                }
+            }
+        }
+    }
+
+    fn handle_message(&self, message: Message, backoff: &mut Backoff) -> anyhow::Result<()> {
+        match message {
+            Message::Result(result) => match result.decode(self.source_id.clone()) {
+                Ok(result) => {
+                    let _ = self.tx.send(SourceMessage::Result(result));
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        source_id = self.source_id,
+                        "Dropping event from upstream that could not be decoded ({})",
+                        err
+                    );
+                }
+            },
+            Message::CommandResponse(CommandResponse::SubscribeOk { .. }) => {
+                tracing::debug!(
+                    source_id = self.source_id,
+                    "Subscribed to upstream source {}",
+                    self.upstream_source_id
+                );
+                self.health.recovered();
+                backoff.reset();
+            }
+            Message::CommandResponse(CommandResponse::SubscribeError { error, .. }) => {
+                return Err(anyhow!(
+                    "Failed to subscribe to upstream source {}: {}",
+                    self.upstream_source_id,
+                    error
+                ));
+            }
+            Message::CommandResponse(_) => {}
+            Message::Notice(Notice::SubscriptionClosed { message, .. }) => {
+                return Err(anyhow!(
+                    "Upstream closed the subscription to source {}: {}",
+                    self.upstream_source_id,
+                    message.as_deref().unwrap_or("no reason given")
+                ));
+            }
+            // The health of the upstream source is reflected by this source,
+            // as its events are delayed all the same
+            Message::Notice(Notice::SourceStatus {
+                status: protocol::SourceStatus::Degraded,
+                message,
+                ..
+            }) => {
+                self.health.degraded(format!(
+                    "Upstream source {} is degraded: {}",
+                    self.upstream_source_id,
+                    message.as_deref().unwrap_or("no reason given")
+                ));
+            }
+            Message::Notice(Notice::SourceStatus {
+                status: protocol::SourceStatus::Recovered,
+                ..
+            }) => self.health.recovered(),
+            Message::Notice(Notice::Lag { count, .. }) => {
+                tracing::warn!(
+                    source_id = self.source_id,
+                    "Missed {} events from upstream source {}",
+                    count,
+                    self.upstream_source_id
+                );
+            }
+            // The event itself is received base64 encoded, and delivered to
+            // subscribers of this source as such
+            Message::Notice(Notice::PayloadEncodingFallback { .. }) => {}
+        }
+
+        Ok(())
+    }
+}
+
+pub trait KiwiSourceBuilder {
+    fn build_source(
+        id: SourceId,
+        connection: RelayConnectionInfo,
+        upstream_source_id: SourceId,
+        channel_capacity: usize,
+    ) -> Box<dyn Source + Send + Sync + 'static> {
+        Box::new(KiwiSource::new(
+            id,
+            connection,
+            upstream_source_id,
+            channel_capacity,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::protocol::PayloadEncoding;
+    use crate::source::SourceResult;
+
+    use super::*;
+
+    fn task() -> (KiwiTask, Receiver<SourceMessage>) {
+        let (tx, rx) = tokio::sync::broadcast::channel(10);
+
+        let task = KiwiTask {
+            source_id: "edge".into(),
+            connection: RelayConnectionInfo::parse("ws://localhost:8000", &BTreeMap::new(), None)
+                .unwrap(),
+            upstream_source_id: "central".into(),
+            health: SourceHealth::new("edge".into(), tx.clone()),
+            tx,
+            ping_interval: PING_INTERVAL,
+        };
+
+        (task, rx)
+    }
+
+    fn message(json: serde_json::Value) -> Message {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_relays_upstream_results() {
+        let (task, mut rx) = task();
+        let mut backoff = Backoff::default();
+
+        task.handle_message(
+            message(serde_json::json!({
+                "type": "RESULT",
+                "data": {
+                    "sourceType": "mqtt",
+                    "sourceId": "central",
+                    "topic": "devices/1",
+                    "payload": { "temp": 21 },
+                    "payloadEncoding": "json",
+                    "qos": 1,
+                    "retain": false,
+                },
+            })),
+            &mut backoff,
+        )
+        .unwrap();
+
+        match rx.try_recv().unwrap() {
+            SourceMessage::Result(SourceResult::Mqtt(result)) => {
+                assert_eq!(result.source_id, "edge");
+                assert_eq!(result.topic, "devices/1");
+                assert_eq!(result.payload, br#"{"temp":21}"#);
+                assert_eq!(result.payload_encoding, PayloadEncoding::Json);
+                assert_eq!(result.qos, 1);
+            }
+            msg => panic!("Expected MQTT result. Received {:?}", msg),
+        }
+
+        // Events that can't be decoded are dropped
+        task.handle_message(
+            message(serde_json::json!({
+                "type": "RESULT",
+                "data": {
+                    "sourceType": "custom",
+                    "kind": "http",
+                    "sourceId": "central",
+                    "payload": "not base64!",
+                    "payloadEncoding": "base64",
+                    "timestamp": 0,
+                },
+            })),
+            &mut backoff,
+        )
+        .unwrap();
+
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_reflects_upstream_status() {
+        let (task, mut rx) = task();
+        let mut backoff = Backoff::default();
+
+        task.handle_message(
+            message(serde_json::json!({
+                "type": "NOTICE",
+                "data": {
+                    "type": "SOURCE_STATUS",
+                    "sourceId": "central",
+                    "status": "DEGRADED",
+                    "message": "broker unreachable",
+                },
+            })),
+            &mut backoff,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            SourceMessage::StatusChanged(crate::source::SourceStatus::Degraded(reason))
+                if reason.contains("broker unreachable")
+        ));
+
+        task.handle_message(
+            message(serde_json::json!({
+                "type": "COMMAND_RESPONSE",
+                "data": { "type": "SUBSCRIBE_OK", "sourceId": "central" },
+            })),
+            &mut backoff,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            SourceMessage::StatusChanged(crate::source::SourceStatus::Recovered)
+        );
+    }
+
+    #[test]
+    fn test_fails_when_subscription_is_lost() {
+        let (task, _rx) = task();
+        let mut backoff = Backoff::default();
+
+        assert!(task
+            .handle_message(
+                message(serde_json::json!({
+                    "type": "COMMAND_RESPONSE",
+                    "data": {
+                        "type": "SUBSCRIBE_ERROR",
+                        "sourceId": "central",
+                        "error": "Source not found",
+                    },
+                })),
+                &mut backoff,
+            )
+            .is_err());
+        assert!(task
+            .handle_message(
+                message(serde_json::json!({
+                    "type": "NOTICE",
+                    "data": {
+                        "type": "SUBSCRIPTION_CLOSED",
+                        "sourceId": "central",
+                        "message": "Source removed",
+                    },
+                })),
+                &mut backoff,
+            )
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fails_when_upstream_does_not_respond_to_pings() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // Completes the opening handshake, then reads frames without ever
+        // responding to them
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+
+            let len = stream.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..len]).into_owned();
+            let key = request
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case("sec-websocket-key"))
+                .map(|(_, key)| key)
+                .unwrap();
+            let accept =
+                tokio_tungstenite::tungstenite::handshake::derive_accept_key(key.trim().as_bytes());
+
+            stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                        accept
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+
+            while stream.read(&mut buf).await.unwrap() > 0 {}
+        });
+
+        let (mut task, _rx) = task();
+        task.connection = RelayConnectionInfo::parse(&url, &BTreeMap::new(), None).unwrap();
+        task.ping_interval = Duration::from_millis(50);
+
+        let err = tokio::time::timeout(
+            Duration::from_secs(5),
+            task.consume(&mut Backoff::default()),
+        )
+        .await
+        .expect("Connection should be considered lost")
+        .unwrap_err();
+
+        assert!(err.to_string().contains("did not respond to ping"));
+    }
+}
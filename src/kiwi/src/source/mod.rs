@@ -1,11 +1,36 @@
-use tokio::sync::broadcast::Receiver;
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use futures::{Stream, StreamExt};
+use tokio::sync::broadcast::{Receiver, Sender};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 
 use crate::hook;
+use crate::protocol::PayloadEncoding;
 
-use self::{counter::CounterSourceBuilder, kafka::KafkaSourceBuilder};
+use self::{
+    counter::CounterSourceBuilder, exec::ExecSourceBuilder, file::FileSourceBuilder,
+    http::HttpSourceBuilder, kafka::KafkaSourceBuilder, kiwi::KiwiSourceBuilder,
+    mqtt::MqttSourceBuilder, nats::NatsSourceBuilder, plugin::PluginSourceBuilder,
+    postgres::PostgresSourceBuilder, redis::RedisSourceBuilder, relay::RelaySourceBuilder,
+};
 
 pub mod counter;
+pub mod exec;
+pub mod file;
+pub mod http;
 pub mod kafka;
+pub mod kiwi;
+pub mod mqtt;
+pub mod nats;
+pub mod plugin;
+pub mod postgres;
+pub mod redis;
+pub mod registry;
+pub mod relay;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SourceMessage {
@@ -13,27 +38,245 @@ pub enum SourceMessage {
     Result(SourceResult),
     /// Source metadata has changed
     MetadataChanged(String),
+    /// The specified partitions are no longer consumed by this instance
+    PartitionsRevoked(BTreeSet<i32>),
+    /// The connectivity of the source to its upstream system has changed
+    StatusChanged(SourceStatus),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SourceStatus {
+    /// The source is unable to reach its upstream system for the specified reason
+    Degraded(String),
+    /// The source has reestablished connectivity to its upstream system
+    Recovered,
+}
+
+/// Occupancy of the channel through which a source broadcasts messages to its
+/// subscribers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelUsage {
+    /// Maximum number of messages retained for subscribers
+    pub capacity: usize,
+    /// Number of messages currently retained for subscribers
+    pub len: usize,
+}
+
+impl ChannelUsage {
+    pub fn new(tx: &Sender<SourceMessage>, capacity: usize) -> Self {
+        Self {
+            capacity,
+            len: tx.len(),
+        }
+    }
+}
+
+/// Tracks whether a source is able to reach its upstream system, notifying
+/// subscribers whenever this changes
+#[derive(Debug, Clone)]
+pub struct SourceHealth {
+    source_id: SourceId,
+    /// Instant at which the source became degraded, along with the reason
+    degraded: Arc<Mutex<Option<(Instant, String)>>>,
+    tx: Sender<SourceMessage>,
+}
+
+impl SourceHealth {
+    pub fn new(source_id: SourceId, tx: Sender<SourceMessage>) -> Self {
+        Self {
+            source_id,
+            degraded: Default::default(),
+            tx,
+        }
+    }
+
+    /// Marks the source as degraded. Subscribers are only notified if the source
+    /// was previously healthy
+    pub fn degraded(&self, reason: String) {
+        let mut degraded = self.degraded.lock().expect("poisoned lock");
+
+        if degraded.is_none() {
+            *degraded = Some((Instant::now(), reason.clone()));
+
+            tracing::warn!(source_id = self.source_id, reason, "Source is degraded");
+
+            let _ = self
+                .tx
+                .send(SourceMessage::StatusChanged(SourceStatus::Degraded(reason)));
+        }
+    }
+
+    /// Marks the source as healthy. Subscribers are only notified if the source
+    /// was previously degraded
+    pub fn recovered(&self) {
+        let mut degraded = self.degraded.lock().expect("poisoned lock");
+
+        if degraded.take().is_some() {
+            tracing::info!(source_id = self.source_id, "Source has recovered");
+
+            let _ = self
+                .tx
+                .send(SourceMessage::StatusChanged(SourceStatus::Recovered));
+        }
+    }
+
+    /// Returns the instant at which the source became degraded, if it is degraded
+    pub fn degraded_since(&self) -> Option<Instant> {
+        self.degraded
+            .lock()
+            .expect("poisoned lock")
+            .as_ref()
+            .map(|(since, _)| *since)
+    }
+
+    /// Returns the reason the source is degraded, if it is degraded
+    pub fn degraded_reason(&self) -> Option<String> {
+        self.degraded
+            .lock()
+            .expect("poisoned lock")
+            .as_ref()
+            .map(|(_, reason)| reason.clone())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SourceResult {
     Kafka(kafka::KafkaSourceResult),
     Counter(counter::CounterSourceResult),
+    Redis(redis::RedisSourceResult),
+    Nats(nats::NatsSourceResult),
+    Mqtt(mqtt::MqttSourceResult),
+    Postgres(postgres::PostgresSourceResult),
+    Custom(CustomSourceResult),
+}
+
+/// A source-agnostic event. Sources without a dedicated result type emit these,
+/// which are delivered to clients and intercept hooks without any source-specific
+/// handling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomSourceResult {
+    /// Source ID
+    pub source_id: SourceId,
+    /// Type of the source that produced the event, such as `http` or `plugin`
+    pub kind: String,
+    /// Event key
+    pub key: Option<Vec<u8>>,
+    /// Additional information about the event, such as headers
+    pub metadata: BTreeMap<String, String>,
+    /// Event payload
+    pub payload: Vec<u8>,
+    /// Timestamp (in milliseconds since the Unix epoch) of the event
+    pub timestamp: i64,
+    /// How the key and payload are represented when sent to clients
+    pub payload_encoding: PayloadEncoding,
+}
+
+impl From<CustomSourceResult> for hook::intercept::types::CustomEventCtx {
+    fn from(value: CustomSourceResult) -> Self {
+        Self {
+            source_id: value.source_id,
+            kind: value.kind,
+            key: value.key,
+            metadata: value.metadata,
+            payload: value.payload,
+            timestamp: value.timestamp,
+        }
+    }
+}
+
+impl SourceResult {
+    /// Partition the result was produced from, if the source is partitioned
+    pub fn partition(&self) -> Option<i32> {
+        match self {
+            SourceResult::Kafka(result) => Some(result.partition),
+            SourceResult::Counter(_)
+            | SourceResult::Redis(_)
+            | SourceResult::Nats(_)
+            | SourceResult::Mqtt(_)
+            | SourceResult::Postgres(_)
+            | SourceResult::Custom(_) => None,
+        }
+    }
 }
 
 pub enum SourceMetadata {
     Kafka(kafka::KafkaSourceMetadata),
 }
 
+/// Describes where a subscription replays historic events from before it
+/// receives live events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Replay {
+    /// Replay each listed partition starting from the specified offset (inclusive)
+    Offsets(BTreeMap<i32, i64>),
+    /// Replay events produced within the specified window
+    Lookback(std::time::Duration),
+}
+
+/// A stream of source messages backing a single subscription
+pub type SourceStream =
+    Pin<Box<dyn Stream<Item = Result<SourceMessage, BroadcastStreamRecvError>> + Send + Sync>>;
+
+/// Resolves to the stream of a replay subscription once the replay has been
+/// set up. Setting up a replay may involve blocking on the backing system, so
+/// it is awaited in its own task, without holding any lock over the sources
+pub type PendingReplay = Pin<Box<dyn Future<Output = Result<SourceStream, SubscribeError>> + Send>>;
+
 #[derive(Debug, thiserror::Error)]
 pub enum SubscribeError {
     #[error("Finite source has ended")]
     FiniteSourceEnded,
+    #[error("Source does not support replay")]
+    ReplayUnsupported,
+    #[error("Failed to set up replay: {0}")]
+    ReplayFailed(String),
+    #[error("Source is not partitioned")]
+    PartitionsUnsupported,
+    #[error("Partitions {0:?} are not assigned to this instance")]
+    PartitionsNotAssigned(Vec<i32>),
 }
 
 pub trait Source {
     fn subscribe(&mut self) -> Result<Receiver<SourceMessage>, SubscribeError>;
 
+    /// Subscribes to the source, first replaying historic events as described by
+    /// `replay` before seamlessly handing over to live events. Live events are
+    /// subscribed to immediately, while the replay is set up by the returned future
+    fn subscribe_with_replay(&mut self, _replay: Replay) -> Result<PendingReplay, SubscribeError> {
+        Err(SubscribeError::ReplayUnsupported)
+    }
+
+    /// Returns the partitions currently consumed by this instance, or `None` if
+    /// the source is not partitioned
+    fn partitions(&self) -> Option<BTreeSet<i32>> {
+        None
+    }
+
+    /// Returns the instant at which the source lost connectivity to its upstream
+    /// system, or `None` if the source is healthy
+    fn degraded_since(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Returns the reason the source is degraded, or `None` if the source is healthy.
+    /// Clients subscribing to a degraded source are notified of it
+    fn degraded_reason(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns the occupancy of the channel through which the source broadcasts
+    /// messages to its subscribers
+    fn channel_usage(&self) -> Option<ChannelUsage> {
+        None
+    }
+
+    /// Returns the Kafka topics consumed by the source, if any. Partition
+    /// discovery periodically sends the metadata of these topics to the source
+    /// through its metadata channel
+    fn kafka_topics(&self) -> Option<&kafka::TopicSelector> {
+        None
+    }
+
     fn source_id(&self) -> &SourceId;
 
     fn metadata_tx(&self) -> &Option<tokio::sync::mpsc::UnboundedSender<SourceMetadata>>;
@@ -43,11 +286,46 @@ pub trait Source {
 
 pub type SourceId = String;
 
+/// Restricts a subscription stream to events produced from the specified
+/// partitions. The stream signals a metadata change once any of the partitions
+/// is revoked from this instance, which closes the subscription
+pub fn route_partitions(stream: SourceStream, partitions: BTreeSet<i32>) -> SourceStream {
+    Box::pin(stream.filter_map(move |message| {
+        let message = match message {
+            Ok(SourceMessage::Result(result))
+                if result
+                    .partition()
+                    .is_some_and(|partition| !partitions.contains(&partition)) =>
+            {
+                None
+            }
+            Ok(SourceMessage::PartitionsRevoked(revoked)) => {
+                let revoked = revoked.intersection(&partitions).collect::<Vec<_>>();
+
+                (!revoked.is_empty()).then(|| {
+                    Ok(SourceMessage::MetadataChanged(format!(
+                        "Partitions {:?} were revoked from this instance",
+                        revoked
+                    )))
+                })
+            }
+            message => Some(message),
+        };
+
+        futures::future::ready(message)
+    }))
+}
+
 impl From<SourceResult> for hook::intercept::types::EventCtx {
     fn from(value: SourceResult) -> Self {
         match value {
             SourceResult::Kafka(kafka_result) => Self::Kafka(kafka_result.into()),
             SourceResult::Counter(counter_result) => Self::Counter(counter_result.into()),
+            SourceResult::Redis(redis_result) => Self::Redis(redis_result.into()),
+            SourceResult::Nats(nats_result) => Self::Nats(nats_result.into()),
+            SourceResult::Mqtt(mqtt_result) => Self::Mqtt(mqtt_result.into()),
+            SourceResult::Postgres(postgres_result) => Self::Postgres(postgres_result.into()),
+            SourceResult::Custom(custom_result) => Self::Custom(custom_result.into()),
         }
     }
 }
@@ -56,3 +334,47 @@ pub struct SourceBuilder;
 
 impl KafkaSourceBuilder for SourceBuilder {}
 impl CounterSourceBuilder for SourceBuilder {}
+impl RedisSourceBuilder for SourceBuilder {}
+impl NatsSourceBuilder for SourceBuilder {}
+impl MqttSourceBuilder for SourceBuilder {}
+impl PostgresSourceBuilder for SourceBuilder {}
+impl HttpSourceBuilder for SourceBuilder {}
+impl RelaySourceBuilder for SourceBuilder {}
+impl KiwiSourceBuilder for SourceBuilder {}
+impl FileSourceBuilder for SourceBuilder {}
+impl ExecSourceBuilder for SourceBuilder {}
+impl PluginSourceBuilder for SourceBuilder {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_notifies_on_transitions() {
+        let (tx, mut rx) = tokio::sync::broadcast::channel(10);
+        let health = SourceHealth::new("test".into(), tx);
+
+        health.recovered();
+        assert!(health.degraded_since().is_none());
+
+        health.degraded("first".into());
+        health.degraded("second".into());
+        assert!(health.degraded_since().is_some());
+        assert_eq!(health.degraded_reason().as_deref(), Some("first"));
+
+        health.recovered();
+        health.recovered();
+        assert!(health.degraded_since().is_none());
+        assert!(health.degraded_reason().is_none());
+
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            SourceMessage::StatusChanged(SourceStatus::Degraded("first".into()))
+        );
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            SourceMessage::StatusChanged(SourceStatus::Recovered)
+        );
+        assert!(rx.try_recv().is_err());
+    }
+}
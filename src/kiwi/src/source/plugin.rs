@@ -0,0 +1,362 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use anyhow::Context;
+use futures_util::{future::Fuse, FutureExt};
+use tokio::sync::broadcast::{Receiver, Sender};
+
+use crate::hook::source::types::{PollResult, SourcePlugin};
+use crate::hook::wasm::{WasmHook, WasmSourceHook};
+use crate::protocol::PayloadEncoding;
+use crate::util::backoff::Backoff;
+
+use super::{
+    ChannelUsage, CustomSourceResult, Source, SourceHealth, SourceId, SourceMessage,
+    SourceMetadata, SourceResult, SubscribeError,
+};
+
+type ShutdownTrigger = tokio::sync::oneshot::Sender<()>;
+type ShutdownReceiver = tokio::sync::oneshot::Receiver<()>;
+
+/// Kind reported for events emitted by plugin sources
+pub const PLUGIN_SOURCE_KIND: &str = "plugin";
+
+/// A source implemented by a WebAssembly component, which Kiwi polls for
+/// events
+pub struct PluginSource {
+    id: SourceId,
+    tx: Weak<Sender<SourceMessage>>,
+    channel_capacity: usize,
+    // Like the sender, the health of the source is owned by the plugin task so
+    // that subscribers can detect when the source has ended
+    health: Weak<SourceHealth>,
+    initial_subscription_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    _shutdown_trigger: ShutdownTrigger,
+}
+
+impl PluginSource {
+    pub fn new(
+        id: SourceId,
+        hook: WasmSourceHook,
+        env: BTreeMap<String, String>,
+        poll_interval: Duration,
+        lazy: bool,
+        payload_encoding: PayloadEncoding,
+        channel_capacity: usize,
+    ) -> Self {
+        let (tx, _) = tokio::sync::broadcast::channel(channel_capacity);
+        let (shutdown_trigger, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let (initial_subscription_tx, initial_subscription_rx) =
+            tokio::sync::oneshot::channel::<()>();
+        let health = Arc::new(SourceHealth::new(id.clone(), tx.clone()));
+
+        // As for counter sources, the plugin task holds the only strong
+        // reference to the sender, as plugins may end
+        let tx = Arc::new(tx);
+        let weak_tx = Arc::downgrade(&tx);
+        let weak_health = Arc::downgrade(&health);
+
+        let task = PluginTask {
+            source_id: id.clone(),
+            hook,
+            env,
+            poll_interval,
+            lazy,
+            payload_encoding,
+            tx,
+            health,
+        };
+
+        tokio::spawn(task.run(initial_subscription_rx, shutdown_rx.fuse()));
+
+        Self {
+            id,
+            tx: weak_tx,
+            channel_capacity,
+            health: weak_health,
+            initial_subscription_tx: Some(initial_subscription_tx),
+            _shutdown_trigger: shutdown_trigger,
+        }
+    }
+}
+
+impl Source for PluginSource {
+    fn subscribe(&mut self) -> Result<Receiver<SourceMessage>, SubscribeError> {
+        if let Some(tx) = self.initial_subscription_tx.take() {
+            let _ = tx.send(());
+        }
+
+        // If the plugin task (our sole sender) has ended, the plugin has
+        // reported that the source is done
+        if let Some(tx) = self.tx.upgrade() {
+            Ok(tx.subscribe())
+        } else {
+            Err(SubscribeError::FiniteSourceEnded)
+        }
+    }
+
+    fn degraded_since(&self) -> Option<std::time::Instant> {
+        self.health
+            .upgrade()
+            .and_then(|health| health.degraded_since())
+    }
+
+    fn degraded_reason(&self) -> Option<String> {
+        self.health
+            .upgrade()
+            .and_then(|health| health.degraded_reason())
+    }
+
+    fn channel_usage(&self) -> Option<ChannelUsage> {
+        self.tx
+            .upgrade()
+            .map(|tx| ChannelUsage::new(&tx, self.channel_capacity))
+    }
+
+    fn source_id(&self) -> &SourceId {
+        &self.id
+    }
+
+    fn metadata_tx(&self) -> &Option<tokio::sync::mpsc::UnboundedSender<SourceMetadata>> {
+        &None
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+struct PluginTask {
+    source_id: SourceId,
+    hook: WasmSourceHook,
+    env: BTreeMap<String, String>,
+    poll_interval: Duration,
+    lazy: bool,
+    payload_encoding: PayloadEncoding,
+    tx: Arc<Sender<SourceMessage>>,
+    health: Arc<SourceHealth>,
+}
+
+impl PluginTask {
+    async fn run(
+        self,
+        initial_subscription_rx: tokio::sync::oneshot::Receiver<()>,
+        mut shutdown_rx: Fuse<ShutdownReceiver>,
+    ) {
+        let mut backoff = Backoff::default();
+
+        if self.lazy {
+            tokio::select! {
+                _ = &mut shutdown_rx => return,
+                _ = initial_subscription_rx => {}
+            }
+        }
+
+        loop {
+            let result = tokio::select! {
+                _ = &mut shutdown_rx => break,
+                result = self.poll_until_done(&mut backoff) => result,
+            };
+
+            match result {
+                Ok(()) => {
+                    tracing::debug!(source_id = self.source_id, "Plugin source has ended");
+                    break;
+                }
+                // The plugin is instantiated anew after failing, as its state
+                // can't be trusted after a trap
+                Err(err) => self.health.degraded(format!("{:#}", err)),
+            }
+
+            tokio::select! {
+                _ = &mut shutdown_rx => break,
+                _ = tokio::time::sleep(backoff.next_delay()) => {}
+            }
+        }
+
+        tracing::debug!("Plugin task for source {} shutting down", self.source_id);
+    }
+
+    /// Instantiates the plugin and polls it until it reports that the source
+    /// is done
+    async fn poll_until_done(&self, backoff: &mut Backoff) -> anyhow::Result<()> {
+        let mut instance = self.hook.instantiate(&self.env).await.context(format!(
+            "Failed to instantiate plugin {}",
+            self.hook.path().display()
+        ))?;
+
+        loop {
+            let result = instance.poll().await.context("Failed to poll plugin")?;
+
+            self.health.recovered();
+            backoff.reset();
+
+            match result {
+                PollResult::Ready(event) => {
+                    let timestamp = match event.timestamp {
+                        Some(timestamp) => i64::try_from(timestamp)
+                            .context("Plugin provided an out of range timestamp")?,
+                        None => std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|elapsed| elapsed.as_millis() as i64)
+                            .unwrap_or_default(),
+                    };
+
+                    let _ = self.tx.send(SourceMessage::Result(SourceResult::Custom(
+                        CustomSourceResult {
+                            source_id: self.source_id.clone(),
+                            kind: PLUGIN_SOURCE_KIND.into(),
+                            key: event.key,
+                            metadata: event.metadata,
+                            payload: event.payload,
+                            timestamp,
+                            payload_encoding: self.payload_encoding,
+                        },
+                    )));
+
+                    // Polling a plugin that always has an event ready never
+                    // yields, so give other tasks (and shutdown) a chance to run
+                    tokio::task::yield_now().await;
+                }
+                PollResult::Pending => tokio::time::sleep(self.poll_interval).await,
+                PollResult::Done => return Ok(()),
+            }
+        }
+    }
+}
+
+pub trait PluginSourceBuilder {
+    fn build_source(
+        id: SourceId,
+        path: PathBuf,
+        env: BTreeMap<String, String>,
+        poll_interval: Duration,
+        lazy: bool,
+        payload_encoding: PayloadEncoding,
+        channel_capacity: usize,
+    ) -> anyhow::Result<Box<dyn Source + Send + Sync + 'static>> {
+        let hook = WasmSourceHook::from_file(&path)
+            .context(format!("Failed to load plugin {}", path.display()))?;
+
+        Ok(Box::new(PluginSource::new(
+            id,
+            hook,
+            env,
+            poll_interval,
+            lazy,
+            payload_encoding,
+            channel_capacity,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::SourceStatus;
+    use super::*;
+
+    /// Path of a plugin built from the sources in `examples/plugin-fixtures`
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/wasm")
+            .join(name)
+    }
+
+    fn source(name: &str) -> PluginSource {
+        PluginSource::new(
+            "test".into(),
+            WasmSourceHook::from_file(fixture(name)).unwrap(),
+            BTreeMap::new(),
+            Duration::from_millis(10),
+            true,
+            PayloadEncoding::Utf8,
+            10,
+        )
+    }
+
+    async fn recv(rx: &mut Receiver<SourceMessage>) -> Option<SourceMessage> {
+        match tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+        {
+            Ok(msg) => Some(msg),
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => None,
+            Err(err) => panic!("Failed to receive message: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_emits_events_until_done() {
+        let mut source = source("counter-source.wasm");
+        let mut rx = source.subscribe().unwrap();
+
+        let expected = [
+            (b"1", None, BTreeMap::new(), None),
+            (b"2", Some(b"2".to_vec()), BTreeMap::new(), Some(2000)),
+            (
+                b"3",
+                Some(b"3".to_vec()),
+                BTreeMap::from([("source".to_string(), "wat".to_string())]),
+                Some(3000),
+            ),
+        ];
+
+        for (payload, key, metadata, timestamp) in expected {
+            match recv(&mut rx).await {
+                Some(SourceMessage::Result(SourceResult::Custom(result))) => {
+                    assert_eq!(result.source_id, "test");
+                    assert_eq!(result.kind, PLUGIN_SOURCE_KIND);
+                    assert_eq!(result.key, key);
+                    assert_eq!(result.metadata, metadata);
+                    assert_eq!(result.payload, payload);
+                    assert_eq!(result.payload_encoding, PayloadEncoding::Utf8);
+
+                    match timestamp {
+                        Some(timestamp) => assert_eq!(result.timestamp, timestamp),
+                        // Events without a timestamp are stamped when polled
+                        None => assert!(result.timestamp > 3000),
+                    }
+                }
+                msg => panic!("Expected plugin result. Received {:?}", msg),
+            }
+        }
+
+        // The subscription is closed once the plugin reports the source is done
+        assert!(recv(&mut rx).await.is_none());
+        assert!(matches!(
+            source.subscribe(),
+            Err(SubscribeError::FiniteSourceEnded)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_degrades_when_plugin_traps() {
+        let mut source = source("failing-source.wasm");
+        let mut rx = source.subscribe().unwrap();
+
+        assert!(matches!(
+            recv(&mut rx).await,
+            Some(SourceMessage::StatusChanged(SourceStatus::Degraded(_)))
+        ));
+        assert!(source.degraded_since().is_some());
+    }
+
+    #[test]
+    fn test_fails_to_build_from_missing_plugin() {
+        struct Builder;
+        impl PluginSourceBuilder for Builder {}
+
+        assert!(<Builder as PluginSourceBuilder>::build_source(
+            "test".into(),
+            fixture("missing.wasm"),
+            BTreeMap::new(),
+            Duration::from_millis(10),
+            false,
+            PayloadEncoding::Utf8,
+            10,
+        )
+        .is_err());
+    }
+}
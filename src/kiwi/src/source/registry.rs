@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+
+use super::{Source, SourceId};
+
+/// Source types built into Kiwi, which cannot be registered by applications
+pub const BUILTIN_SOURCE_TYPES: &[&str] = &[
+    "kafka", "counter", "redis", "nats", "mqtt", "postgres", "http", "relay", "kiwi", "file",
+    "exec", "plugin",
+];
+
+type BuildFn = dyn Fn(
+        SourceId,
+        serde_yaml::Value,
+        usize,
+    ) -> anyhow::Result<Box<dyn Source + Send + Sync + 'static>>
+    + Send
+    + Sync;
+
+/// Builds sources of the types registered by applications embedding Kiwi.
+/// Sources whose configured type is not built into Kiwi are built by the
+/// registered builder of the same name
+#[derive(Clone, Default)]
+pub struct SourceRegistry {
+    builders: BTreeMap<String, Arc<BuildFn>>,
+}
+
+impl SourceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a source type. Every field of a source of this type, other
+    /// than `type`, `id` and `channel_capacity`, is deserialized into `C` before
+    /// being passed to `build` along with the source ID and channel capacity
+    pub fn register<C, F>(&mut self, kind: &str, build: F) -> anyhow::Result<()>
+    where
+        C: DeserializeOwned,
+        F: Fn(SourceId, C, usize) -> anyhow::Result<Box<dyn Source + Send + Sync + 'static>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        if BUILTIN_SOURCE_TYPES.contains(&kind) {
+            anyhow::bail!("Source type {} is built into Kiwi", kind);
+        }
+
+        if self.builders.contains_key(kind) {
+            anyhow::bail!("Source type {} is already registered", kind);
+        }
+
+        let owned_kind = kind.to_string();
+        self.builders.insert(
+            kind.to_string(),
+            Arc::new(move |id, config, channel_capacity| {
+                let config = serde_yaml::from_value(config).context(format!(
+                    "Invalid configuration for {} source {}",
+                    owned_kind, id
+                ))?;
+
+                build(id, config, channel_capacity)
+            }),
+        );
+
+        Ok(())
+    }
+
+    /// Returns whether a source type has been registered
+    pub fn contains(&self, kind: &str) -> bool {
+        self.builders.contains_key(kind)
+    }
+
+    /// Builds a source of a registered type from its configuration
+    pub fn build_source(
+        &self,
+        kind: &str,
+        id: SourceId,
+        config: serde_yaml::Value,
+        channel_capacity: usize,
+    ) -> anyhow::Result<Box<dyn Source + Send + Sync + 'static>> {
+        let build = self
+            .builders
+            .get(kind)
+            .ok_or_else(|| anyhow::anyhow!("Unknown source type {} for source {}", kind, id))?;
+
+        build(id, config, channel_capacity)
+    }
+}
+
+impl std::fmt::Debug for SourceRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SourceRegistry")
+            .field("types", &self.builders.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{SourceMessage, SourceMetadata, SubscribeError};
+    use super::*;
+
+    struct TestSource {
+        id: SourceId,
+        greeting: String,
+        channel_capacity: usize,
+    }
+
+    impl Source for TestSource {
+        fn subscribe(
+            &mut self,
+        ) -> Result<tokio::sync::broadcast::Receiver<SourceMessage>, SubscribeError> {
+            unimplemented!()
+        }
+
+        fn source_id(&self) -> &SourceId {
+            &self.id
+        }
+
+        fn metadata_tx(&self) -> &Option<tokio::sync::mpsc::UnboundedSender<SourceMetadata>> {
+            &None
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    struct TestConfig {
+        greeting: String,
+    }
+
+    fn registry() -> SourceRegistry {
+        let mut registry = SourceRegistry::new();
+
+        registry
+            .register("greeter", |id, config: TestConfig, channel_capacity| {
+                Ok(Box::new(TestSource {
+                    id,
+                    greeting: config.greeting,
+                    channel_capacity,
+                }))
+            })
+            .unwrap();
+
+        registry
+    }
+
+    #[test]
+    fn test_builds_registered_sources() {
+        let config = serde_yaml::from_str("greeting: hello").unwrap();
+        let source = registry()
+            .build_source("greeter", "test".into(), config, 10)
+            .unwrap();
+
+        let source = source.as_any().downcast_ref::<TestSource>().unwrap();
+        assert_eq!(source.id, "test");
+        assert_eq!(source.greeting, "hello");
+        assert_eq!(source.channel_capacity, 10);
+    }
+
+    #[test]
+    fn test_rejects_invalid_configuration() {
+        let config = serde_yaml::from_str("farewell: bye").unwrap();
+
+        assert!(registry()
+            .build_source("greeter", "test".into(), config, 10)
+            .is_err());
+    }
+
+    #[test]
+    fn test_rejects_unknown_types() {
+        let config = serde_yaml::from_str("greeting: hello").unwrap();
+
+        assert!(registry()
+            .build_source("unknown", "test".into(), config, 10)
+            .is_err());
+    }
+
+    #[test]
+    fn test_rejects_duplicate_and_builtin_types() {
+        let build = |_: SourceId,
+                     _: serde_yaml::Value,
+                     _: usize|
+         -> anyhow::Result<Box<dyn Source + Send + Sync>> { unimplemented!() };
+
+        let mut registry = registry();
+
+        assert!(registry.register("greeter", build).is_err());
+        assert!(registry.register("kafka", build).is_err());
+        assert!(registry.register("farewell", build).is_ok());
+        assert!(registry.contains("farewell"));
+    }
+}
@@ -0,0 +1,597 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use eventsource_stream::{EventStreamError, Eventsource};
+use futures_util::{future::Fuse, FutureExt, SinkExt, StreamExt};
+use http::header::{HeaderName, HeaderValue, ACCEPT, CACHE_CONTROL, CONTENT_TYPE};
+use http::{HeaderMap, StatusCode};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::{Receiver, Sender};
+use tokio_rustls::rustls::ClientConfig;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+
+use crate::protocol::PayloadEncoding;
+use crate::util::backoff::Backoff;
+
+use super::{
+    ChannelUsage, CustomSourceResult, Source, SourceHealth, SourceId, SourceMessage,
+    SourceMetadata, SourceResult, SubscribeError,
+};
+
+type ShutdownTrigger = tokio::sync::oneshot::Sender<()>;
+type ShutdownReceiver = tokio::sync::oneshot::Receiver<()>;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Kind reported for events emitted by relay sources
+pub const RELAY_SOURCE_KIND: &str = "relay";
+
+/// Metadata entry holding the type of a server-sent event
+pub const RELAY_EVENT_METADATA: &str = "event";
+
+/// Metadata entry holding the last event ID reported by a server-sent events
+/// upstream
+pub const RELAY_EVENT_ID_METADATA: &str = "eventId";
+
+/// Protocol used to receive events from an upstream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayProtocol {
+    /// Server-sent events, received from an `http` or `https` URL
+    ServerSentEvents,
+    /// WebSocket messages, received from a `ws` or `wss` URL
+    WebSocket,
+}
+
+/// Connection details of an upstream event feed
+#[derive(Clone)]
+pub struct RelayConnectionInfo {
+    protocol: RelayProtocol,
+    /// URL of the upstream, without any fragment
+    url: url::Url,
+    headers: HeaderMap,
+    tls: Option<Arc<ClientConfig>>,
+}
+
+impl RelayConnectionInfo {
+    /// Parses connection details from an `http[s]://` (server-sent events) or
+    /// `ws[s]://` (WebSocket) URL. The specified headers are sent with every
+    /// request to the upstream. Connections to `https` and `wss` URLs are
+    /// secured with TLS, verifying the upstream's certificate against the CA
+    /// certificate(s) at `ca_location` if specified
+    pub fn parse(
+        url: &str,
+        headers: &BTreeMap<String, String>,
+        ca_location: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        let mut url = url::Url::parse(url).context("Invalid relay URL")?;
+        url.set_fragment(None);
+
+        let (protocol, secure) = match url.scheme() {
+            "http" => (RelayProtocol::ServerSentEvents, false),
+            "https" => (RelayProtocol::ServerSentEvents, true),
+            "ws" => (RelayProtocol::WebSocket, false),
+            "wss" => (RelayProtocol::WebSocket, true),
+            scheme => bail!(
+                "Unsupported relay URL scheme `{}`. Only `http`, `https`, `ws` and `wss` are supported",
+                scheme
+            ),
+        };
+
+        let tls = if secure {
+            Some(Arc::new(crate::tls::tls_client_config(ca_location)?))
+        } else {
+            if ca_location.is_some() {
+                bail!("A CA certificate can only be specified for `https` and `wss` URLs");
+            }
+
+            None
+        };
+
+        if !url.username().is_empty() || url.password().is_some() {
+            bail!("Relay URL must not contain credentials. Send them using headers instead");
+        }
+
+        if url.host_str().filter(|host| !host.is_empty()).is_none() {
+            bail!("Relay URL must specify a host");
+        }
+
+        let headers = headers
+            .iter()
+            .map(|(name, value)| {
+                Ok((
+                    HeaderName::try_from(name.as_str())
+                        .context(format!("Invalid header name `{}`", name))?,
+                    HeaderValue::try_from(value.as_str())
+                        .context(format!("Invalid value for header `{}`", name))?,
+                ))
+            })
+            .collect::<anyhow::Result<HeaderMap>>()?;
+
+        Ok(Self {
+            protocol,
+            url,
+            headers,
+            tls,
+        })
+    }
+
+    /// Protocol used to receive events from the upstream
+    pub fn protocol(&self) -> RelayProtocol {
+        self.protocol
+    }
+
+    fn addr(&self) -> String {
+        format!(
+            "{}:{}",
+            self.url.host_str().unwrap_or_default(),
+            self.url
+                .port_or_known_default()
+                .expect("supported schemes have a default port")
+        )
+    }
+
+    /// Builds a request for the event stream, including the configured headers.
+    /// Streams are resumed after the last event ID received, if any
+    fn event_stream_request(
+        &self,
+        last_event_id: Option<&str>,
+    ) -> anyhow::Result<reqwest::RequestBuilder> {
+        let mut client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .default_headers(self.headers.clone());
+
+        if let Some(config) = &self.tls {
+            client = client.use_preconfigured_tls(ClientConfig::clone(config));
+        }
+
+        let mut request = client
+            .build()
+            .context("Failed to create HTTP client")?
+            .get(self.url.clone())
+            .header(ACCEPT, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache");
+
+        if let Some(last_event_id) = last_event_id {
+            request = request.header("Last-Event-ID", last_event_id);
+        }
+
+        Ok(request)
+    }
+}
+
+/// Awaits the future, failing if it does not complete within the idle timeout
+async fn within_idle_timeout<F: Future>(
+    idle_timeout: Option<Duration>,
+    fut: F,
+) -> anyhow::Result<F::Output> {
+    match idle_timeout {
+        Some(idle_timeout) => tokio::time::timeout(idle_timeout, fut)
+            .await
+            .map_err(|_| anyhow!("No data received from upstream within {:?}", idle_timeout)),
+        None => Ok(fut.await),
+    }
+}
+
+/// A WebSocket connection to an upstream
+pub(super) type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Connects to a WebSocket upstream, performing the opening handshake with the
+/// configured headers
+pub(super) async fn connect_websocket(info: &RelayConnectionInfo) -> anyhow::Result<WebSocket> {
+    let mut request = info
+        .url
+        .as_str()
+        .into_client_request()
+        .context("Invalid relay URL")?;
+    request.headers_mut().extend(info.headers.clone());
+
+    let (ws, _) = tokio::time::timeout(
+        CONNECT_TIMEOUT,
+        tokio_tungstenite::connect_async_tls_with_config(
+            request,
+            None,
+            true,
+            info.tls.clone().map(Connector::Rustls),
+        ),
+    )
+    .await
+    .map_err(|_| anyhow!("Timed out connecting to {}", info.addr()))?
+    .context(format!("WebSocket handshake with {} failed", info.addr()))?;
+
+    Ok(ws)
+}
+
+pub struct RelaySource {
+    id: SourceId,
+    tx: Sender<SourceMessage>,
+    channel_capacity: usize,
+    health: SourceHealth,
+    _shutdown_trigger: ShutdownTrigger,
+}
+
+impl RelaySource {
+    pub fn new(
+        id: SourceId,
+        connection: RelayConnectionInfo,
+        messages: Vec<String>,
+        idle_timeout: Option<Duration>,
+        payload_encoding: PayloadEncoding,
+        channel_capacity: usize,
+    ) -> Self {
+        let (tx, _) = tokio::sync::broadcast::channel(channel_capacity);
+        let (shutdown_trigger, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let health = SourceHealth::new(id.clone(), tx.clone());
+
+        let task = RelayTask {
+            source_id: id.clone(),
+            connection,
+            messages,
+            idle_timeout,
+            payload_encoding,
+            tx: tx.clone(),
+            health: health.clone(),
+        };
+
+        tokio::spawn(task.run(shutdown_rx.fuse()));
+
+        Self {
+            id,
+            tx,
+            channel_capacity,
+            health,
+            _shutdown_trigger: shutdown_trigger,
+        }
+    }
+}
+
+impl Source for RelaySource {
+    fn subscribe(&mut self) -> Result<Receiver<SourceMessage>, SubscribeError> {
+        Ok(self.tx.subscribe())
+    }
+
+    fn degraded_since(&self) -> Option<std::time::Instant> {
+        self.health.degraded_since()
+    }
+
+    fn degraded_reason(&self) -> Option<String> {
+        self.health.degraded_reason()
+    }
+
+    fn channel_usage(&self) -> Option<ChannelUsage> {
+        Some(ChannelUsage::new(&self.tx, self.channel_capacity))
+    }
+
+    fn source_id(&self) -> &SourceId {
+        &self.id
+    }
+
+    fn metadata_tx(&self) -> &Option<tokio::sync::mpsc::UnboundedSender<SourceMetadata>> {
+        &None
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// State of a server-sent events stream that is carried over when reconnecting
+#[derive(Debug, Default)]
+struct StreamState {
+    last_event_id: Option<String>,
+    /// Reconnection delay requested by the upstream
+    retry: Option<Duration>,
+}
+
+struct RelayTask {
+    source_id: SourceId,
+    connection: RelayConnectionInfo,
+    messages: Vec<String>,
+    idle_timeout: Option<Duration>,
+    payload_encoding: PayloadEncoding,
+    tx: Sender<SourceMessage>,
+    health: SourceHealth,
+}
+
+impl RelayTask {
+    async fn run(self, mut shutdown_rx: Fuse<ShutdownReceiver>) {
+        let mut backoff = Backoff::default();
+        let mut state = StreamState::default();
+
+        loop {
+            let result = tokio::select! {
+                _ = &mut shutdown_rx => break,
+                result = self.consume(&mut backoff, &mut state) => result,
+            };
+
+            if let Err(err) = result {
+                tracing::debug!(
+                    source_id = self.source_id,
+                    "Lost connection to relay upstream: {:?}",
+                    err
+                );
+                self.health.degraded(format!("{:#}", err));
+            }
+
+            // Upstreams may ask to wait longer than the backoff before reconnecting
+            let delay = std::cmp::max(backoff.next_delay(), state.retry.unwrap_or_default());
+
+            tokio::select! {
+                _ = &mut shutdown_rx => break,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+
+        tracing::debug!("Relay task for source {} shutting down", self.source_id);
+    }
+
+    async fn consume(&self, backoff: &mut Backoff, state: &mut StreamState) -> anyhow::Result<()> {
+        match self.connection.protocol {
+            RelayProtocol::ServerSentEvents => self.consume_events(backoff, state).await,
+            RelayProtocol::WebSocket => {
+                let ws = connect_websocket(&self.connection).await?;
+                self.consume_messages(ws, backoff).await
+            }
+        }
+    }
+
+    fn publish(&self, payload: Vec<u8>, event: Option<String>, event_id: Option<String>) {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as i64)
+            .unwrap_or_default();
+
+        let metadata = [
+            (RELAY_EVENT_METADATA, event),
+            (RELAY_EVENT_ID_METADATA, event_id),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value?)))
+        .collect();
+
+        let _ = self.tx.send(SourceMessage::Result(SourceResult::Custom(
+            CustomSourceResult {
+                source_id: self.source_id.clone(),
+                kind: RELAY_SOURCE_KIND.into(),
+                key: None,
+                metadata,
+                payload,
+                timestamp,
+                payload_encoding: self.payload_encoding,
+            },
+        )));
+    }
+
+    /// Requests the event stream and relays server-sent events until the
+    /// stream ends
+    async fn consume_events(
+        &self,
+        backoff: &mut Backoff,
+        state: &mut StreamState,
+    ) -> anyhow::Result<()> {
+        let response = tokio::time::timeout(
+            CONNECT_TIMEOUT,
+            self.connection
+                .event_stream_request(state.last_event_id.as_deref())?
+                .send(),
+        )
+        .await
+        .map_err(|_| anyhow!("Timed out waiting for a response from upstream"))?
+        .context("Request to upstream failed")?;
+
+        if response.status() != StatusCode::OK {
+            bail!("Upstream responded with status {}", response.status());
+        }
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|mime| mime.trim().to_ascii_lowercase());
+
+        if content_type.as_deref() != Some("text/event-stream") {
+            bail!(
+                "Upstream responded with content type {:?} rather than text/event-stream",
+                content_type
+            );
+        }
+
+        tracing::debug!(source_id = self.source_id, "Receiving server-sent events");
+        self.health.recovered();
+        backoff.reset();
+
+        // The idle timeout applies to the body rather than to events, so that
+        // comments sent to keep the connection alive are taken into account
+        let idle_timeout = self.idle_timeout;
+        let body = futures_util::stream::unfold(response, move |mut response| async move {
+            match within_idle_timeout(idle_timeout, response.chunk()).await {
+                Ok(Ok(Some(chunk))) => Some((Ok(chunk), response)),
+                Ok(Ok(None)) => None,
+                Ok(Err(err)) => Some((
+                    Err(anyhow::Error::new(err).context("Failed to read event stream")),
+                    response,
+                )),
+                Err(err) => Some((Err(err), response)),
+            }
+        });
+        let mut events = std::pin::pin!(body.eventsource());
+
+        loop {
+            let event = events
+                .next()
+                .await
+                .ok_or_else(|| anyhow!("Stream closed by upstream"))?
+                .map_err(|err| match err {
+                    EventStreamError::Transport(err) => err,
+                    err => anyhow!("Received malformed event stream: {}", err),
+                })?;
+
+            // Events without an ID carry the last one received, including over
+            // previous connections
+            if !event.id.is_empty() {
+                state.last_event_id = Some(event.id);
+            }
+            if event.retry.is_some() {
+                state.retry = event.retry;
+            }
+
+            self.publish(
+                event.data.into_bytes(),
+                Some(event.event),
+                state.last_event_id.clone(),
+            );
+        }
+    }
+
+    /// Sends the configured messages and relays the messages received until
+    /// the connection is closed
+    async fn consume_messages(
+        &self,
+        mut ws: WebSocket,
+        backoff: &mut Backoff,
+    ) -> anyhow::Result<()> {
+        for message in &self.messages {
+            ws.send(Message::text(message.clone()))
+                .await
+                .context("Failed to send message to upstream")?;
+        }
+
+        tracing::debug!(source_id = self.source_id, "Receiving WebSocket messages");
+        self.health.recovered();
+        backoff.reset();
+
+        loop {
+            let message = within_idle_timeout(self.idle_timeout, ws.next())
+                .await?
+                .ok_or_else(|| anyhow!("Connection closed by upstream"))?
+                .context("Failed to read WebSocket message")?;
+
+            match message {
+                Message::Text(text) => self.publish(text.as_bytes().to_vec(), None, None),
+                Message::Binary(data) => self.publish(data.to_vec(), None, None),
+                Message::Close(_) => bail!("Connection closed by upstream"),
+                _ => {}
+            }
+        }
+    }
+}
+
+pub trait RelaySourceBuilder {
+    fn build_source(
+        id: SourceId,
+        connection: RelayConnectionInfo,
+        messages: Vec<String>,
+        idle_timeout: Option<Duration>,
+        payload_encoding: PayloadEncoding,
+        channel_capacity: usize,
+    ) -> Box<dyn Source + Send + Sync + 'static> {
+        Box::new(RelaySource::new(
+            id,
+            connection,
+            messages,
+            idle_timeout,
+            payload_encoding,
+            channel_capacity,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_connection_info() {
+        let headers = BTreeMap::from([("Authorization".to_string(), "Bearer token".to_string())]);
+
+        let info = RelayConnectionInfo::parse(
+            "https://feed.example.com/v1/events?topic=prices#frag",
+            &headers,
+            None,
+        )
+        .unwrap();
+        assert_eq!(info.protocol(), RelayProtocol::ServerSentEvents);
+        assert_eq!(info.addr(), "feed.example.com:443");
+        assert_eq!(
+            info.url.as_str(),
+            "https://feed.example.com/v1/events?topic=prices"
+        );
+        assert_eq!(info.headers.get("authorization").unwrap(), "Bearer token");
+        assert!(info.tls.is_some());
+
+        let info =
+            RelayConnectionInfo::parse("ws://localhost:9000", &BTreeMap::new(), None).unwrap();
+        assert_eq!(info.protocol(), RelayProtocol::WebSocket);
+        assert_eq!(info.addr(), "localhost:9000");
+        assert_eq!(info.url.as_str(), "ws://localhost:9000/");
+        assert!(info.tls.is_none());
+
+        let info =
+            RelayConnectionInfo::parse("wss://[::1]/stream", &BTreeMap::new(), None).unwrap();
+        assert_eq!(info.addr(), "[::1]:443");
+
+        assert!(RelayConnectionInfo::parse("tcp://localhost", &BTreeMap::new(), None).is_err());
+        assert!(
+            RelayConnectionInfo::parse("https://user:pw@localhost", &BTreeMap::new(), None)
+                .is_err()
+        );
+        assert!(RelayConnectionInfo::parse(
+            "http://localhost",
+            &BTreeMap::new(),
+            Some(Path::new("ca.pem"))
+        )
+        .is_err());
+        assert!(RelayConnectionInfo::parse(
+            "http://localhost",
+            &BTreeMap::from([("Bad Header".to_string(), "value".to_string())]),
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_publishes_events_as_custom_results() {
+        let (tx, mut rx) = tokio::sync::broadcast::channel(10);
+        let task = RelayTask {
+            source_id: "test".into(),
+            connection: RelayConnectionInfo::parse("http://localhost", &BTreeMap::new(), None)
+                .unwrap(),
+            messages: Vec::new(),
+            idle_timeout: None,
+            payload_encoding: PayloadEncoding::Utf8,
+            health: SourceHealth::new("test".into(), tx.clone()),
+            tx,
+        };
+
+        task.publish(b"tick".to_vec(), Some("price".into()), Some("7".into()));
+        task.publish(b"trade".to_vec(), None, None);
+
+        match rx.try_recv().unwrap() {
+            SourceMessage::Result(SourceResult::Custom(result)) => {
+                assert_eq!(result.kind, RELAY_SOURCE_KIND);
+                assert_eq!(result.payload, b"tick");
+                assert_eq!(
+                    result.metadata,
+                    BTreeMap::from([
+                        (RELAY_EVENT_METADATA.to_string(), "price".to_string()),
+                        (RELAY_EVENT_ID_METADATA.to_string(), "7".to_string()),
+                    ])
+                );
+            }
+            msg => panic!("Expected relay result. Received {:?}", msg),
+        }
+
+        match rx.try_recv().unwrap() {
+            SourceMessage::Result(SourceResult::Custom(result)) => {
+                assert!(result.metadata.is_empty());
+            }
+            msg => panic!("Expected relay result. Received {:?}", msg),
+        }
+    }
+}
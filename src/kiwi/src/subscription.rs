@@ -3,10 +3,10 @@ use std::pin::Pin;
 use async_stream::stream;
 use futures::Stream;
 use ringbuf::{HeapRb, Rb};
-use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tokio_stream::StreamExt;
 
-use crate::{protocol, source::SourceMessage, source::SourceResult};
+use crate::{protocol, source::SourceMessage, source::SourceResult, source::SourceStream};
 
 #[derive(Debug, thiserror::Error)]
 pub enum SubscriptionRecvError {
@@ -19,23 +19,28 @@ pub enum SubscriptionRecvError {
 }
 
 pub enum Subscription {
-    Pull(PullSubscription),
+    Pull(Box<PullSubscription>),
     Push(PushSubscription),
 }
 
 impl Subscription {
-    pub fn from_mode(
-        source_stream: BroadcastStream<SourceMessage>,
+    pub fn from_mode<S>(
+        source_stream: S,
         mode: protocol::SubscriptionMode,
         buffer_capacity: Option<usize>,
-    ) -> Self {
+    ) -> Self
+    where
+        S: Stream<Item = Result<SourceMessage, BroadcastStreamRecvError>> + Send + Sync + 'static,
+    {
+        let source_stream: SourceStream = Box::pin(source_stream);
+
         match mode {
-            protocol::SubscriptionMode::Pull => Self::Pull(PullSubscription {
+            protocol::SubscriptionMode::Pull => Self::Pull(Box::new(PullSubscription {
                 source_stream,
                 requests: 0,
                 lag: 0,
                 buffer: buffer_capacity.map(HeapRb::new),
-            }),
+            })),
             protocol::SubscriptionMode::Push => Self::Push(PushSubscription { source_stream }),
         }
     }
@@ -63,7 +68,7 @@ impl Subscription {
 }
 
 pub struct PushSubscription {
-    source_stream: BroadcastStream<SourceMessage>,
+    source_stream: SourceStream,
 }
 
 impl PushSubscription {
@@ -83,7 +88,7 @@ impl PushSubscription {
 }
 
 pub struct PullSubscription {
-    source_stream: BroadcastStream<SourceMessage>,
+    source_stream: SourceStream,
     requests: u64,
     lag: u64,
     buffer: Option<HeapRb<SourceResult>>,
@@ -173,11 +178,13 @@ impl PullSubscription {
 
 #[cfg(test)]
 mod tests {
+    use crate::protocol::PayloadEncoding;
     use crate::source::kafka::KafkaSourceResult;
 
     use super::*;
     use futures_util::FutureExt;
     use tokio::sync::broadcast;
+    use tokio_stream::wrappers::BroadcastStream;
 
     #[tokio::test]
     async fn test_push_subscription_yields_results() {
@@ -196,7 +203,9 @@ mod tests {
             topic: "test".into(),
             key: None,
             payload: None,
+            headers: Vec::new(),
             timestamp: None,
+            payload_encoding: PayloadEncoding::Base64,
         }));
 
         tx.send(message).unwrap();
@@ -240,7 +249,9 @@ mod tests {
                 topic: "test".into(),
                 key: None,
                 payload: None,
+                headers: Vec::new(),
                 timestamp: None,
+                payload_encoding: PayloadEncoding::Base64,
             }));
 
             tx.send(message).unwrap();
@@ -268,7 +279,9 @@ mod tests {
                 topic: "test".into(),
                 key: None,
                 payload: None,
+                headers: Vec::new(),
                 timestamp: None,
+                payload_encoding: PayloadEncoding::Base64,
             }));
 
             tx.send(message).unwrap();
@@ -334,7 +347,9 @@ mod tests {
                 topic: "test".into(),
                 key: None,
                 payload: None,
+                headers: Vec::new(),
                 timestamp: None,
+                payload_encoding: PayloadEncoding::Base64,
             }));
 
             tx.send(message).unwrap();
@@ -344,7 +359,7 @@ mod tests {
 
         // The buffer is at capacity, but there have been no requests
         // so nothing should be available to read
-        assert!(matches!(fut.await, Err(_)));
+        assert!(fut.await.is_err());
     }
 
     #[tokio::test]
@@ -364,7 +379,9 @@ mod tests {
                 topic: "test".into(),
                 key: None,
                 payload: None,
+                headers: Vec::new(),
                 timestamp: None,
+                payload_encoding: PayloadEncoding::Base64,
             }));
 
             tx.send(message).unwrap();
@@ -391,7 +408,9 @@ mod tests {
             topic: "test".into(),
             key: None,
             payload: None,
+            headers: Vec::new(),
             timestamp: None,
+            payload_encoding: PayloadEncoding::Base64,
         }));
 
         tx.send(message).unwrap();
@@ -426,7 +445,9 @@ mod tests {
                 topic: "test".into(),
                 key: None,
                 payload: None,
+                headers: Vec::new(),
                 timestamp: None,
+                payload_encoding: PayloadEncoding::Base64,
             }));
 
             tx.send(message).unwrap();
@@ -453,7 +474,9 @@ mod tests {
             topic: "test".into(),
             key: None,
             payload: None,
+            headers: Vec::new(),
             timestamp: None,
+            payload_encoding: PayloadEncoding::Base64,
         }));
 
         tx.send(message).unwrap();
@@ -479,7 +502,7 @@ mod tests {
 
         let KafkaSourceResult { offset, .. } = match result {
             SourceResult::Kafka(result) => result,
-            SourceResult::Counter(_) => unreachable!(),
+            _ => unreachable!(),
         };
 
         assert_eq!(offset, -1);
@@ -502,7 +525,9 @@ mod tests {
                 topic: "test".into(),
                 key: None,
                 payload: None,
+                headers: Vec::new(),
                 timestamp: None,
+                payload_encoding: PayloadEncoding::Base64,
             }));
 
             tx.send(message).unwrap();
@@ -531,7 +556,9 @@ mod tests {
             topic: "test".into(),
             key: None,
             payload: None,
+            headers: Vec::new(),
             timestamp: None,
+            payload_encoding: PayloadEncoding::Base64,
         }));
 
         tx.send(message).unwrap();
@@ -540,7 +567,7 @@ mod tests {
 
         let next = stream.next().await.unwrap();
 
-        assert!(matches!(next, Ok(_)));
+        assert!(next.is_ok());
         assert_eq!(next.unwrap().len(), 1);
 
         drop(stream);
@@ -568,7 +595,9 @@ mod tests {
                 topic: "test".into(),
                 key: None,
                 payload: None,
+                headers: Vec::new(),
                 timestamp: None,
+                payload_encoding: PayloadEncoding::Base64,
             }));
 
             tx.send(message).unwrap();
@@ -595,7 +624,9 @@ mod tests {
             topic: "test".into(),
             key: None,
             payload: None,
+            headers: Vec::new(),
             timestamp: None,
+            payload_encoding: PayloadEncoding::Base64,
         }));
 
         tx.send(message).unwrap();
@@ -636,7 +667,9 @@ mod tests {
                 topic: "test".into(),
                 key: None,
                 payload: None,
+                headers: Vec::new(),
                 timestamp: None,
+                payload_encoding: PayloadEncoding::Base64,
             }));
 
             tx.send(message).unwrap();
@@ -654,7 +687,9 @@ mod tests {
             topic: "test".into(),
             key: None,
             payload: None,
+            headers: Vec::new(),
             timestamp: None,
+            payload_encoding: PayloadEncoding::Base64,
         }));
 
         tx.send(message).unwrap();
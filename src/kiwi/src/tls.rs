@@ -32,6 +32,26 @@ pub fn tls_acceptor(cert: impl AsRef<Path>, key: impl AsRef<Path>) -> anyhow::Re
     Ok(TlsAcceptor::from(Arc::new(config)))
 }
 
+/// Creates the client configuration for TLS connections to upstream systems.
+/// Server certificates are verified against the CA certificate(s) at
+/// `ca_location` if specified, or the Mozilla root certificates otherwise
+pub fn tls_client_config(ca_location: Option<&Path>) -> anyhow::Result<rustls::ClientConfig> {
+    let mut roots = rustls::RootCertStore::empty();
+
+    match ca_location {
+        Some(path) => {
+            for cert in load_certs(path)? {
+                roots.add(cert)?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+
+    Ok(rustls::ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth())
+}
+
 pub enum MaybeTlsStream<S> {
     Plain(S),
     Tls(Box<TlsStream<S>>),
@@ -1,3 +1,4 @@
+pub mod backoff;
 pub mod macros;
 pub mod serde;
 pub mod stream;
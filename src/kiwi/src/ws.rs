@@ -1,15 +1,19 @@
 use std::collections::BTreeMap;
+use std::future::Future;
 use std::sync::Mutex;
+use std::time::Duration;
 use std::{net::SocketAddr, sync::Arc};
 
 use anyhow::Context;
 use arc_swap::ArcSwapOption;
 use bytes::Bytes;
 use fastwebsockets::{upgrade, CloseCode, FragmentCollector, Frame, Payload, WebSocketError};
-use http::{Request, Response, StatusCode};
-use http_body_util::Empty;
+use http::{header, Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
 use hyper::service::service_fn;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio_rustls::TlsAcceptor;
 
 use crate::connection::ConnectionManager;
 use crate::hook::authenticate::types::Authenticate;
@@ -18,11 +22,44 @@ use crate::hook::intercept::types::{AuthCtx, ConnectionCtx, WebSocketConnectionC
 
 use crate::hook::intercept::types::Intercept;
 use crate::protocol::{Command, Message, ProtocolError};
+use crate::source::http::{split_events, HttpSource};
 use crate::source::{Source, SourceId};
 use crate::tls::{tls_acceptor, MaybeTlsStream};
 
 type Sources = Arc<Mutex<BTreeMap<SourceId, Box<dyn Source + Send + Sync + 'static>>>>;
 
+/// Health check configuration
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Healthcheck {
+    /// Duration a source may be degraded for before the server is reported
+    /// as not ready. If unset, the health of sources is not considered
+    pub degraded_threshold: Option<Duration>,
+}
+
+impl Healthcheck {
+    fn status(&self, sources: &Sources) -> StatusCode {
+        if let Some(threshold) = self.degraded_threshold {
+            let sources = sources.lock().expect("poisoned lock");
+
+            for source in sources.values() {
+                if let Some(since) = source.degraded_since() {
+                    if since.elapsed() >= threshold {
+                        tracing::debug!(
+                            source_id = source.source_id(),
+                            "Reporting not ready, since source has been degraded for {:?}",
+                            since.elapsed()
+                        );
+
+                        return StatusCode::SERVICE_UNAVAILABLE;
+                    }
+                }
+            }
+        }
+
+        StatusCode::OK
+    }
+}
+
 /// Starts a WebSocket server with the specified configuration
 pub async fn serve<I, A>(
     listen_addr: &SocketAddr,
@@ -31,7 +68,7 @@ pub async fn serve<I, A>(
     authenticate: Arc<ArcSwapOption<A>>,
     subscriber_config: crate::config::Subscriber,
     tls_config: Option<crate::config::Tls>,
-    healthcheck: bool,
+    healthcheck: Option<Healthcheck>,
 ) -> anyhow::Result<()>
 where
     I: Intercept + Send + Sync + 'static,
@@ -43,18 +80,67 @@ where
         None
     };
     let listener = tokio::net::TcpListener::bind(listen_addr).await?;
-    tracing::info!("Server listening on: {listen_addr}");
+
+    serve_listener(
+        listener,
+        acceptor,
+        sources,
+        intercept,
+        authenticate,
+        subscriber_config,
+        healthcheck,
+        std::future::pending(),
+    )
+    .await
+}
+
+/// Serves connections accepted by an already bound listener, which allows the
+/// caller to learn the address of the server before it starts. Once `shutdown`
+/// resolves, the listener is closed, connected WebSocket clients are sent a
+/// close frame and every connection is waited on before returning
+#[allow(clippy::too_many_arguments)]
+pub async fn serve_listener<I, A>(
+    listener: tokio::net::TcpListener,
+    acceptor: Option<TlsAcceptor>,
+    sources: Sources,
+    intercept: Arc<ArcSwapOption<I>>,
+    authenticate: Arc<ArcSwapOption<A>>,
+    subscriber_config: crate::config::Subscriber,
+    healthcheck: Option<Healthcheck>,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()>
+where
+    I: Intercept + Send + Sync + 'static,
+    A: Authenticate + Send + Sync + Unpin + 'static,
+{
+    tracing::info!("Server listening on: {}", listener.local_addr()?);
+
+    let mut connections = JoinSet::new();
+    let (closing_tx, closing_rx) = tokio::sync::watch::channel(false);
+
+    tokio::pin!(shutdown);
 
     loop {
-        let (stream, addr) = listener.accept().await?;
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            // Reap finished connections so the set does not grow unbounded
+            Some(result) = connections.join_next() => {
+                if let Err(e) = result {
+                    tracing::error!("Connection task failed: {}", e);
+                }
+                continue;
+            }
+            () = &mut shutdown => break,
+        };
         tracing::debug!(addr = ?addr, "Accepted connection");
         let acceptor = acceptor.clone();
         let authenticate = Arc::clone(&authenticate);
         let intercept = Arc::clone(&intercept);
         let sources = Arc::clone(&sources);
         let subscriber_config = subscriber_config.clone();
+        let mut closing = closing_rx.clone();
 
-        tokio::spawn(async move {
+        connections.spawn(async move {
             let io = if let Some(acceptor) = acceptor {
                 match acceptor.accept(stream).await {
                     Ok(stream) => {
@@ -69,21 +155,35 @@ where
                 hyper_util::rt::TokioIo::new(MaybeTlsStream::Plain(stream))
             };
 
+            // WebSocket clients outlive the request that upgraded them, so their
+            // tasks are handed back to be waited on once the connection ends
+            let (clients_tx, mut clients_rx) = tokio::sync::mpsc::unbounded_channel();
             let builder =
                 hyper_util::server::conn::auto::Builder::new(hyper_util::rt::TokioExecutor::new());
-            let conn_fut = builder.serve_connection_with_upgrades(
+            let service_closing = closing.clone();
+            let service_clients_tx = clients_tx.clone();
+            let mut conn_fut = Box::pin(builder.serve_connection_with_upgrades(
                 io,
                 service_fn(move |req: Request<hyper::body::Incoming>| {
                     let authenticate = Arc::clone(&authenticate);
                     let sources = Arc::clone(&sources);
                     let intercept = Arc::clone(&intercept);
                     let subscriber_config = subscriber_config.clone();
+                    let clients_tx = service_clients_tx.clone();
+                    let closing = service_closing.clone();
 
                     async move {
-                        if healthcheck && req.uri().path() == "/health" {
-                            return Response::builder()
-                                .status(StatusCode::OK)
-                                .body(Empty::new());
+                        if let Some(healthcheck) = healthcheck {
+                            if req.uri().path() == "/health" {
+                                return Response::builder()
+                                    .status(healthcheck.status(&sources))
+                                    .body(Full::default());
+                            }
+                        }
+
+                        if let Some(source_id) = ingest_source_id(req.uri().path()) {
+                            let source_id = source_id.to_string();
+                            return Ok(handle_ingest(sources, source_id, req).await);
                         }
 
                         let response = handle_ws(
@@ -93,19 +193,155 @@ where
                             subscriber_config,
                             addr,
                             req,
+                            clients_tx,
+                            closing,
                         )
                         .await;
 
                         Ok(response)
                     }
                 }),
-            );
+            ));
+
+            let result = tokio::select! {
+                result = conn_fut.as_mut() => result,
+                () = wait_for_close(&mut closing) => {
+                    conn_fut.as_mut().graceful_shutdown();
+                    conn_fut.as_mut().await
+                }
+            };
 
-            if let Err(e) = conn_fut.await {
+            if let Err(e) = result {
                 tracing::error!(addr = ?addr, "Error occurred while serving connection: {}", e);
             }
+
+            // The connection only drops its service once upgraded, so it is dropped
+            // here for the channel to close once every client has been handed back
+            drop(conn_fut);
+            drop(clients_tx);
+
+            while let Some(client) = clients_rx.recv().await {
+                if let Err(e) = client.await {
+                    tracing::error!(addr = ?addr, "WebSocket client task failed: {}", e);
+                }
+            }
         });
     }
+
+    drop(listener);
+    tracing::info!("Waiting on {} connections to close", connections.len());
+
+    let _ = closing_tx.send(true);
+
+    while let Some(result) = connections.join_next().await {
+        if let Err(e) = result {
+            tracing::error!("Connection task failed: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the ID of the source targeted by a request to publish events, if the
+/// path is of the form `/sources/{id}/events`
+fn ingest_source_id(path: &str) -> Option<&str> {
+    path.strip_prefix("/sources/")
+        .and_then(|path| path.strip_suffix("/events"))
+        .filter(|id| !id.is_empty() && !id.contains('/'))
+}
+
+fn ingest_response(status: StatusCode, body: serde_json::Value) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .expect("valid response")
+}
+
+/// Publishes the events contained in the body of a request to the HTTP source
+/// with the specified ID
+async fn handle_ingest(
+    sources: Sources,
+    source_id: SourceId,
+    request: Request<hyper::body::Incoming>,
+) -> Response<Full<Bytes>> {
+    if request.method() != Method::POST {
+        let mut response = ingest_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            serde_json::json!({ "error": "Only POST is supported" }),
+        );
+        response
+            .headers_mut()
+            .insert(header::ALLOW, header::HeaderValue::from_static("POST"));
+
+        return response;
+    }
+
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+
+    // The lock must not be held across the await point below, so only the
+    // publisher is retained. Requests for sources that do not exist are
+    // rejected as unauthorized, so that source IDs cannot be probed without a
+    // token
+    let (max_body_bytes, publisher) = {
+        let sources = sources.lock().expect("poisoned lock");
+
+        match sources
+            .get(&source_id)
+            .and_then(|source| source.as_any().downcast_ref::<HttpSource>())
+        {
+            Some(source) if source.authorize(authorization) => {
+                (source.max_body_bytes(), source.publisher())
+            }
+            _ => {
+                return ingest_response(
+                    StatusCode::UNAUTHORIZED,
+                    serde_json::json!({ "error": "Invalid or missing bearer token" }),
+                );
+            }
+        }
+    };
+
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(ToOwned::to_owned);
+
+    let body = match Limited::new(request.into_body(), max_body_bytes)
+        .collect()
+        .await
+    {
+        Ok(body) => body.to_bytes(),
+        Err(err) if err.downcast_ref::<LengthLimitError>().is_some() => {
+            return ingest_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                serde_json::json!({
+                    "error": format!("Request body exceeds the limit of {} bytes", max_body_bytes)
+                }),
+            );
+        }
+        Err(err) => {
+            tracing::debug!(source_id, "Failed to read request body: {}", err);
+
+            return ingest_response(
+                StatusCode::BAD_REQUEST,
+                serde_json::json!({ "error": "Failed to read request body" }),
+            );
+        }
+    };
+
+    let events = split_events(&body, content_type.as_deref());
+    let published = events.len();
+    let delivered = publisher.publish(events);
+
+    ingest_response(
+        StatusCode::ACCEPTED,
+        serde_json::json!({ "published": published, "delivered": delivered }),
+    )
 }
 
 #[tracing::instrument(skip_all)]
@@ -138,6 +374,7 @@ where
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_ws<I, A>(
     sources: Sources,
     intercept: Arc<ArcSwapOption<I>>,
@@ -145,7 +382,9 @@ async fn handle_ws<I, A>(
     subscriber_config: crate::config::Subscriber,
     addr: SocketAddr,
     mut request: Request<hyper::body::Incoming>,
-) -> Response<Empty<Bytes>>
+    clients_tx: tokio::sync::mpsc::UnboundedSender<JoinHandle<()>>,
+    closing: tokio::sync::watch::Receiver<bool>,
+) -> Response<Full<Bytes>>
 where
     I: Intercept + Send + Sync + 'static,
     A: Authenticate + Send + Sync + Unpin + 'static,
@@ -159,13 +398,13 @@ where
     } else {
         return Response::builder()
             .status(StatusCode::UNAUTHORIZED)
-            .body(Empty::new())
+            .body(Full::default())
             .unwrap();
     };
 
     let connection_ctx = ConnectionCtx::WebSocket(WebSocketConnectionCtx { addr });
 
-    tokio::spawn(async move {
+    let client = tokio::spawn(async move {
         if let Err(e) = handle_client(
             fut,
             sources,
@@ -173,6 +412,7 @@ where
             subscriber_config,
             connection_ctx.clone(),
             auth_ctx,
+            closing,
         )
         .await
         {
@@ -186,7 +426,11 @@ where
         tracing::debug!(connection = ?connection_ctx, "WebSocket connection terminated normally");
     });
 
-    response
+    // The receiver only hangs up once the connection has ended, in which case
+    // there is nothing left to wait on the client for
+    let _ = clients_tx.send(client);
+
+    response.map(|_| Full::default())
 }
 
 async fn handle_client<I>(
@@ -196,6 +440,7 @@ async fn handle_client<I>(
     subscriber_config: crate::config::Subscriber,
     connection_ctx: ConnectionCtx,
     auth_ctx: Option<AuthCtx>,
+    mut closing: tokio::sync::watch::Receiver<bool>,
 ) -> anyhow::Result<()>
 where
     I: Intercept + Send + Sync + 'static,
@@ -219,7 +464,7 @@ where
     );
 
     // Spawn the ingest actor. If it terminates, the connection should be closed
-    tokio::spawn(async move {
+    let actor = tokio::spawn(async move {
         if let Err(err) = actor.run().await {
             tracing::error!(connection = ?connection_ctx, "Connection manager terminated with error: {:?}", err);
         }
@@ -285,12 +530,26 @@ where
                     },
                 }
             }
+            () = wait_for_close(&mut closing) => {
+                let frame = Frame::close(CloseCode::Away.into(), b"Server is shutting down");
+                ws.write_frame(frame).await?;
+                break;
+            }
         }
     }
 
+    // The actor exits once the command channel is closed
+    drop(cmd_tx);
+    let _ = actor.await;
+
     Ok(())
 }
 
+/// Resolves once the server starts shutting down, or has stopped altogether
+async fn wait_for_close(closing: &mut tokio::sync::watch::Receiver<bool>) {
+    let _ = closing.wait_for(|closing| *closing).await;
+}
+
 enum RecvError {
     WebSocket(WebSocketError),
     Protocol(ProtocolError),
@@ -326,3 +585,18 @@ where
         _ => panic!("Received unexpected opcode"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ingest_source_id() {
+        assert_eq!(ingest_source_id("/sources/deploys/events"), Some("deploys"));
+        assert_eq!(ingest_source_id("/sources//events"), None);
+        assert_eq!(ingest_source_id("/sources/a/b/events"), None);
+        assert_eq!(ingest_source_id("/sources/deploys"), None);
+        assert_eq!(ingest_source_id("/health"), None);
+        assert_eq!(ingest_source_id("/"), None);
+    }
+}
@@ -105,7 +105,7 @@ impl AdminClient {
         Ok(())
     }
 
-    async fn delete_topics(&mut self, topics: &[&str]) -> anyhow::Result<()> {
+    pub async fn delete_topics(&mut self, topics: &[&str]) -> anyhow::Result<()> {
         let result = self.inner.delete_topics(topics, &self.options).await?;
 
         for result in result {
@@ -156,6 +156,25 @@ impl Producer {
     pub async fn send(&self, topic: &str, key: &str, payload: &str) -> anyhow::Result<()> {
         let record = FutureRecord::to(topic).payload(payload).key(key);
 
+        self.inner
+            .send(record, std::time::Duration::from_secs(0))
+            .await
+            .map_err(|(e, _)| anyhow::anyhow!("Failed to send message: {}", e))?;
+
+        Ok(())
+    }
+    pub async fn send_to_partition(
+        &self,
+        topic: &str,
+        partition: i32,
+        key: &str,
+        payload: &str,
+    ) -> anyhow::Result<()> {
+        let record = FutureRecord::to(topic)
+            .partition(partition)
+            .payload(payload)
+            .key(key);
+
         self.inner
             .send(record, std::time::Duration::from_secs(0))
             .await
@@ -1,10 +1,16 @@
 use std::io::Write;
+use std::time::Duration;
 
 use anyhow::Context;
 use nix::sys::signal::{self, Signal};
 use nix::unistd::Pid;
 use tempfile::{NamedTempFile, TempPath};
 
+use super::healthcheck::Healthcheck;
+
+/// Address Kiwi listens on in tests that run a single instance
+pub const ADDRESS: &str = "127.0.0.1:8000";
+
 pub struct Process {
     proc: std::process::Child,
 }
@@ -49,6 +55,7 @@ pub struct ConfigFile {
 }
 
 impl ConfigFile {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> anyhow::Result<Self> {
         let mut file = NamedTempFile::new().context("failed to create temporary file")?;
         file.as_file_mut()
@@ -74,3 +81,36 @@ impl ConfigFile {
         self.inner.into_temp_path()
     }
 }
+
+/// Starts Kiwi with the specified (YAML formatted) sources, listening on
+/// [`ADDRESS`], and waits for it to become healthy
+pub async fn start_kiwi(sources: &str) -> anyhow::Result<(Process, ConfigFile)> {
+    start_kiwi_at(ADDRESS, sources).await
+}
+
+/// Starts Kiwi with the specified (YAML formatted) sources, listening on the
+/// specified address, and waits for it to become healthy
+pub async fn start_kiwi_at(address: &str, sources: &str) -> anyhow::Result<(Process, ConfigFile)> {
+    let config = ConfigFile::from_str(
+        format!(
+            r#"
+        sources:
+{sources}
+        server:
+            address: '{address}'
+        "#
+        )
+        .as_str(),
+    )?;
+    let kiwi = Process::new_with_args(&["--config", config.path_str()])?;
+
+    Healthcheck {
+        interval: Duration::from_millis(200),
+        attempts: 10,
+        url: &format!("http://{}/health", address),
+    }
+    .run()
+    .await?;
+
+    Ok((kiwi, config))
+}
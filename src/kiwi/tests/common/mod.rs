@@ -1,4 +1,21 @@
 pub mod healthcheck;
 pub mod kafka;
 pub mod kiwi;
+pub mod mqtt;
+pub mod nats;
+pub mod postgres;
+pub mod redis;
+pub mod relay;
 pub mod ws;
+
+use base64::Engine;
+
+/// Decodes a base64 encoded key or payload from a Kafka `RESULT` message
+pub fn decode_base64(value: Option<serde_json::Value>) -> Vec<u8> {
+    let value = value.expect("value must be present");
+    let value = value.as_str().expect("value must be a string");
+
+    base64::engine::general_purpose::STANDARD
+        .decode(value)
+        .expect("value must be valid base64")
+}
@@ -0,0 +1,48 @@
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Outgoing, Packet, QoS};
+
+/// MQTT client used to publish messages
+pub struct Client {
+    client: AsyncClient,
+    eventloop: EventLoop,
+}
+
+impl Client {
+    /// Connects to the broker at the specified `mqtt://host:port` URL
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let url = url::Url::parse(url)?;
+        let options = MqttOptions::new(
+            format!("test-{}", nanoid::nanoid!(16)),
+            url.host_str().unwrap_or("localhost"),
+            url.port().unwrap_or(1883),
+        );
+
+        let (client, mut eventloop) = AsyncClient::new(options, 10);
+
+        loop {
+            if let Event::Incoming(Packet::ConnAck(_)) = eventloop.poll().await? {
+                break;
+            }
+        }
+
+        Ok(Self { client, eventloop })
+    }
+
+    /// Publishes a message with QoS 1, waiting until the broker acknowledges it
+    pub async fn publish(&mut self, topic: &str, payload: &str) -> anyhow::Result<()> {
+        self.client
+            .publish(topic, QoS::AtLeastOnce, false, payload.as_bytes().to_vec())
+            .await?;
+
+        let mut packet_id = None;
+
+        loop {
+            match self.eventloop.poll().await? {
+                Event::Outgoing(Outgoing::Publish(id)) => packet_id = Some(id),
+                Event::Incoming(Packet::PubAck(ack)) if Some(ack.pkid) == packet_id => {
+                    return Ok(())
+                }
+                _ => continue,
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use async_nats::jetstream::{self, stream};
+use async_nats::HeaderMap;
+
+/// NATS client used to publish messages and manage JetStream streams
+pub struct Client {
+    client: async_nats::Client,
+}
+
+impl Client {
+    /// Connects to the server at the specified `nats://host:port` URL
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: async_nats::connect(url).await?,
+        })
+    }
+
+    /// Publishes a message with the specified headers, waiting until the server
+    /// has processed it
+    pub async fn publish(
+        &mut self,
+        subject: &str,
+        headers: &[(&str, &str)],
+        payload: &str,
+    ) -> anyhow::Result<()> {
+        let mut map = HeaderMap::new();
+        for (key, value) in headers {
+            map.append(*key, *value);
+        }
+
+        self.client
+            .publish_with_headers(subject.to_string(), map, payload.to_string().into())
+            .await?;
+        self.client.flush().await?;
+
+        Ok(())
+    }
+
+    /// Creates a JetStream stream storing messages published to the subject
+    pub async fn create_stream(&mut self, name: &str, subject: &str) -> anyhow::Result<()> {
+        jetstream::new(self.client.clone())
+            .create_stream(stream::Config {
+                name: name.to_string(),
+                subjects: vec![subject.to_string()],
+                ..Default::default()
+            })
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+use tokio::task::JoinHandle;
+use tokio_postgres::NoTls;
+
+/// PostgreSQL client used to send notifications and set up the server
+pub struct Client {
+    client: tokio_postgres::Client,
+    connection: JoinHandle<()>,
+}
+
+impl Client {
+    /// Connects to the server at the specified `postgres://` URL
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let (client, connection) = tokio_postgres::connect(url, NoTls).await?;
+
+        Ok(Self {
+            client,
+            connection: tokio::spawn(async move {
+                let _ = connection.await;
+            }),
+        })
+    }
+
+    /// Sends a notification to the specified channel
+    pub async fn notify(&mut self, channel: &str, payload: &str) -> anyhow::Result<()> {
+        self.client
+            .execute("SELECT pg_notify($1, $2)", &[&channel, &payload])
+            .await?;
+
+        Ok(())
+    }
+
+    /// Executes one or more statements
+    pub async fn execute(&mut self, statements: &str) -> anyhow::Result<()> {
+        Ok(self.client.batch_execute(statements).await?)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.connection.abort();
+    }
+}
@@ -0,0 +1,28 @@
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+
+/// Redis client used to publish messages and append stream entries
+pub struct Client {
+    conn: MultiplexedConnection,
+}
+
+impl Client {
+    /// Connects to the server at the specified `redis://host:port` URL
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let conn = redis::Client::open(url)?
+            .get_multiplexed_async_connection()
+            .await?;
+
+        Ok(Self { conn })
+    }
+
+    /// Publishes a message, returning the number of clients that received it
+    pub async fn publish(&mut self, channel: &str, payload: &str) -> anyhow::Result<i64> {
+        Ok(self.conn.publish(channel, payload).await?)
+    }
+
+    /// Appends an entry to a stream, returning the ID of the entry
+    pub async fn xadd(&mut self, key: &str, fields: &[(&str, &str)]) -> anyhow::Result<String> {
+        Ok(self.conn.xadd(key, "*", fields).await?)
+    }
+}
@@ -0,0 +1,169 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use fastwebsockets::{FragmentCollector, Frame, OpCode, Payload};
+use futures::StreamExt;
+use http_body_util::{combinators::BoxBody, BodyExt, StreamBody};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper::{Request, Response};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+type Body = BoxBody<Bytes, Infallible>;
+
+/// Requests and messages received by an [`Upstream`]
+#[derive(Debug, Default)]
+struct Received {
+    /// Values of the `Last-Event-ID` header sent with each event stream request
+    last_event_ids: Vec<Option<String>>,
+    /// Messages sent by WebSocket clients
+    messages: Vec<String>,
+}
+
+/// Upstream that streams server-sent events to clients, or WebSocket messages
+/// to clients that request an upgrade
+pub struct Upstream {
+    addr: SocketAddr,
+    /// Chunks of the event stream or WebSocket messages. `None` disconnects
+    /// all clients
+    tx: broadcast::Sender<Option<String>>,
+    received: Arc<Mutex<Received>>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+async fn handle(
+    mut request: Request<Incoming>,
+    tx: broadcast::Sender<Option<String>>,
+    received: Arc<Mutex<Received>>,
+) -> anyhow::Result<Response<Body>> {
+    let rx = tx.subscribe();
+
+    if fastwebsockets::upgrade::is_upgrade_request(&request) {
+        let (response, fut) = fastwebsockets::upgrade::upgrade(&mut request)?;
+
+        tokio::spawn(async move {
+            let mut rx = rx;
+            let Ok(ws) = fut.await else { return };
+            let mut ws = FragmentCollector::new(ws);
+
+            loop {
+                tokio::select! {
+                    frame = ws.read_frame() => {
+                        let Ok(frame) = frame else { return };
+
+                        if frame.opcode == OpCode::Text {
+                            received.lock().unwrap().messages.push(
+                                String::from_utf8(frame.payload.to_vec()).unwrap(),
+                            );
+                        }
+                    }
+                    message = rx.recv() => {
+                        let Ok(Some(message)) = message else { return };
+                        let frame = Frame::text(Payload::Owned(message.into_bytes()));
+
+                        if ws.write_frame(frame).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        return Ok(response.map(|body| body.boxed()));
+    }
+
+    received.lock().unwrap().last_event_ids.push(
+        request
+            .headers()
+            .get("last-event-id")
+            .map(|value| value.to_str().unwrap().to_string()),
+    );
+
+    let chunks = BroadcastStream::new(rx)
+        .take_while(|chunk| futures::future::ready(!matches!(chunk, Ok(None))))
+        .filter_map(|chunk| {
+            futures::future::ready(match chunk {
+                Ok(Some(chunk)) => Some(Ok(hyper::body::Frame::data(Bytes::from(chunk)))),
+                _ => None,
+            })
+        });
+
+    Ok(Response::builder()
+        .header("content-type", "text/event-stream")
+        .body(BodyExt::boxed(StreamBody::new(chunks)))?)
+}
+
+impl Upstream {
+    pub async fn start() -> anyhow::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let (tx, _) = broadcast::channel(100);
+        let received = Arc::new(Mutex::new(Received::default()));
+
+        let server = tokio::spawn({
+            let tx = tx.clone();
+            let received = Arc::clone(&received);
+
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let tx = tx.clone();
+                    let received = Arc::clone(&received);
+
+                    tokio::spawn(async move {
+                        let service = service_fn(move |request| {
+                            handle(request, tx.clone(), Arc::clone(&received))
+                        });
+
+                        let _ = hyper::server::conn::http1::Builder::new()
+                            .serve_connection(TokioIo::new(stream), service)
+                            .with_upgrades()
+                            .await;
+                    });
+                }
+            }
+        });
+
+        Ok(Self {
+            addr,
+            tx,
+            received,
+            server,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Sends a chunk of the event stream, or a WebSocket message, to all
+    /// connected clients
+    pub fn send(&self, chunk: &str) {
+        let _ = self.tx.send(Some(chunk.to_string()));
+    }
+
+    /// Disconnects all connected clients
+    pub fn disconnect(&self) {
+        let _ = self.tx.send(None);
+    }
+
+    /// Values of the `Last-Event-ID` header sent by each event stream request
+    pub fn last_event_ids(&self) -> Vec<Option<String>> {
+        self.received.lock().unwrap().last_event_ids.clone()
+    }
+
+    /// Messages sent by WebSocket clients
+    pub fn messages(&self) -> Vec<String> {
+        self.received.lock().unwrap().messages.clone()
+    }
+}
+
+impl Drop for Upstream {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
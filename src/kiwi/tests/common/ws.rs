@@ -13,6 +13,9 @@ use hyper::header::UPGRADE;
 use hyper::upgrade::Upgraded;
 use hyper::{Request, Response, Uri};
 use hyper_util::rt::TokioIo;
+use kiwi::protocol::{Command, CommandResponse, Message, SubscriptionMode};
+
+use super::kiwi::ADDRESS;
 
 pub struct Client {
     ws: FragmentCollector<TokioIo<Upgraded>>,
@@ -88,8 +91,38 @@ impl Client {
     pub async fn recv_json<T: serde::de::DeserializeOwned>(&mut self) -> anyhow::Result<T> {
         let text_frame = self.recv_text_frame().await?;
         let text = std::str::from_utf8(text_frame.payload.as_ref())?;
-        let value = serde_json::from_str(&text)?;
+        let value = serde_json::from_str(text)?;
 
         Ok(value)
     }
 }
+
+/// Connects to Kiwi listening on [`ADDRESS`] and subscribes to the specified
+/// source in push mode
+pub async fn subscribe(source_id: &str) -> anyhow::Result<Client> {
+    subscribe_at(ADDRESS, source_id).await
+}
+
+/// Connects to Kiwi listening on the specified address and subscribes to the
+/// specified source in push mode
+pub async fn subscribe_at(address: &str, source_id: &str) -> anyhow::Result<Client> {
+    let (mut ws_client, _) = Client::connect(&format!("ws://{}", address)).await?;
+
+    ws_client
+        .send_json(&Command::Subscribe {
+            source_id: source_id.to_string(),
+            mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
+        })
+        .await?;
+
+    let resp: Message = ws_client.recv_json().await?;
+
+    assert!(
+        matches!(resp, Message::CommandResponse(CommandResponse::SubscribeOk { source_id: id }) if id == source_id)
+    );
+
+    Ok(ws_client)
+}
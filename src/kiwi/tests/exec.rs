@@ -0,0 +1,118 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::kiwi::{start_kiwi, ConfigFile, Process};
+use common::ws::{subscribe, Client as WsClient};
+use kiwi::protocol::{Message, Notice, PayloadEncoding, SourceResult, SourceStatus};
+
+use crate::common::healthcheck::Healthcheck;
+
+async fn recv_payload(ws_client: &mut WsClient) -> anyhow::Result<serde_json::Value> {
+    let msg: Message =
+        tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await??;
+
+    match msg {
+        Message::Result(SourceResult::Custom {
+            source_id,
+            kind,
+            payload,
+            payload_encoding,
+            timestamp,
+            ..
+        }) => {
+            assert_eq!(source_id, "ticks");
+            assert_eq!(kind, "exec");
+            assert_eq!(payload_encoding, PayloadEncoding::Json);
+            assert!(timestamp > 0);
+
+            Ok(payload)
+        }
+        msg => panic!("Expected exec result. Received {:?}", msg),
+    }
+}
+
+/// Tests that each line written by a command is delivered to subscribers, and
+/// that the command is restarted once it exits
+#[tokio::test]
+async fn test_streams_command_output() -> anyhow::Result<()> {
+    let (_kiwi, _config) = start_kiwi(
+        r#"
+            - type: exec
+              id: ticks
+              command: sh
+              args:
+                - '-c'
+                - 'for n in 1 2 3; do echo "{\"tick\":$n,\"run\":\"$RUN\"}"; sleep 0.2; done'
+              env:
+                RUN: test
+              lazy: true
+              payload_encoding: json
+        "#,
+    )
+    .await?;
+
+    let mut ws_client = subscribe("ticks").await?;
+
+    // The command only starts once the source is subscribed to, so no ticks
+    // are missed
+    for _ in 0..2 {
+        for tick in 1..=3 {
+            assert_eq!(
+                recv_payload(&mut ws_client).await?,
+                serde_json::json!({ "tick": tick, "run": "test" })
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Tests that clients subscribing to a degraded source are notified of it, and
+/// that the server is reported as unhealthy once the source has been degraded
+/// for longer than the configured threshold
+#[tokio::test]
+async fn test_reports_degraded_source() -> anyhow::Result<()> {
+    let config = ConfigFile::from_str(
+        r#"
+        sources:
+            - type: exec
+              id: broken
+              command: /nonexistent/command
+        server:
+            address: '127.0.0.1:8000'
+            healthcheck_degraded_threshold_ms: 1500
+        "#,
+    )?;
+    let _kiwi = Process::new_with_args(&["--config", config.path_str()])?;
+
+    Healthcheck {
+        interval: Duration::from_millis(100),
+        attempts: 10,
+        url: "http://127.0.0.1:8000/health",
+    }
+    .run()
+    .await?;
+
+    let mut ws_client = subscribe("broken").await?;
+
+    let msg: Message =
+        tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await??;
+
+    assert!(matches!(
+        msg,
+        Message::Notice(Notice::SourceStatus {
+            source_id,
+            status: SourceStatus::Degraded,
+            message: Some(message),
+        }) if source_id == "broken" && message.contains("Failed to run /nonexistent/command")
+    ));
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+
+    let response = reqwest::get("http://127.0.0.1:8000/health").await?;
+
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+    Ok(())
+}
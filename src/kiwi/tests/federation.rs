@@ -0,0 +1,143 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::kiwi::{start_kiwi_at, ConfigFile, Process};
+use common::ws::{subscribe_at, Client as WsClient};
+use kiwi::protocol::{Message, PayloadEncoding, SourceResult};
+
+const UPSTREAM_ADDRESS: &str = "127.0.0.1:8000";
+const EDGE_ADDRESS: &str = "127.0.0.1:8001";
+
+async fn start_upstream() -> anyhow::Result<(Process, ConfigFile)> {
+    start_kiwi_at(
+        UPSTREAM_ADDRESS,
+        r#"
+            - type: http
+              id: deployments
+              tokens:
+                - s3cr3t
+              payload_encoding: json
+        "#,
+    )
+    .await
+}
+
+async fn start_edge() -> anyhow::Result<(Process, ConfigFile)> {
+    start_kiwi_at(
+        EDGE_ADDRESS,
+        &format!(
+            r#"
+            - type: kiwi
+              id: edge-deployments
+              url: 'ws://{UPSTREAM_ADDRESS}'
+              source_id: deployments
+        "#
+        ),
+    )
+    .await
+}
+
+/// Publishes an event to the upstream until the edge relays it, since the edge
+/// subscribes to the upstream asynchronously
+async fn publish_until_received(
+    ws_client: &mut WsClient,
+    body: &serde_json::Value,
+) -> anyhow::Result<Message> {
+    let client = reqwest::Client::new();
+    let recv = ws_client.recv_json::<Message>();
+    futures::pin_mut!(recv);
+
+    tokio::time::timeout(Duration::from_secs(20), async {
+        loop {
+            // The upstream may be unreachable while it restarts
+            let _ = client
+                .post(format!(
+                    "http://{}/sources/deployments/events",
+                    UPSTREAM_ADDRESS
+                ))
+                .bearer_auth("s3cr3t")
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .send()
+                .await;
+
+            tokio::select! {
+                msg = &mut recv => return msg,
+                _ = tokio::time::sleep(Duration::from_millis(200)) => {}
+            }
+        }
+    })
+    .await?
+}
+
+fn assert_federated(msg: Message, expected: &serde_json::Value) {
+    match msg {
+        Message::Result(SourceResult::Custom {
+            source_id,
+            kind,
+            payload,
+            payload_encoding,
+            timestamp,
+            ..
+        }) => {
+            // Events keep the type and metadata of the upstream source
+            assert_eq!(source_id, "edge-deployments");
+            assert_eq!(kind, "http");
+            assert_eq!(&payload, expected);
+            assert_eq!(payload_encoding, PayloadEncoding::Json);
+            assert!(timestamp > 0);
+        }
+        msg => panic!("Expected HTTP result. Received {:?}", msg),
+    }
+}
+
+/// Tests that events from a source on an upstream Kiwi instance are delivered
+/// to subscribers of a Kiwi source on another instance
+#[tokio::test]
+async fn test_relays_upstream_source() -> anyhow::Result<()> {
+    let (_upstream, _upstream_config) = start_upstream().await?;
+    let (_edge, _edge_config) = start_edge().await?;
+
+    let mut ws_client = subscribe_at(EDGE_ADDRESS, "edge-deployments").await?;
+
+    let body = serde_json::json!({ "service": "api", "version": 42 });
+    let msg = publish_until_received(&mut ws_client, &body).await?;
+
+    assert_federated(msg, &body);
+
+    Ok(())
+}
+
+/// Tests that the Kiwi source resubscribes after the upstream instance
+/// restarts, without disconnecting its own subscribers
+#[tokio::test]
+async fn test_resubscribes_after_upstream_restart() -> anyhow::Result<()> {
+    let (mut upstream, _upstream_config) = start_upstream().await?;
+    let (_edge, _edge_config) = start_edge().await?;
+
+    let mut ws_client = subscribe_at(EDGE_ADDRESS, "edge-deployments").await?;
+
+    let body = serde_json::json!({ "service": "api", "version": 1 });
+    assert_federated(publish_until_received(&mut ws_client, &body).await?, &body);
+
+    upstream.kill();
+    upstream.proc_mut().wait()?;
+    drop(upstream);
+
+    let (_upstream, _upstream_config) = start_upstream().await?;
+
+    // Degradation of the source may be reported while the upstream is down
+    let body = serde_json::json!({ "service": "api", "version": 2 });
+    loop {
+        match publish_until_received(&mut ws_client, &body).await? {
+            Message::Notice(_) => continue,
+            msg => {
+                assert_federated(msg, &body);
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
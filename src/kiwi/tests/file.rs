@@ -0,0 +1,141 @@
+pub mod common;
+
+use std::io::Write;
+use std::time::Duration;
+
+use common::kiwi::start_kiwi;
+use common::ws::Client as WsClient;
+use kiwi::protocol::{
+    Command, CommandResponse, Message, Notice, PayloadEncoding, SourceResult, SubscriptionMode,
+};
+
+async fn subscribe(ws_client: &mut WsClient, source_id: &str) -> anyhow::Result<CommandResponse> {
+    ws_client
+        .send_json(&Command::Subscribe {
+            source_id: source_id.to_string(),
+            mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
+        })
+        .await?;
+
+    match ws_client.recv_json().await? {
+        Message::CommandResponse(resp) => Ok(resp),
+        msg => panic!("Expected command response. Received {:?}", msg),
+    }
+}
+
+async fn recv_line(ws_client: &mut WsClient) -> anyhow::Result<(u64, serde_json::Value)> {
+    let msg: Message =
+        tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await??;
+
+    match msg {
+        Message::Result(SourceResult::Custom {
+            source_id,
+            kind,
+            metadata,
+            payload,
+            payload_encoding,
+            ..
+        }) => {
+            assert_eq!(source_id, "events");
+            assert_eq!(kind, "file");
+            assert_eq!(payload_encoding, PayloadEncoding::Json);
+
+            Ok((metadata["offset"].parse()?, payload))
+        }
+        msg => panic!("Expected file result. Received {:?}", msg),
+    }
+}
+
+/// Tests that lines appended to a tailed file are delivered to subscribers
+#[tokio::test]
+async fn test_tails_file_source() -> anyhow::Result<()> {
+    let mut file = tempfile::NamedTempFile::new()?;
+    writeln!(file, r#"{{"existing":true}}"#)?;
+
+    let (_kiwi, _config) = start_kiwi(&format!(
+        r#"
+            - type: file
+              id: events
+              path: '{}'
+              payload_encoding: json
+        "#,
+        file.path().display()
+    ))
+    .await?;
+
+    let (mut ws_client, _) = WsClient::connect("ws://127.0.0.1:8000").await?;
+
+    assert!(matches!(
+        subscribe(&mut ws_client, "events").await?,
+        CommandResponse::SubscribeOk { .. }
+    ));
+
+    writeln!(file, r#"{{"level":"info"}}"#)?;
+    writeln!(file, r#"{{"level":"warn"}}"#)?;
+
+    assert_eq!(
+        recv_line(&mut ws_client).await?,
+        (18, serde_json::json!({ "level": "info" }))
+    );
+    assert_eq!(
+        recv_line(&mut ws_client).await?,
+        (35, serde_json::json!({ "level": "warn" }))
+    );
+
+    Ok(())
+}
+
+/// Tests that a recording is replayed once subscribed to, after which the
+/// subscription is closed and the source can no longer be subscribed to
+#[tokio::test]
+async fn test_replays_file_source() -> anyhow::Result<()> {
+    let mut file = tempfile::NamedTempFile::new()?;
+    for (ts, n) in [(1000, 1), (1200, 2), (1400, 3)] {
+        writeln!(file, r#"{{"ts":{},"n":{}}}"#, ts, n)?;
+    }
+
+    let (_kiwi, _config) = start_kiwi(&format!(
+        r#"
+            - type: file
+              id: events
+              path: '{}'
+              replay:
+                timestamp_field: ts
+                speed: 2
+              lazy: true
+              payload_encoding: json
+        "#,
+        file.path().display()
+    ))
+    .await?;
+
+    let (mut ws_client, _) = WsClient::connect("ws://127.0.0.1:8000").await?;
+
+    assert!(matches!(
+        subscribe(&mut ws_client, "events").await?,
+        CommandResponse::SubscribeOk { .. }
+    ));
+
+    for n in 1..=3 {
+        let (_, payload) = recv_line(&mut ws_client).await?;
+        assert_eq!(payload["n"], n);
+    }
+
+    let msg: Message =
+        tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await??;
+
+    assert!(matches!(
+        msg,
+        Message::Notice(Notice::SubscriptionClosed { source_id, .. }) if source_id == "events"
+    ));
+
+    assert!(matches!(
+        subscribe(&mut ws_client, "events").await?,
+        CommandResponse::SubscribeError { .. }
+    ));
+
+    Ok(())
+}
@@ -3,6 +3,11 @@ pub mod common;
 use std::time::Duration;
 
 use common::kiwi::{ConfigFile, Process};
+use kiwi::hook::intercept::types::{
+    Action, ConnectionCtx, Context, CounterEventCtx, EventCtx, Intercept, KafkaEventCtx,
+    WebSocketConnectionCtx,
+};
+use kiwi::hook::wasm::{WasmHook, WasmInterceptHook};
 
 use crate::common::healthcheck::Healthcheck;
 use crate::common::ws::Client as WsClient;
@@ -48,3 +53,53 @@ async fn test_authenticate() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Tests that the `intercept` hook receives the context laid out as described by
+/// the current WIT package
+#[tokio::test]
+async fn test_intercept() -> anyhow::Result<()> {
+    const INTERCEPT_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/wasm/kafka-even-numbers-intercept.wasm"
+    );
+
+    let hook = WasmInterceptHook::from_file(INTERCEPT_PATH)?;
+
+    let context = |event: EventCtx| {
+        Context::new(
+            None,
+            ConnectionCtx::WebSocket(WebSocketConnectionCtx::new(
+                "127.0.0.1:1234".parse().unwrap(),
+            )),
+            event,
+        )
+    };
+    let kafka_event = |payload: &str| {
+        EventCtx::Kafka(
+            KafkaEventCtx::new("numbers".into(), "numbers".into(), 0, 0)
+                .with_key(b"key".to_vec())
+                .with_payload(payload.as_bytes().to_vec())
+                .with_headers(vec![("header".into(), Some(b"value".to_vec()))])
+                .with_timestamp(0),
+        )
+    };
+
+    assert!(matches!(
+        hook.intercept(&context(kafka_event("998"))).await?,
+        Action::Forward
+    ));
+    assert!(matches!(
+        hook.intercept(&context(kafka_event("999"))).await?,
+        Action::Discard
+    ));
+    assert!(matches!(
+        hook.intercept(&context(EventCtx::Counter(CounterEventCtx::new(
+            "counter".into(),
+            1
+        ))))
+        .await?,
+        Action::Forward
+    ));
+
+    Ok(())
+}
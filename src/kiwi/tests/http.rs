@@ -0,0 +1,155 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::kiwi::{ConfigFile, Process};
+use common::ws::{subscribe, Client as WsClient};
+use kiwi::protocol::{Message, PayloadEncoding, SourceResult};
+use reqwest::StatusCode;
+
+const EVENTS_URL: &str = "http://127.0.0.1:8000/sources/deployments/events";
+
+async fn start_kiwi() -> anyhow::Result<(Process, ConfigFile)> {
+    common::kiwi::start_kiwi(
+        r#"
+            - type: http
+              id: deployments
+              tokens:
+                - s3cr3t
+              max_body_bytes: 64
+              payload_encoding: json
+            - type: counter
+              id: counter
+              min: 0
+              interval_ms: 1000
+              lazy: true
+        "#,
+    )
+    .await
+}
+
+async fn recv_payload(ws_client: &mut WsClient) -> anyhow::Result<serde_json::Value> {
+    let msg: Message =
+        tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await??;
+
+    match msg {
+        Message::Result(SourceResult::Custom {
+            source_id,
+            kind,
+            payload,
+            payload_encoding,
+            timestamp,
+            ..
+        }) => {
+            assert_eq!(source_id, "deployments");
+            assert_eq!(kind, "http");
+            assert_eq!(payload_encoding, PayloadEncoding::Json);
+            assert!(timestamp > 0);
+
+            Ok(payload)
+        }
+        _ => panic!("Expected HTTP event. Received {:?}", msg),
+    }
+}
+
+/// Tests that events posted to an HTTP source, individually or as an NDJSON
+/// batch, are delivered to its subscribers
+#[tokio::test]
+async fn test_receives_posted_events_http_source() -> anyhow::Result<()> {
+    let (_kiwi, _config) = start_kiwi().await?;
+    let mut ws_client = subscribe("deployments").await?;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(EVENTS_URL)
+        .bearer_auth("s3cr3t")
+        .header("content-type", "application/json")
+        .body(r#"{"service":"api"}"#)
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&response.text().await?)?,
+        serde_json::json!({ "published": 1, "delivered": 1 })
+    );
+    assert_eq!(
+        recv_payload(&mut ws_client).await?,
+        serde_json::json!({ "service": "api" })
+    );
+
+    let response = client
+        .post(EVENTS_URL)
+        .bearer_auth("s3cr3t")
+        .header("content-type", "application/x-ndjson")
+        .body("{\"id\":1}\n{\"id\":2}\n")
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    for id in 1..=2 {
+        assert_eq!(
+            recv_payload(&mut ws_client).await?,
+            serde_json::json!({ "id": id })
+        );
+    }
+
+    Ok(())
+}
+
+/// Tests that requests which are unauthorized, too large or target a source
+/// that is not an HTTP source are rejected without publishing any events
+#[tokio::test]
+async fn test_rejects_invalid_requests_http_source() -> anyhow::Result<()> {
+    let (_kiwi, _config) = start_kiwi().await?;
+    let mut ws_client = subscribe("deployments").await?;
+    let client = reqwest::Client::new();
+
+    let response = client.post(EVENTS_URL).body("{}").send().await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = client
+        .post(EVENTS_URL)
+        .bearer_auth("wrong")
+        .body("{}")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = client
+        .post(EVENTS_URL)
+        .bearer_auth("s3cr3t")
+        .body(format!(r#"{{"padding":"{}"}}"#, "a".repeat(64)))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let response = client
+        .post("http://127.0.0.1:8000/sources/counter/events")
+        .bearer_auth("s3cr3t")
+        .body("{}")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = client
+        .post("http://127.0.0.1:8000/sources/missing/events")
+        .bearer_auth("s3cr3t")
+        .body("{}")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = client.get(EVENTS_URL).bearer_auth("s3cr3t").send().await?;
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    assert!(
+        tokio::time::timeout(Duration::from_millis(500), ws_client.recv_json::<Message>())
+            .await
+            .is_err(),
+        "No events should have been published"
+    );
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 pub mod common;
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::{Seek, SeekFrom, Write};
 use std::time::Duration;
 
@@ -60,6 +61,9 @@ async fn test_receives_messages_kafka_source() -> anyhow::Result<()> {
         .send_json(&Command::Subscribe {
             source_id: topic.clone(),
             mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
         })
         .await?;
 
@@ -94,7 +98,7 @@ async fn test_receives_messages_kafka_source() -> anyhow::Result<()> {
                 }) => {
                     assert_eq!(source_id.as_ref(), topic);
                     assert_eq!(
-                        std::str::from_utf8(&payload.unwrap()).unwrap(),
+                        std::str::from_utf8(&common::decode_base64(payload)).unwrap(),
                         format!("Message {}", count)
                     );
                 }
@@ -157,6 +161,9 @@ async fn test_closes_subscription_on_partition_added() -> anyhow::Result<()> {
         .send_json(&Command::Subscribe {
             source_id: topic.clone(),
             mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
         })
         .await?;
 
@@ -186,6 +193,75 @@ async fn test_closes_subscription_on_partition_added() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Tests that subscriptions to a Kafka source are closed when its topic is deleted
+#[tokio::test]
+async fn test_closes_subscription_on_topic_deleted() -> anyhow::Result<()> {
+    let bootstrap_server = BOOTSTRAP_SERVER.as_str();
+    let mut client = AdminClient::new(bootstrap_server)?;
+    let topic = client.create_random_topic(1).await?;
+    let config = ConfigFile::from_str(
+        format!(
+            r#"
+        sources:
+            - type: kafka
+              topic: {topic}
+
+        kafka:
+            bootstrap_servers:
+                - '{bootstrap_server}'
+            partition_discovery_interval_ms: 3000
+        server:
+            address: '127.0.0.1:8000'
+        "#
+        )
+        .as_str(),
+    )?;
+    let _kiwi = Process::new_with_args(&["--config", config.path_str()])?;
+
+    Healthcheck {
+        interval: Duration::from_millis(200),
+        attempts: 10,
+        url: "http://127.0.0.1:8000/health",
+    }
+    .run()
+    .await?;
+
+    let (mut ws_client, _) = WsClient::connect("ws://127.0.0.1:8000").await?;
+
+    ws_client
+        .send_json(&Command::Subscribe {
+            source_id: topic.clone(),
+            mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
+        })
+        .await?;
+
+    let resp: Message = ws_client.recv_json().await?;
+
+    assert!(
+        matches!(resp, kiwi::protocol::Message::CommandResponse(CommandResponse::SubscribeOk { source_id }) if source_id == topic)
+    );
+
+    client.delete_topics(&[topic.as_str()]).await?;
+
+    let resp: Message = tokio::select! {
+        resp = ws_client.recv_json() => resp?,
+        _ = tokio::time::sleep(Duration::from_secs(7)) => panic!("Expected timely response"),
+    };
+
+    match resp {
+        Message::Notice(Notice::SubscriptionClosed { source_id, message }) => {
+            assert_eq!(source_id, topic);
+            assert_eq!(message, Some(format!("Topic {} was deleted", topic)));
+        }
+        _ => panic!("Expected subscription closed notice"),
+    }
+
+    Ok(())
+}
+
 /// Tests that specifying a name for a Kafka source is allowed, and that
 /// all subscription requests and messages use the specified source name
 /// rather than the topic name
@@ -227,6 +303,9 @@ async fn test_named_kafka_source() -> anyhow::Result<()> {
         .send_json(&Command::Subscribe {
             source_id: topic.clone(),
             mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
         })
         .await?;
 
@@ -239,6 +318,9 @@ async fn test_named_kafka_source() -> anyhow::Result<()> {
         .send_json(&Command::Subscribe {
             source_id: "my-kafka-source".to_string(),
             mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
         })
         .await?;
 
@@ -297,6 +379,9 @@ async fn test_dynamic_config_source_removal() -> anyhow::Result<()> {
         .send_json(&Command::Subscribe {
             source_id: topic.clone(),
             mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
         })
         .await?;
 
@@ -310,8 +395,7 @@ async fn test_dynamic_config_source_removal() -> anyhow::Result<()> {
     config.as_file_mut().seek(SeekFrom::Start(0))?;
 
     config.as_file_mut().write_all(
-        format!(
-            r#"
+        r#"
         sources: []
         kafka:
             bootstrap_servers:
@@ -319,7 +403,6 @@ async fn test_dynamic_config_source_removal() -> anyhow::Result<()> {
         server:
             address: '127.0.0.1:8000'
         "#
-        )
         .as_bytes(),
     )?;
 
@@ -337,6 +420,9 @@ async fn test_dynamic_config_source_removal() -> anyhow::Result<()> {
         .send_json(&Command::Subscribe {
             source_id: topic.clone(),
             mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
         })
         .await?;
 
@@ -395,6 +481,9 @@ async fn test_intercept_hook() -> anyhow::Result<()> {
         .send_json(&Command::Subscribe {
             source_id: topic.clone(),
             mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
         })
         .await?;
 
@@ -428,7 +517,8 @@ async fn test_intercept_hook() -> anyhow::Result<()> {
                 }) => {
                     assert_eq!(source_id.as_ref(), topic);
 
-                    let payload: i32 = std::str::from_utf8(&payload.unwrap())?.parse()?;
+                    let payload: i32 =
+                        std::str::from_utf8(&common::decode_base64(payload))?.parse()?;
 
                     assert_eq!(payload % 2, 0);
 
@@ -450,3 +540,547 @@ async fn test_intercept_hook() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Tests that a Kafka source configured with a starting position replays
+/// events produced before Kiwi started
+#[tokio::test]
+async fn test_start_from_last_n() -> anyhow::Result<()> {
+    let bootstrap_server = BOOTSTRAP_SERVER.as_str();
+    let mut client = AdminClient::new(bootstrap_server)?;
+    let topic = client.create_random_topic(1).await?;
+
+    let producer = Producer::new(bootstrap_server)?;
+
+    for i in 0..10 {
+        let payload = format!("Message {}", i);
+        producer.send(&topic, &payload, &payload).await?;
+    }
+
+    let config = ConfigFile::from_str(
+        format!(
+            r#"
+        sources:
+            - type: kafka
+              topic: {topic}
+              start_from:
+                last_n: 5
+
+        kafka:
+            bootstrap_servers:
+                - '{bootstrap_server}'
+        server:
+            address: '127.0.0.1:8000'
+        "#
+        )
+        .as_str(),
+    )?;
+    let _kiwi = Process::new_with_args(&["--config", config.path_str()])?;
+
+    Healthcheck {
+        interval: Duration::from_millis(200),
+        attempts: 10,
+        url: "http://127.0.0.1:8000/health",
+    }
+    .run()
+    .await?;
+
+    let (mut ws_client, _) = WsClient::connect("ws://127.0.0.1:8000").await?;
+
+    ws_client
+        .send_json(&Command::Subscribe {
+            source_id: topic.clone(),
+            mode: SubscriptionMode::Pull,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
+        })
+        .await?;
+
+    assert!(matches!(
+        ws_client.recv_json().await?,
+        Message::CommandResponse(CommandResponse::SubscribeOk { source_id }) if source_id == topic
+    ));
+
+    ws_client
+        .send_json(&Command::Request {
+            source_id: topic.clone(),
+            n: 5,
+        })
+        .await?;
+
+    assert!(matches!(
+        ws_client.recv_json().await?,
+        Message::CommandResponse(CommandResponse::RequestOk { .. })
+    ));
+
+    for count in 5..10 {
+        match ws_client.recv_json::<Message>().await? {
+            Message::Result(kiwi::protocol::SourceResult::Kafka { payload, .. }) => {
+                assert_eq!(
+                    std::str::from_utf8(&common::decode_base64(payload))?,
+                    format!("Message {}", count)
+                );
+            }
+            msg => panic!("Expected Kafka message. Received {:?}", msg),
+        }
+    }
+
+    Ok(())
+}
+
+/// Tests that subscribers can replay historic events from a specific offset
+#[tokio::test]
+async fn test_subscribe_from_offsets() -> anyhow::Result<()> {
+    let bootstrap_server = BOOTSTRAP_SERVER.as_str();
+    let mut client = AdminClient::new(bootstrap_server)?;
+    let topic = client.create_random_topic(1).await?;
+
+    let producer = Producer::new(bootstrap_server)?;
+
+    for i in 0..5 {
+        let payload = format!("Message {}", i);
+        producer.send(&topic, &payload, &payload).await?;
+    }
+
+    let config = ConfigFile::from_str(
+        format!(
+            r#"
+        sources:
+            - type: kafka
+              topic: {topic}
+
+        kafka:
+            bootstrap_servers:
+                - '{bootstrap_server}'
+        server:
+            address: '127.0.0.1:8000'
+        "#
+        )
+        .as_str(),
+    )?;
+    let _kiwi = Process::new_with_args(&["--config", config.path_str()])?;
+
+    Healthcheck {
+        interval: Duration::from_millis(200),
+        attempts: 10,
+        url: "http://127.0.0.1:8000/health",
+    }
+    .run()
+    .await?;
+
+    let (mut ws_client, _) = WsClient::connect("ws://127.0.0.1:8000").await?;
+
+    ws_client
+        .send_json(&Command::Subscribe {
+            source_id: topic.clone(),
+            mode: SubscriptionMode::Push,
+            from_offsets: Some(BTreeMap::from([(0, 2)])),
+            lookback_ms: None,
+            partitions: None,
+        })
+        .await?;
+
+    assert!(matches!(
+        ws_client.recv_json().await?,
+        Message::CommandResponse(CommandResponse::SubscribeOk { source_id }) if source_id == topic
+    ));
+
+    for i in 5..7 {
+        let payload = format!("Message {}", i);
+        producer.send(&topic, &payload, &payload).await?;
+    }
+
+    // Replayed events are followed by live events without gaps or duplicates
+    for count in 2..7 {
+        match ws_client.recv_json::<Message>().await? {
+            Message::Result(kiwi::protocol::SourceResult::Kafka { payload, .. }) => {
+                assert_eq!(
+                    std::str::from_utf8(&common::decode_base64(payload))?,
+                    format!("Message {}", count)
+                );
+            }
+            msg => panic!("Expected Kafka message. Received {:?}", msg),
+        }
+    }
+
+    Ok(())
+}
+
+/// Tests that a Kafka source in a consumer group can route subscriptions to
+/// the partitions assigned to it
+#[tokio::test]
+async fn test_consumer_group_partition_routing() -> anyhow::Result<()> {
+    let bootstrap_server = BOOTSTRAP_SERVER.as_str();
+    let mut client = AdminClient::new(bootstrap_server)?;
+    let topic = client.create_random_topic(2).await?;
+    let group = format!("{}-group", topic);
+
+    let config = ConfigFile::from_str(
+        format!(
+            r#"
+        sources:
+            - type: kafka
+              topic: {topic}
+              consumer_group: {group}
+              start_from: earliest
+
+        kafka:
+            bootstrap_servers:
+                - '{bootstrap_server}'
+        server:
+            address: '127.0.0.1:8000'
+        "#
+        )
+        .as_str(),
+    )?;
+    let _kiwi = Process::new_with_args(&["--config", config.path_str()])?;
+
+    Healthcheck {
+        interval: Duration::from_millis(200),
+        attempts: 10,
+        url: "http://127.0.0.1:8000/health",
+    }
+    .run()
+    .await?;
+
+    let (mut ws_client, _) = WsClient::connect("ws://127.0.0.1:8000").await?;
+
+    // Partitions are only routable once the group has assigned them
+    let mut subscribed = false;
+
+    for _ in 0..50 {
+        ws_client
+            .send_json(&Command::Subscribe {
+                source_id: topic.clone(),
+                mode: SubscriptionMode::Push,
+                from_offsets: None,
+                lookback_ms: None,
+                partitions: Some(BTreeSet::from([1])),
+            })
+            .await?;
+
+        match ws_client.recv_json().await? {
+            Message::CommandResponse(CommandResponse::SubscribeOk { .. }) => {
+                subscribed = true;
+                break;
+            }
+            Message::CommandResponse(CommandResponse::SubscribeError { .. }) => {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+            msg => panic!("Expected subscribe response. Received {:?}", msg),
+        }
+    }
+
+    assert!(subscribed, "Partition 1 was never assigned");
+
+    let producer = Producer::new(bootstrap_server)?;
+
+    for i in 0..4 {
+        let payload = format!("Message {}", i);
+        producer
+            .send_to_partition(&topic, i % 2, &payload, &payload)
+            .await?;
+    }
+
+    for count in [1, 3] {
+        match ws_client.recv_json::<Message>().await? {
+            Message::Result(kiwi::protocol::SourceResult::Kafka {
+                payload, partition, ..
+            }) => {
+                assert_eq!(partition, 1);
+                assert_eq!(
+                    std::str::from_utf8(&common::decode_base64(payload))?,
+                    format!("Message {}", count)
+                );
+            }
+            msg => panic!("Expected Kafka message. Received {:?}", msg),
+        }
+    }
+
+    Ok(())
+}
+
+/// Tests that a Kafka source configured with a topic pattern consumes every
+/// matching topic, including those created after the source
+#[tokio::test]
+async fn test_topic_pattern_discovers_new_topics() -> anyhow::Result<()> {
+    let bootstrap_server = BOOTSTRAP_SERVER.as_str();
+    let mut client = AdminClient::new(bootstrap_server)?;
+    let prefix = format!("test-{}", nanoid::nanoid!());
+    let existing = format!("{}.tenant-1", prefix);
+    let created = format!("{}.tenant-2", prefix);
+
+    client.create_named_topic(&existing, 1).await?;
+
+    let config = ConfigFile::from_str(
+        format!(
+            r#"
+        sources:
+            - type: kafka
+              id: tenants
+              topic: '^{prefix}\.tenant-.*'
+              start_from: earliest
+
+        kafka:
+            bootstrap_servers:
+                - '{bootstrap_server}'
+            partition_discovery_interval_ms: 1000
+        server:
+            address: '127.0.0.1:8000'
+        "#
+        )
+        .as_str(),
+    )?;
+    let _kiwi = Process::new_with_args(&["--config", config.path_str()])?;
+
+    Healthcheck {
+        interval: Duration::from_millis(200),
+        attempts: 10,
+        url: "http://127.0.0.1:8000/health",
+    }
+    .run()
+    .await?;
+
+    let (mut ws_client, _) = WsClient::connect("ws://127.0.0.1:8000").await?;
+
+    ws_client
+        .send_json(&Command::Subscribe {
+            source_id: "tenants".into(),
+            mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
+        })
+        .await?;
+
+    let resp: Message = ws_client.recv_json().await?;
+
+    assert!(matches!(
+        resp,
+        Message::CommandResponse(CommandResponse::SubscribeOk { source_id }) if source_id == "tenants"
+    ));
+
+    let producer = Producer::new(bootstrap_server)?;
+
+    producer.send(&existing, "key", "existing").await?;
+
+    // Newly matching topics are consumed from `start_from` once discovered
+    client.create_named_topic(&created, 1).await?;
+    producer.send(&created, "key", "created").await?;
+
+    for expected in [existing, created] {
+        match ws_client.recv_json::<Message>().await? {
+            Message::Result(kiwi::protocol::SourceResult::Kafka {
+                source_id, topic, ..
+            }) => {
+                assert_eq!(source_id, "tenants");
+                assert_eq!(topic, expected);
+            }
+            msg => panic!("Expected Kafka message. Received {:?}", msg),
+        }
+    }
+
+    Ok(())
+}
+
+/// Tests that topics discovered after a pattern source is created start from
+/// the configured position, which defaults to the high watermark
+#[tokio::test]
+async fn test_topic_pattern_discovered_topics_start_from_latest() -> anyhow::Result<()> {
+    let bootstrap_server = BOOTSTRAP_SERVER.as_str();
+    let mut client = AdminClient::new(bootstrap_server)?;
+    let prefix = format!("test-{}", nanoid::nanoid!());
+    let created = format!("{}.tenant-1", prefix);
+
+    let config = ConfigFile::from_str(
+        format!(
+            r#"
+        sources:
+            - type: kafka
+              id: tenants
+              topic: '^{prefix}\.tenant-.*'
+
+        kafka:
+            bootstrap_servers:
+                - '{bootstrap_server}'
+            partition_discovery_interval_ms: 2000
+        server:
+            address: '127.0.0.1:8000'
+        "#
+        )
+        .as_str(),
+    )?;
+    let _kiwi = Process::new_with_args(&["--config", config.path_str()])?;
+
+    Healthcheck {
+        interval: Duration::from_millis(200),
+        attempts: 10,
+        url: "http://127.0.0.1:8000/health",
+    }
+    .run()
+    .await?;
+
+    let (mut ws_client, _) = WsClient::connect("ws://127.0.0.1:8000").await?;
+
+    ws_client
+        .send_json(&Command::Subscribe {
+            source_id: "tenants".into(),
+            mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
+        })
+        .await?;
+
+    let resp: Message = ws_client.recv_json().await?;
+
+    assert!(matches!(
+        resp,
+        Message::CommandResponse(CommandResponse::SubscribeOk { source_id }) if source_id == "tenants"
+    ));
+
+    let producer = Producer::new(bootstrap_server)?;
+
+    // Events produced before the topic is discovered are below the high watermark
+    // the topic is consumed from
+    client.create_named_topic(&created, 1).await?;
+    producer.send(&created, "key", "before").await?;
+
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    producer.send(&created, "key", "after").await?;
+
+    match ws_client.recv_json::<Message>().await? {
+        Message::Result(kiwi::protocol::SourceResult::Kafka { topic, payload, .. }) => {
+            assert_eq!(topic, created);
+            assert_eq!(
+                std::str::from_utf8(&common::decode_base64(payload))?,
+                "after"
+            );
+        }
+        msg => panic!("Expected Kafka message. Received {:?}", msg),
+    }
+
+    Ok(())
+}
+
+/// Tests that a Kafka source in a consumer group consumes the concrete topics
+/// matching its pattern
+#[tokio::test]
+async fn test_consumer_group_topic_pattern() -> anyhow::Result<()> {
+    let bootstrap_server = BOOTSTRAP_SERVER.as_str();
+    let mut client = AdminClient::new(bootstrap_server)?;
+    let prefix = format!("test-{}", nanoid::nanoid!());
+    let topics = [
+        format!("{}.tenant-1", prefix),
+        format!("{}.tenant-2", prefix),
+    ];
+
+    for topic in topics.iter() {
+        client.create_named_topic(topic, 1).await?;
+    }
+
+    let config = ConfigFile::from_str(
+        format!(
+            r#"
+        sources:
+            - type: kafka
+              id: tenants
+              topic: '^{prefix}\.tenant-.*'
+              consumer_group: {prefix}-group
+              start_from: earliest
+
+        kafka:
+            bootstrap_servers:
+                - '{bootstrap_server}'
+        server:
+            address: '127.0.0.1:8000'
+        "#
+        )
+        .as_str(),
+    )?;
+    let _kiwi = Process::new_with_args(&["--config", config.path_str()])?;
+
+    Healthcheck {
+        interval: Duration::from_millis(200),
+        attempts: 10,
+        url: "http://127.0.0.1:8000/health",
+    }
+    .run()
+    .await?;
+
+    let (mut ws_client, _) = WsClient::connect("ws://127.0.0.1:8000").await?;
+
+    ws_client
+        .send_json(&Command::Subscribe {
+            source_id: "tenants".into(),
+            mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
+        })
+        .await?;
+
+    let resp: Message = ws_client.recv_json().await?;
+
+    assert!(matches!(
+        resp,
+        Message::CommandResponse(CommandResponse::SubscribeOk { source_id }) if source_id == "tenants"
+    ));
+
+    let producer = Producer::new(bootstrap_server)?;
+
+    for topic in topics.iter() {
+        producer.send(topic, "key", topic).await?;
+    }
+
+    let mut received = BTreeSet::new();
+
+    for _ in 0..topics.len() {
+        match ws_client.recv_json::<Message>().await? {
+            Message::Result(kiwi::protocol::SourceResult::Kafka { topic, .. }) => {
+                received.insert(topic);
+            }
+            msg => panic!("Expected Kafka message. Received {:?}", msg),
+        }
+    }
+
+    assert_eq!(received, BTreeSet::from(topics));
+
+    Ok(())
+}
+
+/// Tests that Kafka clients can be created with the OAUTHBEARER mechanism, whose
+/// OIDC token retrieval is only available if librdkafka is built with libcurl
+#[test]
+fn test_creates_clients_with_oauthbearer_sasl() -> anyhow::Result<()> {
+    let config = serde_yaml::from_str::<kiwi::config::Config>(
+        r#"
+        sources: []
+        server:
+            address: '127.0.0.1:8000'
+        kafka:
+            bootstrap_servers:
+                - 'localhost:9092'
+            security:
+                protocol: sasl_ssl
+                sasl:
+                    mechanism: OAUTHBEARER
+                    client_id: kiwi
+                    client_secret: secret
+                    token_endpoint_url: https://auth.example.com/token
+        "#,
+    )?;
+
+    let mut client_config = rdkafka::ClientConfig::new();
+    client_config.extend(
+        config
+            .kafka
+            .expect("Kafka is configured")
+            .client_properties()?,
+    );
+
+    let _: rdkafka::consumer::BaseConsumer = client_config.set("group.id", "kiwi").create()?;
+
+    Ok(())
+}
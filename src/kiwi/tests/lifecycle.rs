@@ -10,14 +10,11 @@ static GRACE_PERIOD_MS: u64 = 500;
 #[tokio::test]
 async fn test_shuts_down_sigint() -> anyhow::Result<()> {
     let config = ConfigFile::from_str(
-        format!(
-            r#"
+        r#"
         sources: []
         server:
             address: '127.0.0.1:8000'
-        "#
-        )
-        .as_str(),
+        "#,
     )?;
 
     let mut kiwi = Process::new_with_args(&["--config", config.path_str()])?;
@@ -36,14 +33,11 @@ async fn test_shuts_down_sigint() -> anyhow::Result<()> {
 #[tokio::test]
 async fn test_shuts_down_sigterm() -> anyhow::Result<()> {
     let config = ConfigFile::from_str(
-        format!(
-            r#"
+        r#"
         sources: []
         server:
             address: '127.0.0.1:8000'
-        "#
-        )
-        .as_str(),
+        "#,
     )?;
 
     let mut kiwi = Process::new_with_args(&["--config", config.path_str()])?;
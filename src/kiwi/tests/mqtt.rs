@@ -0,0 +1,100 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::kiwi::start_kiwi;
+use common::mqtt::Client as MqttClient;
+use common::ws::subscribe;
+use kiwi::protocol::{Message, PayloadEncoding, SourceResult};
+use once_cell::sync::Lazy;
+
+static MQTT_URL: Lazy<String> =
+    Lazy::new(|| std::env::var("MQTT_URL").expect("MQTT_URL env var is required"));
+
+/// Tests that subscribers receive messages published to topics matching the
+/// filters of an MQTT source, delivered at the QoS level of each filter
+#[tokio::test]
+async fn test_receives_messages_mqtt_source() -> anyhow::Result<()> {
+    let mqtt_url = MQTT_URL.as_str();
+    let prefix = format!("test-{}", nanoid::nanoid!());
+    let (_kiwi, _config) = start_kiwi(&format!(
+        r#"
+            - type: mqtt
+              id: telemetry
+              url: '{mqtt_url}'
+              topics:
+                - filter: '{prefix}/+/telemetry'
+                  qos: 1
+                - filter: '{prefix}/alerts/#'
+              payload_encoding: json
+        "#
+    ))
+    .await?;
+
+    let mut ws_client = subscribe("telemetry").await?;
+    let mut mqtt = MqttClient::connect(mqtt_url).await?;
+
+    // The source subscribes to the broker asynchronously, so keep publishing
+    // until the first message is received
+    let telemetry_topic = format!("{}/device-1/telemetry", prefix);
+    let publisher = tokio::spawn(async move {
+        while mqtt
+            .publish(&telemetry_topic, r#"{"temp":21}"#)
+            .await
+            .is_ok()
+        {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    });
+
+    let msg: Message =
+        tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await??;
+    publisher.abort();
+
+    match msg {
+        Message::Result(SourceResult::Mqtt {
+            source_id,
+            topic,
+            payload,
+            payload_encoding,
+            qos,
+            retain,
+        }) => {
+            assert_eq!(source_id, "telemetry");
+            assert_eq!(topic, format!("{}/device-1/telemetry", prefix));
+            assert_eq!(payload, serde_json::json!({ "temp": 21 }));
+            assert_eq!(payload_encoding, PayloadEncoding::Json);
+            assert_eq!(qos, 1);
+            assert!(!retain);
+        }
+        _ => panic!("Expected MQTT message. Received {:?}", msg),
+    }
+
+    // Messages matching a filter subscribed to with QoS 0 are downgraded
+    let mut mqtt = MqttClient::connect(mqtt_url).await?;
+    let alert_topic = format!("{}/alerts/device-1/overheating", prefix);
+    mqtt.publish(&alert_topic, r#"{"temp":90}"#).await?;
+
+    loop {
+        let msg: Message =
+            tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await??;
+
+        match msg {
+            Message::Result(SourceResult::Mqtt {
+                topic,
+                payload,
+                qos,
+                ..
+            }) if topic == alert_topic => {
+                assert_eq!(payload, serde_json::json!({ "temp": 90 }));
+                assert_eq!(qos, 0);
+                break;
+            }
+            // Telemetry published before the publisher was stopped
+            Message::Result(SourceResult::Mqtt { .. }) => continue,
+            _ => panic!("Expected MQTT message. Received {:?}", msg),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,141 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::kiwi::start_kiwi;
+use common::nats::Client as NatsClient;
+use common::ws::subscribe;
+use kiwi::protocol::{Message, NatsHeader, PayloadEncoding, SourceResult};
+use once_cell::sync::Lazy;
+
+static NATS_URL: Lazy<String> =
+    Lazy::new(|| std::env::var("NATS_URL").expect("NATS_URL env var is required"));
+
+/// Tests that subscribers receive messages published to subjects matching the
+/// wildcard subject of a NATS source, along with their headers
+#[tokio::test]
+async fn test_receives_messages_nats_source() -> anyhow::Result<()> {
+    let nats_url = NATS_URL.as_str();
+    let prefix = format!("test-{}", nanoid::nanoid!());
+    let (_kiwi, _config) = start_kiwi(&format!(
+        r#"
+            - type: nats
+              id: orders
+              url: '{nats_url}'
+              subject: '{prefix}.>'
+              payload_encoding: utf8
+        "#
+    ))
+    .await?;
+
+    let mut ws_client = subscribe("orders").await?;
+    let mut nats = NatsClient::connect(nats_url).await?;
+
+    // The source subscribes to NATS asynchronously, so keep publishing until
+    // the first message is received
+    let subject = format!("{}.eu.created", prefix);
+    let publisher = tokio::spawn(async move {
+        while nats
+            .publish(&subject, &[("Trace", "abc")], "hello")
+            .await
+            .is_ok()
+        {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    });
+
+    let msg: Message =
+        tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await??;
+    publisher.abort();
+
+    match msg {
+        Message::Result(SourceResult::Nats {
+            source_id,
+            subject,
+            headers,
+            payload,
+            payload_encoding,
+            sequence,
+            ..
+        }) => {
+            assert_eq!(source_id, "orders");
+            assert_eq!(subject, format!("{}.eu.created", prefix));
+            assert_eq!(
+                headers,
+                vec![NatsHeader {
+                    key: "Trace".into(),
+                    value: "abc".into(),
+                }]
+            );
+            assert_eq!(payload, serde_json::json!("hello"));
+            assert_eq!(payload_encoding, PayloadEncoding::Utf8);
+            assert_eq!(sequence, None);
+        }
+        _ => panic!("Expected NATS message. Received {:?}", msg),
+    }
+
+    Ok(())
+}
+
+/// Tests that a NATS source consuming a JetStream stream delivers messages
+/// stored before it was created according to its deliver policy
+#[tokio::test]
+async fn test_receives_messages_nats_jetstream_source() -> anyhow::Result<()> {
+    let nats_url = NATS_URL.as_str();
+    let stream = format!("TEST_{}", nanoid::nanoid!(10, &nanoid::alphabet::SAFE[2..]));
+    let subject = format!("{}.events", stream.to_lowercase());
+    let mut nats = NatsClient::connect(nats_url).await?;
+
+    nats.create_stream(&stream, &subject).await?;
+
+    for i in 1..=3 {
+        nats.publish(&subject, &[], &format!(r#"{{"id":{}}}"#, i))
+            .await?;
+    }
+
+    let (_kiwi, _config) = start_kiwi(&format!(
+        r#"
+            - type: nats
+              id: events
+              url: '{nats_url}'
+              subject: '{subject}'
+              payload_encoding: json
+              jetstream:
+                stream: {stream}
+                deliver_policy:
+                  start_sequence: 2
+        "#
+    ))
+    .await?;
+
+    let mut ws_client = subscribe("events").await?;
+
+    // Stored messages may be delivered before the subscription is established,
+    // so only messages published afterwards are asserted on
+    nats.publish(&subject, &[], r#"{"id":4}"#).await?;
+
+    loop {
+        let msg: Message =
+            tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await??;
+
+        match msg {
+            Message::Result(SourceResult::Nats {
+                payload,
+                sequence,
+                timestamp,
+                ..
+            }) => {
+                assert!(sequence >= Some(2));
+                assert!(timestamp.is_some());
+
+                if sequence == Some(4) {
+                    assert_eq!(payload, serde_json::json!({ "id": 4 }));
+                    break;
+                }
+            }
+            _ => panic!("Expected NATS message. Received {:?}", msg),
+        }
+    }
+
+    Ok(())
+}
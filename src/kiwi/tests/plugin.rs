@@ -0,0 +1,97 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::kiwi::{ConfigFile, Process};
+use common::ws::Client as WsClient;
+use kiwi::protocol::{
+    Command, CommandResponse, Message, Notice, PayloadEncoding, SourceResult, SubscriptionMode,
+};
+
+use crate::common::healthcheck::Healthcheck;
+
+const PLUGIN_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/wasm/counter-source.wasm"
+);
+
+/// Tests that events emitted by a source plugin are delivered to subscribers,
+/// and that the subscription is closed once the plugin reports the source is done
+#[tokio::test]
+async fn test_plugin_source() -> anyhow::Result<()> {
+    let config = ConfigFile::from_str(
+        format!(
+            r#"
+        sources:
+            - type: plugin
+              id: counter
+              path: {PLUGIN_PATH}
+              lazy: true
+              payload_encoding: json
+        server:
+            address: '127.0.0.1:8000'
+        "#
+        )
+        .as_str(),
+    )?;
+
+    let _kiwi = Process::new_with_args(&["--config", config.path_str()])?;
+
+    Healthcheck {
+        interval: Duration::from_millis(200),
+        attempts: 10,
+        url: "http://127.0.0.1:8000/health",
+    }
+    .run()
+    .await?;
+
+    let (mut ws_client, _) = WsClient::connect("ws://127.0.0.1:8000").await?;
+
+    ws_client
+        .send_json(&Command::Subscribe {
+            source_id: "counter".into(),
+            mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
+        })
+        .await?;
+
+    let resp: Message = ws_client.recv_json().await?;
+
+    assert!(matches!(
+        resp,
+        Message::CommandResponse(CommandResponse::SubscribeOk { source_id }) if source_id == "counter"
+    ));
+
+    for n in 1..=3 {
+        let msg: Message =
+            tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await??;
+
+        match msg {
+            Message::Result(SourceResult::Custom {
+                source_id,
+                kind,
+                payload,
+                payload_encoding,
+                ..
+            }) => {
+                assert_eq!(source_id, "counter");
+                assert_eq!(kind, "plugin");
+                assert_eq!(payload, serde_json::json!(n));
+                assert_eq!(payload_encoding, PayloadEncoding::Json);
+            }
+            msg => panic!("Expected plugin result. Received {:?}", msg),
+        }
+    }
+
+    let msg: Message =
+        tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await??;
+
+    assert!(matches!(
+        msg,
+        Message::Notice(Notice::SubscriptionClosed { source_id, .. }) if source_id == "counter"
+    ));
+
+    Ok(())
+}
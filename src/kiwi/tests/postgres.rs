@@ -0,0 +1,127 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::kiwi::start_kiwi;
+use common::postgres::Client as PostgresClient;
+use common::ws::subscribe;
+use kiwi::protocol::{Message, PayloadEncoding, SourceResult};
+use once_cell::sync::Lazy;
+
+/// URL of a server that requires password authentication for connections over
+/// TCP (e.g. `md5` in `pg_hba.conf`), as a user permitted to create roles
+static POSTGRES_URL: Lazy<String> =
+    Lazy::new(|| std::env::var("POSTGRES_URL").expect("POSTGRES_URL env var is required"));
+
+/// Starts Kiwi with a Postgres source connecting to `source_url`, and returns the
+/// first notification received by a subscriber
+async fn receive_notification(source_url: &str, channel: &str) -> anyhow::Result<Message> {
+    let (_kiwi, _config) = start_kiwi(&format!(
+        r#"
+            - type: postgres
+              id: orders
+              url: '{source_url}'
+              channels:
+                - '{channel}'
+              payload_encoding: json
+        "#
+    ))
+    .await?;
+
+    let mut ws_client = subscribe("orders").await?;
+    let mut postgres = PostgresClient::connect(POSTGRES_URL.as_str()).await?;
+
+    // The source listens on the channel asynchronously, so keep notifying until
+    // the first notification is received
+    let notified_channel = channel.to_string();
+    let notifier = tokio::spawn(async move {
+        while postgres
+            .notify(&notified_channel, r#"{"id":1}"#)
+            .await
+            .is_ok()
+        {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    });
+
+    let msg = tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await;
+    notifier.abort();
+
+    msg?
+}
+
+/// Tests that subscribers receive notifications sent to the channels a
+/// Postgres source listens on
+#[tokio::test]
+async fn test_receives_notifications_postgres_source() -> anyhow::Result<()> {
+    let channel = format!("Test-{}", nanoid::nanoid!());
+    let msg = receive_notification(POSTGRES_URL.as_str(), &channel).await?;
+
+    match msg {
+        Message::Result(SourceResult::Postgres {
+            source_id,
+            channel: notified_channel,
+            payload,
+            payload_encoding,
+            process_id,
+        }) => {
+            assert_eq!(source_id, "orders");
+            // Channels are case-sensitive
+            assert_eq!(notified_channel, channel);
+            assert_eq!(payload, serde_json::json!({ "id": 1 }));
+            assert_eq!(payload_encoding, PayloadEncoding::Json);
+            assert!(process_id > 0);
+        }
+        _ => panic!("Expected Postgres notification. Received {:?}", msg),
+    }
+
+    Ok(())
+}
+
+/// Tests that a Postgres source authenticates as users whose passwords are
+/// stored as SCRAM-SHA-256 verifiers and as MD5 hashes, for which the server
+/// requests the respective authentication method, and that wrong passwords are
+/// rejected
+#[tokio::test]
+async fn test_authenticates_postgres_source() -> anyhow::Result<()> {
+    let mut postgres = PostgresClient::connect(POSTGRES_URL.as_str()).await?;
+
+    for encryption in ["scram-sha-256", "md5"] {
+        let user = format!("kiwi-{}", nanoid::nanoid!());
+        postgres
+            .execute(&format!(
+                "SET password_encryption = '{encryption}'; CREATE ROLE \"{user}\" LOGIN PASSWORD 'p@ss'"
+            ))
+            .await?;
+
+        let mut url = url::Url::parse(POSTGRES_URL.as_str())?;
+        url.set_username(&user).expect("URL must have a host");
+        url.set_password(Some("p%40ss"))
+            .expect("URL must have a host");
+
+        let channel = format!("Test-{}", nanoid::nanoid!());
+        let msg = receive_notification(url.as_str(), &channel).await?;
+
+        assert!(
+            matches!(msg, Message::Result(SourceResult::Postgres { .. })),
+            "Expected Postgres notification for {} password. Received {:?}",
+            encryption,
+            msg
+        );
+
+        url.set_password(Some("wrong"))
+            .expect("URL must have a host");
+
+        // The source never starts listening, so no notification is received
+        let msg = receive_notification(url.as_str(), &channel).await;
+
+        assert!(
+            !matches!(msg, Ok(Message::Result(SourceResult::Postgres { .. }))),
+            "Expected {} authentication with a wrong password to fail. Received {:?}",
+            encryption,
+            msg
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,151 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::kiwi::start_kiwi;
+use common::redis::Client as RedisClient;
+use common::ws::subscribe;
+use kiwi::protocol::{Message, PayloadEncoding, RedisField, SourceResult};
+use once_cell::sync::Lazy;
+
+static REDIS_URL: Lazy<String> =
+    Lazy::new(|| std::env::var("REDIS_URL").expect("REDIS_URL env var is required"));
+
+/// Tests that subscribers receive messages published to the channels and
+/// patterns of a Redis pub/sub source
+#[tokio::test]
+async fn test_receives_messages_redis_pubsub_source() -> anyhow::Result<()> {
+    let redis_url = REDIS_URL.as_str();
+    let channel = format!("test-{}", nanoid::nanoid!());
+    let (_kiwi, _config) = start_kiwi(&format!(
+        r#"
+            - type: redis
+              id: notifications
+              url: '{redis_url}'
+              channels:
+                - {channel}
+              patterns:
+                - '{channel}.*'
+              payload_encoding: utf8
+        "#
+    ))
+    .await?;
+
+    let mut ws_client = subscribe("notifications").await?;
+    let mut redis = RedisClient::connect(redis_url).await?;
+
+    // The source subscribes to Redis asynchronously, so wait until it receives
+    // published messages
+    let mut attempts = 0;
+    while redis
+        .publish(&format!("{}.ready", channel), "ready")
+        .await?
+        == 0
+    {
+        attempts += 1;
+        assert!(attempts < 50, "Redis source did not subscribe");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    redis.publish(&channel, "hello").await?;
+
+    let messages = [
+        (
+            format!("{}.ready", channel),
+            Some(format!("{}.*", channel)),
+            "ready",
+        ),
+        (channel.clone(), None, "hello"),
+    ];
+
+    for (expected_channel, expected_pattern, expected_payload) in messages {
+        let msg: Message = ws_client.recv_json().await?;
+
+        match msg {
+            Message::Result(SourceResult::Redis {
+                source_id,
+                channel,
+                pattern,
+                payload,
+                payload_encoding,
+                ..
+            }) => {
+                assert_eq!(source_id, "notifications");
+                assert_eq!(channel, expected_channel);
+                assert_eq!(pattern, expected_pattern);
+                assert_eq!(payload, Some(serde_json::json!(expected_payload)));
+                assert_eq!(payload_encoding, PayloadEncoding::Utf8);
+            }
+            _ => panic!("Expected Redis message. Received {:?}", msg),
+        }
+    }
+
+    Ok(())
+}
+
+/// Tests that subscribers receive entries appended to the streams of a Redis
+/// streams source, starting after the configured entry ID
+#[tokio::test]
+async fn test_receives_entries_redis_streams_source() -> anyhow::Result<()> {
+    let redis_url = REDIS_URL.as_str();
+    let stream = format!("test-{}", nanoid::nanoid!());
+    let mut redis = RedisClient::connect(redis_url).await?;
+
+    // Entries appended before the source is created are read as the source
+    // starts from the beginning of the stream
+    let first_id = redis.xadd(&stream, &[("order", r#"{"id":1}"#)]).await?;
+
+    let (_kiwi, _config) = start_kiwi(&format!(
+        r#"
+            - type: redis
+              id: orders
+              url: '{redis_url}'
+              streams:
+                - {stream}
+              start_id: '0'
+              payload_encoding: json
+        "#
+    ))
+    .await?;
+
+    let mut ws_client = subscribe("orders").await?;
+
+    // The first entry may be read before the subscription is established, so
+    // only entries appended afterwards are asserted on
+    let second_id = redis.xadd(&stream, &[("order", r#"{"id":2}"#)]).await?;
+
+    loop {
+        let msg: Message = ws_client.recv_json().await?;
+
+        match msg {
+            Message::Result(SourceResult::Redis {
+                source_id,
+                channel,
+                entry_id,
+                fields,
+                ..
+            }) => {
+                assert_eq!(source_id, "orders");
+                assert_eq!(channel, stream);
+
+                if entry_id.as_ref() == Some(&first_id) {
+                    continue;
+                }
+
+                assert_eq!(entry_id, Some(second_id));
+                assert_eq!(
+                    fields,
+                    vec![RedisField {
+                        key: "order".into(),
+                        value: serde_json::json!({ "id": 2 }),
+                    }]
+                );
+
+                break;
+            }
+            _ => panic!("Expected Redis entry. Received {:?}", msg),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::kiwi::start_kiwi;
+use common::relay::Upstream;
+use common::ws::{subscribe, Client as WsClient};
+use kiwi::protocol::{Message, Notice, PayloadEncoding, SourceResult};
+
+/// Sends a chunk to the upstream until the source relays an event, since the
+/// source connects to the upstream asynchronously
+async fn send_until_received(
+    upstream: &Upstream,
+    ws_client: &mut WsClient,
+    chunk: &str,
+) -> anyhow::Result<Message> {
+    let recv = ws_client.recv_json::<Message>();
+    futures::pin_mut!(recv);
+
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            upstream.send(chunk);
+
+            tokio::select! {
+                msg = &mut recv => return msg,
+                _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+            }
+        }
+    })
+    .await?
+}
+
+/// Tests that server-sent events are relayed to subscribers, and that the
+/// stream is resumed from the last event ID after the upstream disconnects
+#[tokio::test]
+async fn test_relays_server_sent_events_relay_source() -> anyhow::Result<()> {
+    let upstream = Upstream::start().await?;
+    let (_kiwi, _config) = start_kiwi(&format!(
+        r#"
+            - type: relay
+              id: prices
+              url: 'http://{}/prices'
+              payload_encoding: json
+        "#,
+        upstream.addr()
+    ))
+    .await?;
+
+    let mut ws_client = subscribe("prices").await?;
+
+    let msg = send_until_received(
+        &upstream,
+        &mut ws_client,
+        "event: price\nid: 1\ndata: {\"bid\":1.5}\n\n",
+    )
+    .await?;
+
+    match msg {
+        Message::Result(SourceResult::Custom {
+            source_id,
+            kind,
+            metadata,
+            payload,
+            payload_encoding,
+            ..
+        }) => {
+            assert_eq!(source_id, "prices");
+            assert_eq!(kind, "relay");
+            assert_eq!(payload, serde_json::json!({ "bid": 1.5 }));
+            assert_eq!(payload_encoding, PayloadEncoding::Json);
+            assert_eq!(metadata.get("event").map(String::as_str), Some("price"));
+            assert_eq!(metadata.get("eventId").map(String::as_str), Some("1"));
+        }
+        _ => panic!("Expected relay event. Received {:?}", msg),
+    }
+
+    upstream.disconnect();
+
+    // Drain events sent before the upstream disconnected
+    loop {
+        let msg = send_until_received(&upstream, &mut ws_client, "id: 2\ndata: {}\n\n").await?;
+
+        match msg {
+            Message::Result(SourceResult::Custom { metadata, .. })
+                if metadata.get("eventId").map(String::as_str) == Some("2") =>
+            {
+                assert_eq!(metadata.get("event").map(String::as_str), Some("message"));
+                break;
+            }
+            Message::Result(SourceResult::Custom { .. }) => continue,
+            // The source is reported as degraded until it reconnects
+            Message::Notice(Notice::SourceStatus { .. }) => continue,
+            _ => panic!("Expected relay event. Received {:?}", msg),
+        }
+    }
+
+    let last_event_ids = upstream.last_event_ids();
+    assert_eq!(last_event_ids.first(), Some(&None));
+    assert!(last_event_ids.contains(&Some("1".to_string())));
+
+    Ok(())
+}
+
+/// Tests that WebSocket messages are relayed to subscribers, after sending the
+/// configured messages to the upstream
+#[tokio::test]
+async fn test_relays_websocket_messages_relay_source() -> anyhow::Result<()> {
+    let upstream = Upstream::start().await?;
+    let (_kiwi, _config) = start_kiwi(&format!(
+        r#"
+            - type: relay
+              id: trades
+              url: 'ws://{}/trades'
+              messages:
+                - '{{"op":"subscribe"}}'
+              payload_encoding: utf8
+        "#,
+        upstream.addr()
+    ))
+    .await?;
+
+    let mut ws_client = subscribe("trades").await?;
+
+    let msg = send_until_received(&upstream, &mut ws_client, "trade").await?;
+
+    match msg {
+        Message::Result(SourceResult::Custom {
+            source_id,
+            kind,
+            metadata,
+            payload,
+            payload_encoding,
+            ..
+        }) => {
+            assert_eq!(source_id, "trades");
+            assert_eq!(kind, "relay");
+            assert_eq!(payload, serde_json::json!("trade"));
+            assert_eq!(payload_encoding, PayloadEncoding::Utf8);
+            assert!(metadata.is_empty());
+        }
+        _ => panic!("Expected relay event. Received {:?}", msg),
+    }
+
+    assert_eq!(
+        upstream.messages(),
+        vec![r#"{"op":"subscribe"}"#.to_string()]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,305 @@
+pub mod common;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use common::ws::Client as WsClient;
+use kiwi::config::{Config, CustomSourceConfig, SourceType};
+use kiwi::hook::authenticate::types::{Authenticate, Outcome};
+use kiwi::hook::intercept::types::{
+    Action, ConnectionCtx, Context, CustomEventCtx, EventCtx, Intercept, TransformedPayload,
+    WebSocketConnectionCtx,
+};
+use kiwi::protocol::{
+    Command, CommandResponse, Message, PayloadEncoding, SourceResult, SubscriptionMode,
+};
+use kiwi::server::KiwiServer;
+use kiwi::source::http::HttpSource;
+use kiwi::source::registry::SourceRegistry;
+
+/// Accepts connections that present the API key as a query parameter
+struct ApiKey;
+
+#[async_trait]
+impl Authenticate for ApiKey {
+    async fn authenticate(&self, request: http::Request<()>) -> anyhow::Result<Outcome> {
+        if request.uri().query() == Some("x-api-key=12345") {
+            Ok(Outcome::Authenticate)
+        } else {
+            Ok(Outcome::Reject)
+        }
+    }
+}
+
+/// Uppercases the payloads of HTTP events
+struct Uppercase;
+
+#[async_trait]
+impl Intercept for Uppercase {
+    async fn intercept(&self, context: &Context) -> anyhow::Result<Action> {
+        match &context.event {
+            EventCtx::Custom(event) if event.kind == "http" => Ok(Action::Transform(
+                TransformedPayload::Custom(event.payload.to_ascii_uppercase()),
+            )),
+            _ => Ok(Action::Forward),
+        }
+    }
+}
+
+fn config(sources: Vec<SourceType>) -> Config {
+    let mut config = serde_yaml::from_str::<Config>(
+        r#"
+        sources: []
+        server:
+            address: '127.0.0.1:0'
+        "#,
+    )
+    .unwrap();
+    config.sources = sources;
+
+    config
+}
+
+fn http_source(id: &str) -> SourceType {
+    SourceType::Http {
+        id: id.to_string(),
+        tokens: vec!["s3cr3t".into()],
+        max_body_bytes: 1024,
+        payload_encoding: PayloadEncoding::Utf8,
+        channel_capacity: None,
+    }
+}
+
+async fn subscribe(server: &KiwiServer, source_id: &str) -> anyhow::Result<WsClient> {
+    let (mut ws_client, _) =
+        WsClient::connect(&format!("ws://{}/?x-api-key=12345", server.local_addr())).await?;
+
+    ws_client
+        .send_json(&Command::Subscribe {
+            source_id: source_id.to_string(),
+            mode: SubscriptionMode::Push,
+            from_offsets: None,
+            lookback_ms: None,
+            partitions: None,
+        })
+        .await?;
+
+    let resp: Message = ws_client.recv_json().await?;
+
+    assert!(
+        matches!(resp, Message::CommandResponse(CommandResponse::SubscribeOk { source_id: id }) if id == source_id)
+    );
+
+    Ok(ws_client)
+}
+
+async fn recv_payload(ws_client: &mut WsClient) -> anyhow::Result<serde_json::Value> {
+    let msg: Message =
+        tokio::time::timeout(Duration::from_secs(5), ws_client.recv_json()).await??;
+
+    match msg {
+        Message::Result(SourceResult::Custom { kind, payload, .. }) if kind == "http" => {
+            Ok(payload)
+        }
+        msg => panic!("Expected HTTP result. Received {:?}", msg),
+    }
+}
+
+/// Tests that events published through the server handle are delivered to
+/// subscribers, passing through natively implemented hooks
+#[tokio::test]
+async fn test_embedded_server_with_native_hooks() -> anyhow::Result<()> {
+    let server = KiwiServer::builder()
+        .config(config(vec![http_source("deployments")]))
+        .authenticate(ApiKey)
+        .intercept(Uppercase)
+        .start()
+        .await?;
+
+    assert!(
+        WsClient::connect(&format!("ws://{}/?x-api-key=wrong", server.local_addr()))
+            .await
+            .is_err()
+    );
+
+    let mut ws_client = subscribe(&server, "deployments").await?;
+
+    let publisher = server.publisher("deployments").expect("HTTP source exists");
+    assert_eq!(publisher.publish(vec![b"released".to_vec()]), 1);
+
+    assert_eq!(recv_payload(&mut ws_client).await?, "RELEASED");
+
+    server.shutdown().await?;
+
+    Ok(())
+}
+
+/// Tests that sources of types registered by the embedding application are
+/// built from the configuration
+#[tokio::test]
+async fn test_embedded_server_with_registered_sources() -> anyhow::Result<()> {
+    #[derive(serde::Deserialize)]
+    struct WebhookConfig {
+        token: String,
+    }
+
+    let mut registry = SourceRegistry::new();
+    registry.register("webhook", |id, config: WebhookConfig, channel_capacity| {
+        Ok(Box::new(HttpSource::new(
+            id,
+            vec![config.token],
+            1024,
+            PayloadEncoding::Utf8,
+            channel_capacity,
+        )))
+    })?;
+
+    let server = KiwiServer::builder()
+        .config(config(vec![SourceType::Custom(CustomSourceConfig {
+            kind: "webhook".into(),
+            id: "hooks".into(),
+            channel_capacity: None,
+            config: serde_yaml::from_str("token: s3cr3t")?,
+        })]))
+        .source_registry(registry)
+        .authenticate(ApiKey)
+        .start()
+        .await?;
+
+    let mut ws_client = subscribe(&server, "hooks").await?;
+
+    let publisher = server.publisher("hooks").expect("registered source exists");
+    publisher.publish(vec![b"pushed".to_vec()]);
+
+    assert_eq!(recv_payload(&mut ws_client).await?, "pushed");
+
+    Ok(())
+}
+
+/// Tests that sources built by the embedding application are served alongside
+/// the configured ones, and can be published into through the server handle
+#[tokio::test]
+async fn test_embedded_server_with_added_sources() -> anyhow::Result<()> {
+    let server = KiwiServer::builder()
+        .config(config(vec![http_source("deployments")]))
+        .source(HttpSource::new(
+            "releases".into(),
+            vec!["s3cr3t".into()],
+            1024,
+            PayloadEncoding::Utf8,
+            100,
+        ))
+        .authenticate(ApiKey)
+        .start()
+        .await?;
+
+    let mut ws_client = subscribe(&server, "releases").await?;
+
+    let publisher = server
+        .with_source("releases", HttpSource::publisher)
+        .expect("added source exists");
+    publisher.publish(vec![b"v1.0.0".to_vec()]);
+
+    assert_eq!(recv_payload(&mut ws_client).await?, "v1.0.0");
+
+    // Sources added by the application may not share IDs with configured sources
+    assert!(KiwiServer::builder()
+        .config(config(vec![http_source("releases")]))
+        .source(HttpSource::new(
+            "releases".into(),
+            vec!["s3cr3t".into()],
+            1024,
+            PayloadEncoding::Utf8,
+            100,
+        ))
+        .start()
+        .await
+        .is_err());
+
+    server.shutdown().await?;
+
+    Ok(())
+}
+
+/// Tests that contexts can be constructed to test natively implemented hooks
+#[tokio::test]
+async fn test_native_hooks_with_constructed_contexts() -> anyhow::Result<()> {
+    let context = |kind: &str| {
+        Context::new(
+            None,
+            ConnectionCtx::WebSocket(WebSocketConnectionCtx::new(
+                "127.0.0.1:1234".parse().unwrap(),
+            )),
+            EventCtx::Custom(CustomEventCtx::new(
+                "deployments".into(),
+                kind.into(),
+                b"released".to_vec(),
+                0,
+            )),
+        )
+    };
+
+    assert!(matches!(
+        Uppercase.intercept(&context("http")).await?,
+        Action::Transform(TransformedPayload::Custom(payload)) if payload == b"RELEASED"
+    ));
+    assert!(matches!(
+        Uppercase.intercept(&context("file")).await?,
+        Action::Forward
+    ));
+
+    Ok(())
+}
+
+/// Tests that the server closes connected clients and stops accepting
+/// connections once shut down
+#[tokio::test]
+async fn test_embedded_server_shutdown() -> anyhow::Result<()> {
+    let server = KiwiServer::builder()
+        .config(config(vec![http_source("deployments")]))
+        .start()
+        .await?;
+
+    let addr = server.local_addr();
+
+    let (mut ws_client, _) = WsClient::connect(&format!("ws://{}", addr)).await?;
+
+    tokio::time::timeout(Duration::from_secs(5), server.shutdown()).await??;
+
+    // The client is sent a close frame rather than being left connected
+    assert!(matches!(
+        tokio::time::timeout(Duration::from_secs(5), ws_client.recv_text_frame()).await,
+        Ok(Err(_))
+    ));
+
+    assert!(WsClient::connect(&format!("ws://{}", addr)).await.is_err());
+
+    Ok(())
+}
+
+/// Tests that connections which were not upgraded to WebSocket connections do
+/// not keep the server from shutting down
+#[tokio::test]
+async fn test_embedded_server_shutdown_after_http_requests() -> anyhow::Result<()> {
+    let server = KiwiServer::builder()
+        .config(config(vec![http_source("deployments")]))
+        .start()
+        .await?;
+
+    let addr = server.local_addr();
+
+    let response = reqwest::get(format!("http://{}/health", addr)).await?;
+    assert_eq!(response.status(), 200);
+
+    let response = reqwest::Client::new()
+        .post(format!("http://{}/sources/deployments/events", addr))
+        .bearer_auth("s3cr3t")
+        .body("released")
+        .send()
+        .await?;
+    assert_eq!(response.status(), 202);
+
+    tokio::time::timeout(Duration::from_secs(5), server.shutdown()).await??;
+
+    Ok(())
+}